@@ -0,0 +1,89 @@
+use std::thread::JoinHandle;
+
+use crate::{config, event_sound, events, finished_message, format_remaining, hook, notify, quiet, relay, sound, terminal, timer::Timer, webhook, Options, Running};
+
+/// Tell the webhook and event pipe, if set up, about `event` happening to `running`.
+pub fn send_event(running: &Running, event: webhook::Event, options: &Options) {
+  if options.webhook.is_none() && options.event_pipe.is_none() && options.exec.is_none() && options.relay.is_none() {
+    return;
+  }
+
+  let payload = webhook::Payload {
+    event,
+    label: &running.label,
+    stage: &running.timer.stage().name,
+    duration: running.timer.duration().as_secs_f64(),
+    remaining: running.timer.remaining().as_secs_f64(),
+    started_at: running.started_at,
+    timestamp: chrono::Local::now(),
+  };
+
+  if let Some(path) = &options.event_pipe {
+    events::write(path, &payload.to_json());
+  }
+
+  if let Some(address) = options.relay.as_ref().filter(|_| matches!(event, webhook::Event::Finish | webhook::Event::Cancel)) {
+    relay::send(address, &payload.to_json());
+  }
+
+  if let Some(command) = options.exec.as_ref().filter(|_| routed(event, config::Alert::Exec, true, options)) {
+    hook::run(command, &payload);
+  }
+
+  let Some(url) = options.webhook.as_ref().filter(|_| routed(event, config::Alert::Webhook, event.is_posted(), options)) else {
+    return;
+  };
+
+  match &options.webhook_body {
+    Some(template) => webhook::post_with_headers(url, &payload.fill(template, webhook::sends_json(&options.webhook_headers)), &options.webhook_headers),
+    None => webhook::post_with_headers(url, &payload.to_json(), &options.webhook_headers),
+  }
+}
+
+/// Check whether `event` goes to `alert`, which it does by `default` unless the config routes it elsewhere.
+pub fn routed(event: webhook::Event, alert: config::Alert, default: bool, options: &Options) -> bool {
+  options.routes.get(event).map_or(default, |alerts| alerts.contains(&alert))
+}
+
+/// Ring, notify, or play a sound for `event` wherever the config routes it, as nothing does by default.
+///
+/// Used for the events that have no alerts of their own, returning what's still playing.
+pub fn alert(running: &Running, event: webhook::Event, interactive: bool, options: &Options) -> Vec<JoinHandle<()>> {
+  let mut playing = Vec::new();
+
+  if quiet(options) {
+    return playing;
+  }
+
+  if routed(event, config::Alert::Notify, false, options) {
+    notify::send(&running.notification, &event_message(&running.timer, event));
+  }
+
+  // every timer starts at once, so the start sound is played for all of them together
+  if event != webhook::Event::Start && routed(event, config::Alert::Sound, false, options) {
+    if let Some(sound) = event_sound(running, sound::Event::StageEnd, options) {
+      playing.extend(sound::play(&sound, running.volume.unwrap_or(options.volume)));
+    }
+  }
+
+  if interactive && routed(event, config::Alert::Bell, false, options) {
+    playing.push(terminal::ring(running.bell.as_ref().unwrap_or(&options.bell)));
+  }
+
+  playing
+}
+
+/// Get the notification body saying `event` happened to `timer`.
+fn event_message(timer: &Timer, event: webhook::Event) -> String {
+  let remaining = format_remaining(timer.remaining().as_secs_f64());
+
+  match event {
+    webhook::Event::Start => format!("Started for {}", format_remaining(timer.duration().as_secs_f64())),
+    webhook::Event::Pause => format!("Paused with {} left", remaining),
+    webhook::Event::Resume => format!("Resumed with {} left", remaining),
+    webhook::Event::Extend => format!("Extended, {} left", remaining),
+    webhook::Event::Stage => format!("Starting {}", timer.stage().name),
+    webhook::Event::Finish => finished_message(timer),
+    webhook::Event::Cancel => format!("Cancelled with {} left", remaining),
+  }
+}
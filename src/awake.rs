@@ -0,0 +1,77 @@
+#[cfg(not(windows))]
+use std::process::{Child, Command, Stdio};
+
+/// Keeps the machine from going to sleep until dropped.
+pub struct Inhibitor {
+  #[cfg(not(windows))]
+  child: Child,
+}
+
+/// Stop the machine from sleeping or idling while a timer runs, so it's awake to sound the alarm.
+///
+/// Returns `None` when the platform's inhibitor couldn't be taken.
+#[cfg(not(windows))]
+pub fn inhibit() -> Option<Inhibitor> {
+  // the inhibitor lasts as long as the command it runs, which is killed when dropped
+  #[cfg(target_os = "macos")]
+  let mut command = {
+    // caffeinate takes an IOPMAssertion, and lets it go by itself should timr be killed
+    let mut caffeinate = Command::new("caffeinate");
+    caffeinate.args(["-i", "-w", &std::process::id().to_string()]);
+    caffeinate
+  };
+
+  #[cfg(not(target_os = "macos"))]
+  let mut command = {
+    // waiting on timr's pid lets the inhibitor go by itself should timr be killed
+    let mut systemd_inhibit = Command::new("systemd-inhibit");
+    systemd_inhibit
+      .args([
+        "--what=sleep:idle",
+        concat!("--who=", env!("CARGO_PKG_NAME")),
+        "--why=Timer running",
+        "--mode=block",
+        "tail",
+      ])
+      .arg(format!("--pid={}", std::process::id()))
+      .args(["-f", "/dev/null"]);
+    systemd_inhibit
+  };
+
+  match command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+    Ok(child) => Some(Inhibitor { child }),
+    Err(error) => {
+      eprintln!("Failed to keep the system awake: {}", error);
+      None
+    }
+  }
+}
+
+/// Stop the machine from sleeping or idling while a timer runs, so it's awake to sound the alarm.
+#[cfg(windows)]
+pub fn inhibit() -> Option<Inhibitor> {
+  use windows_sys::Win32::System::Power::{SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED};
+
+  // lasts until cleared from the same thread, or timr exits
+  if unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) } == 0 {
+    eprintln!("Failed to keep the system awake");
+    return None;
+  }
+
+  Some(Inhibitor {})
+}
+
+impl Drop for Inhibitor {
+  fn drop(&mut self) {
+    #[cfg(not(windows))]
+    {
+      let _ = self.child.kill();
+      let _ = self.child.wait();
+    }
+
+    #[cfg(windows)]
+    unsafe {
+      windows_sys::Win32::System::Power::SetThreadExecutionState(windows_sys::Win32::System::Power::ES_CONTINUOUS);
+    }
+  }
+}
@@ -0,0 +1,180 @@
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, BufRead, BufReader, Write},
+  path::PathBuf,
+  process::{exit, Command, Stdio},
+  thread::sleep,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  data_dir,
+  json::{self, Value},
+  lock, serve,
+};
+
+/// How long to wait for a daemon that was just started to start listening.
+const STARTUP: Duration = Duration::from_secs(3);
+/// How often to try connecting while it starts.
+const RETRY: Duration = Duration::from_millis(50);
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type Stream = std::fs::File;
+
+/// A connection to the daemon, `timr serve`, which sends requests and waits for their responses.
+pub struct Connection {
+  reader: BufReader<Stream>,
+  writer: Stream,
+  next_id: u64,
+  /// Process the daemon runs as, so other commands can leave it to the connection.
+  pub pid: i32,
+}
+
+impl Connection {
+  /// Connect to the daemon if one is running, checking it speaks the same protocol.
+  pub fn open() -> io::Result<Self> {
+    #[cfg(unix)]
+    let writer = Stream::connect(serve::socket_path())?;
+    #[cfg(windows)]
+    let writer = std::fs::OpenOptions::new().read(true).write(true).open(serve::PIPE)?;
+
+    // a daemon that's stuck still takes the connection, but never answers
+    #[cfg(unix)]
+    writer.set_read_timeout(Some(STARTUP))?;
+
+    let mut connection = Self {
+      reader: BufReader::new(writer.try_clone()?),
+      writer,
+      next_id: 1,
+      pid: 0,
+    };
+
+    let version = connection
+      .call("version", Value::Object(Vec::new()))
+      .map_err(|error| io::Error::new(io::ErrorKind::TimedOut, error))?;
+
+    #[cfg(unix)]
+    connection.writer.set_read_timeout(None)?;
+    let protocol = version.get("protocol").and_then(Value::as_f64).unwrap_or(0.0) as u32;
+
+    if protocol != serve::PROTOCOL {
+      return Err(io::Error::other(format!(
+        "the daemon speaks protocol {} where this timr speaks {}, so it needs replacing with timr serve --takeover",
+        protocol,
+        serve::PROTOCOL
+      )));
+    }
+
+    connection.pid = version.get("pid").and_then(Value::as_f64).unwrap_or(0.0) as i32;
+
+    Ok(connection)
+  }
+
+  /// Call `method` with `params`, getting its result or the error the daemon gave.
+  pub fn call(&mut self, method: &str, params: Value) -> Result<Value, String> {
+    let id = self.next_id;
+    self.next_id += 1;
+
+    let request = Value::object([("jsonrpc", "2.0".into()), ("id", (id as f64).into()), ("method", method.into()), ("params", params)]);
+
+    writeln!(self.writer, "{}", request).and_then(|_| self.writer.flush()).map_err(|error| error.to_string())?;
+
+    loop {
+      let response = self.next()?;
+
+      // notifications the connection is subscribed to can come first
+      if response.get("id").and_then(Value::as_f64) != Some(id as f64) {
+        continue;
+      }
+
+      if let Some(error) = response.get("error") {
+        return Err(error.get("message").and_then(Value::as_str).unwrap_or("Unknown error").to_string());
+      }
+
+      return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+    }
+  }
+
+  /// Wait for the next message from the daemon.
+  pub fn next(&mut self) -> Result<Value, String> {
+    let mut line = String::new();
+
+    match self.reader.read_line(&mut line) {
+      Ok(0) => Err("The daemon closed the connection".to_string()),
+      Ok(_) => json::parse(&line),
+      Err(error) => Err(error.to_string()),
+    }
+  }
+}
+
+/// Connect to the daemon, starting one in the background if none is running, exiting with an error if it can't be reached.
+pub fn daemon() -> Connection {
+  match Connection::open() {
+    Ok(connection) => return connection,
+    Err(error) if error.kind() == io::ErrorKind::TimedOut => unanswered(error),
+    Err(error) if error.kind() == io::ErrorKind::Other => {
+      eprintln!("Failed to talk to the daemon: {}", error);
+      exit(1);
+    }
+    Err(_) => {}
+  }
+
+  let exe = std::env::current_exe().unwrap_or_else(|error| {
+    eprintln!("Failed to find timr: {}", error);
+    exit(1);
+  });
+
+  let mut command = Command::new(exe);
+  command.arg("serve");
+
+  if let Err(error) = detached(&mut command).spawn() {
+    eprintln!("Failed to start the daemon: {}", error);
+    exit(1);
+  }
+
+  let started = Instant::now();
+
+  loop {
+    match Connection::open() {
+      Ok(connection) => return connection,
+      Err(error) if error.kind() == io::ErrorKind::TimedOut || started.elapsed() >= STARTUP => unanswered(error),
+      Err(_) => sleep(RETRY),
+    }
+  }
+}
+
+/// Exit with an error for the daemon not answering, saying which process it is when another holds the lock.
+fn unanswered(error: io::Error) -> ! {
+  match lock::holder(&lock::path_for(&serve::socket_path())) {
+    Some(pid) => eprintln!("The daemon (pid {}) isn't answering, replace it with timr serve --takeover", pid),
+    None => eprintln!("Failed to talk to the daemon: {}", error),
+  }
+
+  exit(1);
+}
+
+/// Get the file a daemon started in the background writes its errors to, with no terminal to show them.
+fn log_path() -> PathBuf {
+  data_dir().join("daemon.log")
+}
+
+/// Set `command` up to run on its own in the background, without the terminal, carrying on once timr exits.
+///
+/// Its errors are added to the end of `log_path`, or dropped if that can't be opened.
+pub fn detached(command: &mut Command) -> &mut Command {
+  let log = fs::create_dir_all(data_dir()).and_then(|_| OpenOptions::new().create(true).append(true).open(log_path()));
+
+  command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(log.map_or_else(|_| Stdio::null(), Stdio::from));
+
+  // in a group of its own, so it isn't stopped along with the shell's jobs
+  #[cfg(unix)]
+  std::os::unix::process::CommandExt::process_group(command, 0);
+
+  // DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP, leaving the console behind
+  #[cfg(windows)]
+  std::os::windows::process::CommandExt::creation_flags(command, 0x8 | 0x200);
+
+  command
+}
@@ -0,0 +1,34 @@
+use std::{
+  io::Write,
+  process::{Command, Stdio},
+};
+
+/// Put `text` on the system clipboard with the platform's clipboard tool, returning whether one took it.
+pub fn copy(text: &str) -> bool {
+  tools().iter().any(|(program, args)| pipe(program, args, text))
+}
+
+/// Get the clipboard tools to try in turn, with their arguments.
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+  if cfg!(target_os = "macos") {
+    vec![("pbcopy", &[])]
+  } else if cfg!(windows) {
+    vec![("clip", &[])]
+  } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+    vec![("wl-copy", &[])]
+  } else {
+    vec![("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+  }
+}
+
+/// Run `program` with `text` on its standard input, returning whether it succeeded.
+fn pipe(program: &str, args: &[&str], text: &str) -> bool {
+  let Ok(mut child) = Command::new(program).args(args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else {
+    return false;
+  };
+
+  // the stdin is dropped once written, so the tool knows it has everything
+  let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+
+  child.wait().is_ok_and(|status| status.success()) && written
+}
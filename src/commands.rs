@@ -0,0 +1,1297 @@
+use std::{
+  io::{stdout, IsTerminal},
+  process::exit,
+  time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{client, config, export, format_remaining, format_short, history, json::Value, next_value, parse_duration, parse_value, state, stats, sync, terminal};
+
+const TMUX_ICON: &str = "⏳";
+const TMUX_PAUSED_ICON: &str = "⏸";
+
+/// Colors status bars show the timer in, running normally, paused, and in its last minute.
+const RUNNING_COLOR: &str = "#50fa7b";
+const PAUSED_COLOR: &str = "#f1fa8c";
+const ENDING_COLOR: &str = "#ff5555";
+
+/// How long before the end a timer is shown as ending, in seconds.
+const ENDING_AFTER: f64 = 60.0;
+/// Widest the bars in `timr report` are drawn.
+const REPORT_WIDTH: usize = 50;
+
+/// Status bar to format the timer for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFormat {
+  Polybar,
+  /// i3blocks with `markup=pango` set on the block.
+  I3blocks,
+}
+
+impl std::str::FromStr for StatusFormat {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "polybar" => Ok(Self::Polybar),
+      "i3blocks" => Ok(Self::I3blocks),
+      _ => Err(format!("Invalid status bar format: {}", value)),
+    }
+  }
+}
+
+/// Timer status bars and prompts show when several are running and none is pinned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Primary {
+  /// The timer ending soonest, preferring those still running.
+  #[default]
+  Soonest,
+  /// The timer started most recently.
+  Recent,
+}
+
+/// Narrows down the timers a command applies to, beyond picking one by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Filter {
+  /// Only timers started at least this long ago.
+  pub older_than: Option<Duration>,
+  /// Only paused timers.
+  pub paused: bool,
+}
+
+impl Filter {
+  /// Check whether a timer that's `paused` or not, started at `started_at` in seconds since the Unix epoch, gets through.
+  fn allows(&self, paused: bool, started_at: f64) -> bool {
+    (paused || !self.paused) && self.older_than.is_none_or(|age| state::now() - started_at >= age.as_secs_f64())
+  }
+
+  /// Check whether the timer in the snapshot `timer` gets through.
+  fn allows_state(&self, timer: &state::TimerState) -> bool {
+    self.allows(timer.paused, timer.started_at)
+  }
+}
+
+/// Print the primary timer as a short snippet for tmux's `status-right`, or nothing when no timer is running.
+///
+/// The time remaining is worked out afresh on every call, so it stays current however often tmux polls.
+pub fn tmux_status() {
+  let timers = state::load();
+
+  let Some(timer) = shown(&timers) else {
+    return;
+  };
+
+  println!("{}", status_text(timer));
+}
+
+/// Print the primary timer as a colored line for polybar or i3blocks, or an empty line when no timer is running.
+///
+/// Like `tmux_status`, the time remaining is worked out afresh on every call.
+pub fn statusbar(format: StatusFormat) {
+  let timers = state::load();
+
+  // an empty line clears the block, where printing nothing would leave the last timer up
+  let Some(timer) = shown(&timers) else {
+    println!();
+    return;
+  };
+
+  println!("{}", statusbar_line(timer, format));
+}
+
+/// Print the primary timer as a tiny segment for a shell prompt, like `⏳12m`, or nothing when no timer is running.
+///
+/// Only the state files and config are read, so it's quick enough to run on every prompt.
+pub fn prompt() {
+  if let Some(timer) = shown(&state::load()) {
+    print!("{}", prompt_segment(timer));
+  }
+}
+
+/// Print every timer as an xbar or SwiftBar plugin: the primary one in the menu bar, and all of them in its menu along with buttons to control them.
+pub fn menubar() {
+  let timers = state::load();
+
+  // the menu's buttons run this same timr, as plugins don't get the user's PATH
+  let exe = std::env::current_exe()
+    .map(|exe| exe.display().to_string())
+    .unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string());
+
+  print!("{}", menubar_text(&timers, shown(&timers), &exe));
+}
+
+/// Ask every running timer `filter` lets through to `control` itself, or only the one called `name`, returning whether there were any.
+///
+/// Timers running in a terminal all go along with any one of them that's picked. A `reason` for cancelling is kept in the history.
+pub fn control(control: state::Control, name: Option<&str>, filter: Filter, reason: Option<&str>) -> bool {
+  let mut controlled = 0;
+  let mut daemon_pid = None;
+
+  // timers on the daemon are controlled one by one, where the others all go at once
+  if let Ok(mut daemon) = client::Connection::open() {
+    daemon_pid = Some(daemon.pid);
+
+    for id in daemon_ids(&mut daemon, name, filter) {
+      let mut params = vec![("id".to_string(), id)];
+
+      if let Some(reason) = reason {
+        params.push(("reason".to_string(), reason.into()));
+      }
+
+      if daemon.call(control.name(), Value::Object(params)).is_ok() {
+        controlled += 1;
+      }
+    }
+  }
+
+  let picked = |timer: &state::TimerState| name.is_none_or(|name| timer.label == name) && filter.allows_state(timer);
+  let everything = name.is_none() && filter == Filter::default();
+  controlled += state::send(control, reason, (!everything).then_some(&picked as _), daemon_pid);
+
+  controlled > 0
+}
+
+/// Extend the timers on the daemon, or only the one called `name`, by `by`, exiting with an error when there are none.
+pub fn extend(name: Option<&str>, by: &str) {
+  let missing = || match name {
+    Some(name) => format!("No timer named {}", name),
+    None => "No timers running in the background".to_string(),
+  };
+
+  let Ok(mut daemon) = client::Connection::open() else {
+    eprintln!("{}", missing());
+    exit(1);
+  };
+
+  let ids = daemon_ids(&mut daemon, name, Filter::default());
+
+  if ids.is_empty() {
+    eprintln!("{}", missing());
+    exit(1);
+  }
+
+  for id in ids {
+    match daemon.call("extend", Value::object([("id", id), ("by", by.into())])) {
+      Ok(timer) => println!(
+        "Extended {} to {} left",
+        timer.get("label").and_then(Value::as_str).unwrap_or_default(),
+        format_remaining(timer.get("remaining").and_then(Value::as_f64).unwrap_or_default())
+      ),
+      Err(error) => {
+        eprintln!("Failed to extend the timer: {}", error);
+        exit(1);
+      }
+    }
+  }
+}
+
+/// Get the ids of every timer on `daemon` that `filter` lets through, or only the one called `name`, which can also be its id when no timer goes by it.
+fn daemon_ids(daemon: &mut client::Connection, name: Option<&str>, filter: Filter) -> Vec<Value> {
+  let timers = match daemon.call("query", Value::Object(Vec::new())) {
+    Ok(Value::Array(timers)) => timers,
+    _ => Vec::new(),
+  };
+
+  let named = |timer: &&Value, name: &str| timer.get("label").and_then(Value::as_str) == Some(name);
+  let numbered = |timer: &&Value, name: &str| timer.get("id").and_then(Value::as_f64).map(|id| id.to_string()).as_deref() == Some(name);
+
+  let picked = match name {
+    Some(name) if timers.iter().any(|timer| named(&timer, name)) => timers.iter().filter(|timer| named(timer, name)).collect(),
+    Some(name) => timers.iter().filter(|timer| numbered(timer, name)).collect(),
+    None => timers.iter().collect::<Vec<_>>(),
+  };
+
+  picked
+    .into_iter()
+    .filter(|timer| {
+      let paused = timer.get("paused") == Some(&Value::Bool(true));
+      filter.allows(paused, timer.get("started_at").and_then(Value::as_f64).unwrap_or_default())
+    })
+    .filter_map(|timer| timer.get("id").cloned())
+    .collect()
+}
+
+/// Start timers on the daemon from `args`, each a duration or profile, so they run without tying up a terminal.
+pub fn start(args: &[String]) {
+  let mut durations = Vec::new();
+  let mut name = None;
+  let mut exec = None;
+  let mut tags = Vec::new();
+  let mut env = config::load().exec_env.unwrap_or_else(|| vec!["PATH".to_string()]);
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--name" => name = Some(next_value(&mut args, arg)),
+      "--exec" => exec = Some(next_value(&mut args, arg)),
+      "--tag" => tags.push(Value::from(next_value(&mut args, arg))),
+      "--env" => env.push(next_value(&mut args, arg).to_string()),
+      _ if arg.starts_with('-') && arg.len() > 1 => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+      _ => durations.push(arg.as_str()),
+    }
+  }
+
+  if durations.is_empty() {
+    eprintln!("No duration specified");
+    exit(1);
+  }
+
+  if name.is_some() && durations.len() > 1 {
+    eprintln!("Only a single timer can be named");
+    exit(1);
+  }
+
+  let origin = origin(exec, &env);
+  let mut daemon = client::daemon();
+
+  for duration in durations {
+    let what = if duration.starts_with(|c: char| c.is_ascii_digit()) { "duration" } else { "profile" };
+    let mut params = vec![(what.to_string(), Value::from(duration))];
+
+    if let Some(name) = name {
+      params.push(("label".to_string(), name.into()));
+    }
+
+    if !tags.is_empty() {
+      params.push(("tags".to_string(), Value::Array(tags.clone())));
+    }
+
+    params.extend(origin.iter().cloned());
+
+    match daemon.call("start", Value::Object(params)) {
+      Ok(timer) => println!(
+        "Started {} (#{}) in the background",
+        timer.get("label").and_then(Value::as_str).unwrap_or(duration),
+        timer.get("id").and_then(Value::as_f64).unwrap_or_default()
+      ),
+      Err(error) => {
+        eprintln!("Failed to start {}: {}", duration, error);
+        exit(1);
+      }
+    }
+  }
+}
+
+/// Get the params telling the daemon where timers are being started from, so their hook, `exec` if given, runs here with the variables named in `env`.
+fn origin(exec: Option<&str>, env: &[String]) -> Vec<(String, Value)> {
+  let mut params = Vec::new();
+
+  if let Some(exec) = exec {
+    params.push(("exec".to_string(), exec.into()));
+  }
+
+  if let Ok(dir) = std::env::current_dir() {
+    params.push(("cwd".to_string(), dir.to_string_lossy().as_ref().into()));
+  }
+
+  let env = env
+    .iter()
+    .filter_map(|name| Some((name.clone(), Value::from(std::env::var(name).ok()?.as_str()))))
+    .collect();
+
+  params.push(("env".to_string(), Value::Object(env)));
+  params
+}
+
+/// Print every running timer `filter` lets through along with the time it has left.
+///
+/// Timers on the daemon that ended lately are listed after them, saying how they ended, unless filtering.
+pub fn list(filter: Filter) {
+  let timers = state::load();
+  let ended = if filter == Filter::default() { state::load_ended() } else { Vec::new() };
+
+  if timers.is_empty() && ended.is_empty() {
+    println!("No timers running");
+    return;
+  }
+
+  let timers = timers.into_iter().filter(|timer| filter.allows_state(timer)).collect::<Vec<_>>();
+
+  if timers.is_empty() && ended.is_empty() {
+    println!("No timers match");
+    return;
+  }
+
+  print!("{}{}", list_text(&timers), ended_text(&ended));
+}
+
+/// Print the label, state, time remaining, and percent done of every running timer, as JSON if `json`, exiting with an error when there are none.
+///
+/// Only the state files are read, so it's cheap enough to poll.
+pub fn status(json: bool) {
+  let timers = state::load();
+
+  if json {
+    println!("{}", status_json(&timers));
+  } else {
+    print!("{}", status_lines(&timers));
+  }
+
+  if timers.is_empty() {
+    exit(1);
+  }
+}
+
+/// Print every event from the daemon as it happens, as the JSON-RPC notifications themselves if `json`, until interrupted.
+///
+/// Ticks, sent every second, are only shown with `ticks`.
+pub fn watch(json: bool, ticks: bool) {
+  let Ok(mut daemon) = client::Connection::open() else {
+    eprintln!("The daemon isn't running, start it with timr serve");
+    exit(1);
+  };
+
+  let events = ["tick", "stage", "finished", "pause", "resume", "cancelled", "started", "extended"]
+    .into_iter()
+    .filter(|&event| ticks || event != "tick")
+    .map(Value::from)
+    .collect();
+
+  if let Err(error) = daemon.call("subscribe", Value::object([("events", Value::Array(events))])) {
+    eprintln!("Failed to watch the daemon: {}", error);
+    exit(1);
+  }
+
+  loop {
+    let message = match daemon.next() {
+      Ok(message) => message,
+      Err(_) => {
+        eprintln!("The daemon stopped");
+        exit(1);
+      }
+    };
+
+    if json {
+      println!("{}", message);
+    } else if let Some(text) = event_text(&message) {
+      println!("{}  {}", chrono::Local::now().format("%H:%M:%S"), text);
+    }
+  }
+}
+
+/// Print every session in the history in `format`.
+pub fn export(format: export::Format) {
+  let config = config::load();
+
+  print!("{}", export::export(&history::load(config.history_backend()), format, &toggl_email(&config)));
+}
+
+/// Get the email for the Toggl export from `config`, empty if it isn't set.
+fn toggl_email(config: &config::Config) -> String {
+  config.toggl.as_ref().and_then(|toggl| toggl.email.clone()).unwrap_or_default()
+}
+
+/// Print the sessions in the history picked out by `args`, as a table with the total time at the bottom.
+///
+/// With `export` first, they're printed in the `--format` given instead.
+pub fn history(args: &[String]) {
+  if args.first().is_some_and(|arg| arg == "prune") {
+    prune(&args[1..]);
+    return;
+  }
+
+  if args.first().is_some_and(|arg| arg == "sync") {
+    sync(&args[1..]);
+    return;
+  }
+
+  let exporting = args.first().is_some_and(|arg| arg == "export");
+  let mut query = history::Query::default();
+  let mut format = None;
+  let mut args = args[usize::from(exporting)..].iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--format" if exporting => format = Some(parse_value::<export::Format>(next_value(&mut args, arg))),
+      "--today" => query.since = Some(history::start_of_day(chrono::Local::now())),
+      "--since" => match history::parse_since(next_value(&mut args, arg), chrono::Local::now()) {
+        Ok(since) => query.since = Some(since),
+        Err(error) => {
+          eprintln!("{}", error);
+          exit(1);
+        }
+      },
+      "--profile" => query.profile = Some(next_value(&mut args, arg).to_string()),
+      "--tag" => query.tag = Some(next_value(&mut args, arg).to_string()),
+      _ => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+  }
+
+  // numbered by where they are in the whole history, so the numbers stay the same however they're filtered
+  let config = config::load();
+  let sessions = history::query(config.history_backend(), &query);
+
+  if exporting {
+    let sessions = sessions.into_iter().map(|(_, session)| session).collect::<Vec<_>>();
+
+    let Some(format) = format else {
+      eprintln!("Missing --format for history export, one of csv, json, ics, timewarrior, or toggl");
+      exit(1);
+    };
+
+    print!("{}", export::export(&sessions, format, &toggl_email(&config)));
+    return;
+  }
+
+  if sessions.is_empty() {
+    println!("No sessions in the history match");
+    return;
+  }
+
+  print!("{}", history_text(&sessions));
+}
+
+/// Let go of old sessions in the history, those past the retention or beyond the most entries in the config, or given by `--older-than` and `--keep` in `args`.
+fn prune(args: &[String]) {
+  let config = config::load();
+  let limits = config.history.clone().unwrap_or_default();
+  let mut retention = limits.retention.as_deref().map(parse_duration);
+  let mut max_entries = limits.max_entries;
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--older-than" => retention = Some(parse_duration(next_value(&mut args, arg))),
+      "--keep" => match next_value(&mut args, arg).parse() {
+        Ok(keep) => max_entries = Some(keep),
+        Err(_) => {
+          eprintln!("Invalid number of sessions to keep");
+          exit(1);
+        }
+      },
+      _ => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+  }
+
+  if retention.is_none() && max_entries.is_none() {
+    eprintln!("Nothing to prune by, set retention or max_entries under [history] in the config, or use --older-than or --keep");
+    exit(1);
+  }
+
+  match history::prune(config.history_backend(), retention, max_entries) {
+    Ok(0) => println!("Nothing to prune"),
+    Ok(1) => println!("Pruned 1 session from the history"),
+    Ok(removed) => println!("Pruned {} sessions from the history", removed),
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
+
+/// Attach a note to a session in the history saying what was done, the one numbered first in `args` or else the last, taking it back off when empty.
+pub fn note(args: &[String]) {
+  let (number, text) = match args {
+    [text] => (None, text),
+    [number, text] => match number.trim_start_matches('#').parse::<usize>() {
+      Ok(number) => (Some(number), text),
+      Err(_) => {
+        eprintln!("Invalid session number: {}", number);
+        exit(1);
+      }
+    },
+    _ => {
+      eprintln!("timr note takes the note, after the number of the session from timr history if not the last one");
+      exit(1);
+    }
+  };
+
+  let text = text.trim();
+
+  let (noted, label) = match history::note(config::load().history_backend(), number, text) {
+    Ok(Some(noted)) => noted,
+    Ok(None) => {
+      match number {
+        Some(number) => eprintln!("No session #{} in the history", number),
+        None => eprintln!("No sessions in the history yet"),
+      }
+
+      exit(1);
+    }
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  };
+
+  if text.is_empty() {
+    println!("Took the note off {} (#{})", label, noted);
+  } else {
+    println!("Added a note to {} (#{})", label, noted);
+  }
+}
+
+/// Sync the history with the remote in the config, saying how many sessions went each way.
+fn sync(args: &[String]) {
+  if let Some(arg) = args.first() {
+    eprintln!("Unknown option: {}", arg);
+    exit(1);
+  }
+
+  let config = config::load();
+
+  let Some(remote) = &config.sync else {
+    eprintln!("Set remote under [sync] in the config to sync the history with");
+    exit(1);
+  };
+
+  let sessions = |count| match count {
+    1 => "1 session".to_string(),
+    count => format!("{} sessions", count),
+  };
+
+  match sync::run(remote, config.history_backend()) {
+    Ok(synced) => println!(
+      "Synced the history with {}, bringing in {} and sending {}",
+      remote.remote,
+      sessions(synced.pulled),
+      sessions(synced.pushed)
+    ),
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
+
+/// Print totals, the completion rate, and tables by profile and by day for the sessions in the period given in `args`, the last week unless told otherwise.
+pub fn stats(args: &[String]) {
+  let today = chrono::Local::now().date_naive();
+  let mut from = today - chrono::Days::new(6);
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match period(arg, &mut args) {
+      Some(start) => from = start,
+      None => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+  }
+
+  let config = config::load();
+  let sessions = history::load(config.history_backend());
+  let goal = config.pomodoro.as_ref().and_then(|pomodoro| pomodoro.goal);
+  let pomodoros = stats::pomodoros(&sessions, &stats::pomodoro_profile(&config), goal, today);
+
+  print!("{}", stats::text(&sessions, from, today, &pomodoros));
+}
+
+/// Chart the time on timers each day in the period given in `args`, the last week unless told otherwise, colored by tag or by profile with `--by`.
+pub fn report(args: &[String]) {
+  let today = chrono::Local::now().date_naive();
+  let mut from = today - chrono::Days::new(6);
+  let mut group = stats::Group::Tag;
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    if arg == "--by" {
+      group = parse_value(next_value(&mut args, arg));
+      continue;
+    }
+
+    match period(arg, &mut args) {
+      Some(start) => from = start,
+      None => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+  }
+
+  // the date and the time on the day either side of the bar, with a spare column so lines never wrap
+  let width = (terminal::get_width() as usize).saturating_sub(29).min(REPORT_WIDTH);
+
+  print!(
+    "{}",
+    stats::report(&history::load(config::load().history_backend()), from, today, group, width, stdout().is_terminal())
+  );
+}
+
+/// Get the day the period `arg` starts on, taking its value from `args` if it needs one, or nothing if it isn't a period.
+fn period<'a>(arg: &str, args: &mut impl Iterator<Item = &'a String>) -> Option<chrono::NaiveDate> {
+  let now = chrono::Local::now();
+  let today = now.date_naive();
+
+  match arg {
+    "--today" => Some(today),
+    "--week" => Some(today - chrono::Days::new(6)),
+    "--month" => Some(today - chrono::Days::new(29)),
+    "--since" => match history::parse_since(next_value(args, arg), now) {
+      Ok(since) => Some(
+        chrono::DateTime::from_timestamp(since as i64, 0)
+          .unwrap_or_default()
+          .with_timezone(&chrono::Local)
+          .date_naive(),
+      ),
+      Err(error) => {
+        eprintln!("{}", error);
+        exit(1);
+      }
+    },
+    _ => None,
+  }
+}
+
+/// Get a table of `sessions`, each with its number, one to a line under a header, followed by how many there were and their time all together.
+fn history_text(sessions: &[(usize, history::Session)]) -> String {
+  let mut rows = vec![["#", "Date", "Start", "Duration", "Label", "Profile", "Tags", "Note"].map(str::to_string)];
+
+  for (number, session) in sessions {
+    let started_at = chrono::DateTime::from_timestamp_millis((session.started_at * 1000.0) as i64)
+      .unwrap_or_default()
+      .with_timezone(&chrono::Local);
+
+    rows.push([
+      number.to_string(),
+      started_at.format("%Y-%m-%d").to_string(),
+      started_at.format("%H:%M").to_string(),
+      format_short(session.duration),
+      if let Some(reason) = session.reason.as_ref().filter(|_| session.cancelled) {
+        format!("{} (cancelled: {})", session.label, reason)
+      } else if session.cancelled {
+        format!("{} (cancelled)", session.label)
+      } else {
+        session.label.clone()
+      },
+      session.profile.clone().unwrap_or_default(),
+      session.tags.join(", "),
+      session.note.clone().unwrap_or_default(),
+    ]);
+  }
+
+  let mut text = columns(&rows);
+
+  let total = sessions.iter().map(|(_, session)| session.duration).sum::<f64>();
+  let count = if sessions.len() == 1 {
+    "1 session".to_string()
+  } else {
+    format!("{} sessions", sessions.len())
+  };
+
+  text.push_str(&format!("\n{}, {} in all\n", count, format_short(total)));
+  text
+}
+
+/// Print every profile in the config with the time it's set for, along with how often it's been run and for how long on average with `--stats`.
+pub fn profiles(args: &[String]) {
+  let mut with_stats = false;
+
+  for arg in args {
+    match arg.as_str() {
+      "list" => {}
+      "--stats" => with_stats = true,
+      _ => {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+  }
+
+  let config = config::load();
+  let backend = config.history_backend();
+
+  let Some(profiles) = config.profiles.filter(|profiles| !profiles.is_empty()) else {
+    println!("No profiles in {}", config::path().display());
+    return;
+  };
+
+  let sessions = if with_stats { history::load(backend) } else { Vec::new() };
+
+  let profiles = profiles
+    .iter()
+    .map(|profile| {
+      let planned = profile.stages().iter().map(|stage| stage.duration.as_secs_f64()).sum();
+      (profile.name.clone(), planned, with_stats.then(|| stats::usage(&sessions, &profile.name)))
+    })
+    .collect::<Vec<_>>();
+
+  print!("{}", profiles_text(&profiles));
+}
+
+/// Get a table of `profiles`, each with the time it's set for and, if given, how much it's been used.
+fn profiles_text(profiles: &[(String, f64, Option<stats::Usage>)]) -> String {
+  let with_stats = profiles.iter().any(|(_, _, usage)| usage.is_some());
+
+  let mut rows = vec![["Profile", "Duration"].map(str::to_string).to_vec()];
+
+  if with_stats {
+    rows[0].extend(["Runs", "Finished", "Average", "vs planned", "Last run"].map(str::to_string));
+  }
+
+  for (name, planned, usage) in profiles {
+    let mut row = vec![name.clone(), format_short(*planned)];
+
+    match usage {
+      Some(usage) if usage.runs > 0 => {
+        let last = chrono::DateTime::from_timestamp_millis((usage.last.unwrap_or_default() * 1000.0) as i64)
+          .unwrap_or_default()
+          .with_timezone(&chrono::Local);
+
+        row.extend([
+          usage.runs.to_string(),
+          format!("{:.0}%", usage.finished as f64 / usage.runs as f64 * 100.0),
+          format_short(usage.average),
+          if *planned > 0.0 {
+            format!("{:+.0}%", (usage.average / planned - 1.0) * 100.0)
+          } else {
+            String::new()
+          },
+          last.format("%Y-%m-%d").to_string(),
+        ]);
+      }
+      Some(_) => row.extend(["0", "", "", "", "never"].map(str::to_string)),
+      None => {}
+    }
+
+    rows.push(row);
+  }
+
+  columns(&rows)
+}
+
+/// Lay out `rows` in columns as wide as their widest cell, two spaces apart.
+fn columns<R: AsRef<[String]>>(rows: &[R]) -> String {
+  let count = rows.iter().map(|row| row.as_ref().len()).max().unwrap_or(0);
+  let widths = (0..count)
+    .map(|column| {
+      rows
+        .iter()
+        .filter_map(|row| row.as_ref().get(column))
+        .map(|cell| terminal::display_width(cell))
+        .max()
+        .unwrap_or(0)
+    })
+    .collect::<Vec<_>>();
+
+  rows
+    .iter()
+    .map(|row| {
+      let cells = row
+        .as_ref()
+        .iter()
+        .zip(&widths)
+        .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - terminal::display_width(cell))));
+
+      format!("{}\n", cells.collect::<Vec<_>>().join("  ").trim_end())
+    })
+    .collect()
+}
+
+/// Describe the event in the notification `message` from the daemon, or nothing if it isn't one.
+fn event_text(message: &Value) -> Option<String> {
+  let params = message.get("params")?;
+  let label = params.get("label").and_then(Value::as_str).unwrap_or_default();
+  let left = || format_remaining(params.get("remaining").and_then(Value::as_f64).unwrap_or_default());
+
+  let text = match message.get("method")?.as_str()? {
+    "tick" => match params.get("timers") {
+      Some(Value::Array(timers)) => timers
+        .iter()
+        .map(|timer| {
+          format!(
+            "{} {}",
+            timer.get("label").and_then(Value::as_str).unwrap_or_default(),
+            format_remaining(timer.get("remaining").and_then(Value::as_f64).unwrap_or_default())
+          )
+        })
+        .collect::<Vec<_>>()
+        .join(", "),
+      _ => return None,
+    },
+    "started" => format!(
+      "{} started for {}",
+      label,
+      format_remaining(params.get("duration").and_then(Value::as_f64).unwrap_or_default())
+    ),
+    "stage" => format!("{} moved on to {}", label, params.get("stage").and_then(Value::as_str).unwrap_or_default()),
+    "pause" => format!("{} paused with {} left", label, left()),
+    "resume" => format!("{} resumed with {} left", label, left()),
+    "extended" => format!("{} extended to {} left", label, left()),
+    "finished" => format!("{} finished", label),
+    "cancelled" => format!("{} was cancelled with {} left", label, left()),
+    _ => return None,
+  };
+
+  Some(text)
+}
+
+/// Pin the timer called `name` for status bars and prompts to show, or with `next`, the one after the timer shown now.
+pub fn pin(name: Option<&str>, next: bool) {
+  let mut timers = state::load();
+
+  let picked = match name {
+    Some(name) => timers.iter().find(|timer| timer.label == name || timer.id.is_some_and(|id| id.to_string() == name)),
+    None if next => {
+      // in the order they were started, going back round to the first after the last
+      timers.sort_by(|a, b| a.started_at.total_cmp(&b.started_at));
+
+      let index = shown(&timers).and_then(|shown| timers.iter().position(|timer| timer.label == shown.label));
+      timers.get(index.map_or(0, |index| (index + 1) % timers.len()))
+    }
+    None => None,
+  };
+
+  let Some(picked) = picked else {
+    match name {
+      Some(name) => eprintln!("No timer named {}", name),
+      None => eprintln!("No timers running"),
+    }
+
+    exit(1);
+  };
+
+  if let Err(error) = state::pin(Some(&picked.label)) {
+    eprintln!("Failed to pin the timer: {}", error);
+    exit(1);
+  }
+
+  println!("Pinned {}", picked.label);
+}
+
+/// Stop pinning a timer, going back to showing the primary one.
+pub fn unpin() {
+  if let Err(error) = state::pin(None) {
+    eprintln!("Failed to unpin the timer: {}", error);
+    exit(1);
+  }
+}
+
+/// Get the timer status bars and prompts show out of `timers`: the one pinned, or else the primary one as the config picks it.
+fn shown(timers: &[state::TimerState]) -> Option<&state::TimerState> {
+  primary(timers, config::load().primary.unwrap_or_default(), state::pinned().as_deref())
+}
+
+/// Get the timer called `pinned` if it's running, or else the one `primary` picks.
+fn primary<'a>(timers: &'a [state::TimerState], primary: Primary, pinned: Option<&str>) -> Option<&'a state::TimerState> {
+  if let Some(timer) = pinned.and_then(|pinned| timers.iter().find(|timer| timer.label == pinned)) {
+    return Some(timer);
+  }
+
+  match primary {
+    Primary::Soonest => soonest(timers),
+    Primary::Recent => timers.iter().max_by(|a, b| a.started_at.total_cmp(&b.started_at)),
+  }
+}
+
+/// Get the running timer that ends soonest, or a paused one if none are running.
+fn soonest(timers: &[state::TimerState]) -> Option<&state::TimerState> {
+  timers
+    .iter()
+    .min_by(|a, b| (a.paused, a.remaining_now()).partial_cmp(&(b.paused, b.remaining_now())).unwrap())
+}
+
+/// Get `timer` as plain text, like `⏳ work 4m12s`.
+fn status_text(timer: &state::TimerState) -> String {
+  let icon = if timer.paused { TMUX_PAUSED_ICON } else { TMUX_ICON };
+
+  if timer.stage.is_empty() {
+    format!("{} {}", icon, format_remaining(timer.remaining_now()))
+  } else {
+    format!("{} {} {}", icon, timer.stage, format_remaining(timer.remaining_now()))
+  }
+}
+
+/// Get `timer` as a prompt segment, showing only the largest units left.
+fn prompt_segment(timer: &state::TimerState) -> String {
+  let icon = if timer.paused { TMUX_PAUSED_ICON } else { TMUX_ICON };
+  let seconds = timer.remaining_now() as u64;
+
+  let remaining = match seconds {
+    0..60 => format!("{}s", seconds),
+    60..3600 => format!("{}m", seconds / 60),
+    _ => format!("{}h{}m", seconds / 3600, seconds % 3600 / 60),
+  };
+
+  format!("{}{}", icon, remaining)
+}
+
+/// Get `timer` as a line for the status bar, colored by whether it's paused or about to end.
+fn statusbar_line(timer: &state::TimerState, format: StatusFormat) -> String {
+  let color = if timer.paused {
+    PAUSED_COLOR
+  } else if timer.remaining_now() <= ENDING_AFTER {
+    ENDING_COLOR
+  } else {
+    RUNNING_COLOR
+  };
+
+  let text = status_text(timer);
+
+  match format {
+    // a lone % starts a formatting tag in polybar
+    StatusFormat::Polybar => format!("%{{F{}}}{}%{{F-}}", color, text.replace('%', "%%")),
+    StatusFormat::I3blocks => format!(
+      "<span foreground=\"{}\">{}</span>",
+      color,
+      text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    ),
+  }
+}
+
+/// Get a line for each of `timers`, with the labels lined up, and the ids of those on the daemon.
+fn list_text(timers: &[state::TimerState]) -> String {
+  let width = timers.iter().map(|timer| terminal::display_width(&timer.label)).max().unwrap_or(0);
+
+  timers
+    .iter()
+    .map(|timer| {
+      let id = timer.id.map(|id| format!("  #{}", id)).unwrap_or_default();
+
+      format!(
+        "{}{}  {}{}\n",
+        timer.label,
+        " ".repeat(width - terminal::display_width(&timer.label)),
+        status_text(timer),
+        id
+      )
+    })
+    .collect()
+}
+
+/// Get a line for each timer that `ended`, saying how and how long ago, like `tea  ✓ finished 4m10s ago  #3`.
+fn ended_text(ended: &[state::EndedState]) -> String {
+  let width = ended.iter().map(|timer| terminal::display_width(&timer.label)).max().unwrap_or(0);
+
+  ended
+    .iter()
+    .map(|timer| {
+      let outcome = match timer.outcome {
+        state::Outcome::Finished => "✓ finished",
+        state::Outcome::Cancelled => "✗ cancelled",
+      };
+
+      format!(
+        "{}{}  {} {} ago  #{}\n",
+        timer.label,
+        " ".repeat(width - terminal::display_width(&timer.label)),
+        outcome,
+        format_remaining(state::now() - timer.ended_at),
+        timer.id
+      )
+    })
+    .collect()
+}
+
+/// Get how far through `timer` is, from 0 to 100.
+fn percent_done(timer: &state::TimerState) -> f64 {
+  if timer.duration <= 0.0 {
+    return 100.0;
+  }
+
+  (timer.elapsed_now() / timer.duration * 100.0).clamp(0.0, 100.0)
+}
+
+/// Get whether `timer` is running or paused.
+fn state_name(timer: &state::TimerState) -> &'static str {
+  if timer.paused {
+    "paused"
+  } else {
+    "running"
+  }
+}
+
+/// Get a line for each of `timers` with its state, time remaining, and percent done, with the labels lined up.
+fn status_lines(timers: &[state::TimerState]) -> String {
+  let width = timers.iter().map(|timer| terminal::display_width(&timer.label)).max().unwrap_or(0);
+
+  timers
+    .iter()
+    .map(|timer| {
+      format!(
+        "{}{}  {:<7}  {}  {}%\n",
+        timer.label,
+        " ".repeat(width - terminal::display_width(&timer.label)),
+        state_name(timer),
+        format_remaining(timer.remaining_now()),
+        percent_done(timer).floor()
+      )
+    })
+    .collect()
+}
+
+/// Get `timers` as a JSON array, with the times in seconds.
+fn status_json(timers: &[state::TimerState]) -> Value {
+  Value::Array(
+    timers
+      .iter()
+      .map(|timer| {
+        Value::object([
+          ("label", timer.label.as_str().into()),
+          ("stage", timer.stage.as_str().into()),
+          ("state", state_name(timer).into()),
+          ("duration", timer.duration.into()),
+          ("remaining", timer.remaining_now().round().into()),
+          ("percent", percent_done(timer).floor().into()),
+        ])
+      })
+      .collect(),
+  )
+}
+
+/// Get the xbar plugin output for `timers`, with buttons running `exe`.
+fn menubar_text(timers: &[state::TimerState], shown: Option<&state::TimerState>, exe: &str) -> String {
+  let Some(shown) = shown else {
+    return format!("{}\n", TMUX_ICON);
+  };
+
+  // a | starts the line's parameters
+  let mut output = format!("{}\n---\n", status_text(shown).replace('|', "¦"));
+
+  for timer in timers {
+    output.push_str(&format!("{}: {}\n", timer.label.replace('|', "¦"), status_text(timer)));
+  }
+
+  output.push_str("---\n");
+
+  let controls = if timers.iter().all(|timer| timer.paused) {
+    [("Resume", state::Control::Resume), ("Cancel", state::Control::Cancel)]
+  } else {
+    [("Pause", state::Control::Pause), ("Cancel", state::Control::Cancel)]
+  };
+
+  for (title, control) in controls {
+    output.push_str(&format!("{} | bash=\"{}\" param1={} terminal=false refresh=true\n", title, exe, control.name()));
+  }
+
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn statusbar_lines() {
+    let timer = state::TimerState {
+      id: None,
+      label: "tea".to_string(),
+      stage: "<steep>".to_string(),
+      duration: 240.0,
+      remaining: 90.0,
+      paused: true,
+      started_at: 0.0,
+      saved_at: state::now(),
+    };
+
+    assert_eq!(statusbar_line(&timer, StatusFormat::Polybar), "%{F#f1fa8c}⏸ <steep> 1m30s%{F-}");
+    assert_eq!(statusbar_line(&timer, StatusFormat::I3blocks), "<span foreground=\"#f1fa8c\">⏸ &lt;steep&gt; 1m30s</span>");
+  }
+
+  #[test]
+  fn prompt_segments() {
+    let mut timer = state::TimerState {
+      id: None,
+      label: "tea".to_string(),
+      stage: String::new(),
+      duration: 5400.0,
+      remaining: 750.0,
+      paused: true,
+      started_at: 0.0,
+      saved_at: state::now(),
+    };
+
+    assert_eq!(prompt_segment(&timer), "⏸12m");
+
+    timer.remaining = 3900.0;
+    assert_eq!(prompt_segment(&timer), "⏸1h5m");
+
+    timer.remaining = 42.0;
+    assert_eq!(prompt_segment(&timer), "⏸42s");
+  }
+
+  #[test]
+  fn list_lines() {
+    let timer = |label: &str, remaining| state::TimerState {
+      id: None,
+      label: label.to_string(),
+      stage: String::new(),
+      duration: 1500.0,
+      remaining,
+      paused: true,
+      started_at: 0.0,
+      saved_at: state::now(),
+    };
+
+    assert_eq!(list_text(&[timer("focus", 600.0), timer("tea", 90.0)]), "focus  ⏸ 10m0s\ntea    ⏸ 1m30s\n");
+
+    let ended = |id, label: &str, outcome| state::EndedState {
+      id,
+      label: label.to_string(),
+      outcome,
+      ended_at: state::now() - 250.0,
+    };
+
+    assert_eq!(
+      ended_text(&[ended(3, "tea", state::Outcome::Finished), ended(4, "laundry", state::Outcome::Cancelled)]),
+      "tea      ✓ finished 4m10s ago  #3\nlaundry  ✗ cancelled 4m10s ago  #4\n"
+    );
+  }
+
+  #[test]
+  fn status_output() {
+    let timer = state::TimerState {
+      id: None,
+      label: "focus".to_string(),
+      stage: String::new(),
+      duration: 1500.0,
+      remaining: 600.0,
+      paused: true,
+      started_at: 0.0,
+      saved_at: state::now(),
+    };
+
+    assert_eq!(status_lines(std::slice::from_ref(&timer)), "focus  paused   10m0s  60%\n");
+    assert_eq!(
+      status_json(&[timer]).to_string(),
+      r#"[{"label":"focus","stage":"","state":"paused","duration":1500,"remaining":600,"percent":60}]"#
+    );
+    assert_eq!(status_json(&[]).to_string(), "[]");
+  }
+
+  #[test]
+  fn primary_timer() {
+    let timer = |label: &str, remaining, started_at| state::TimerState {
+      id: None,
+      label: label.to_string(),
+      stage: String::new(),
+      duration: 600.0,
+      remaining,
+      paused: false,
+      started_at,
+      saved_at: state::now(),
+    };
+    let timers = [timer("tea", 60.0, 100.0), timer("focus", 300.0, 200.0), timer("laundry", 500.0, 50.0)];
+    let label = |timer: Option<&state::TimerState>| timer.map(|timer| timer.label.clone());
+
+    assert_eq!(label(primary(&timers, Primary::Soonest, None)).as_deref(), Some("tea"));
+    assert_eq!(label(primary(&timers, Primary::Recent, None)).as_deref(), Some("focus"));
+    assert_eq!(label(primary(&timers, Primary::Soonest, Some("laundry"))).as_deref(), Some("laundry"));
+    assert_eq!(label(primary(&timers, Primary::Soonest, Some("gone"))).as_deref(), Some("tea"));
+  }
+
+  #[test]
+  fn history_table() {
+    let session = |label: &str, profile: Option<&str>, tags: &[&str]| history::Session {
+      label: label.to_string(),
+      duration: 1500.0,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled: false,
+      profile: profile.map(str::to_string),
+      tags: tags.iter().map(ToString::to_string).collect(),
+      note: None,
+      reason: None,
+    };
+    let started_at = chrono::DateTime::from_timestamp(1714564800, 0).unwrap().with_timezone(&chrono::Local);
+    let (date, start) = (started_at.format("%Y-%m-%d"), started_at.format("%H:%M"));
+
+    let mut noted = session("focus", Some("pomodoro"), &["work"]);
+    noted.note = Some("outline".to_string());
+
+    let text = history_text(&[(1, noted), (3, session("tea", None, &[]))]);
+
+    assert_eq!(
+      text,
+      format!("#  Date        Start  Duration  Label  Profile   Tags  Note\n1  {date}  {start}  25m       focus  pomodoro  work  outline\n3  {date}  {start}  25m       tea\n\n2 sessions, 50m in all\n")
+    );
+  }
+
+  #[test]
+  fn profiles_table() {
+    let usage = stats::Usage {
+      runs: 4,
+      finished: 3,
+      average: 1650.0,
+      last: Some(1714564800.0),
+    };
+    let last = chrono::DateTime::from_timestamp(1714564800, 0).unwrap().with_timezone(&chrono::Local).format("%Y-%m-%d");
+
+    assert_eq!(profiles_text(&[("tea".to_string(), 180.0, None)]), "Profile  Duration\ntea      3m\n");
+    assert_eq!(
+      profiles_text(&[
+        ("pomodoro".to_string(), 1500.0, Some(usage)),
+        ("tea".to_string(), 180.0, Some(stats::usage(&[], "tea")))
+      ]),
+      format!("Profile   Duration  Runs  Finished  Average  vs planned  Last run\npomodoro  25m       4     75%       27m30s   +10%        {last}\ntea       3m        0                                    never\n")
+    );
+  }
+
+  #[test]
+  fn origins() {
+    let params = origin(Some("./log.sh"), &["PATH".to_string(), "TIMR_UNSET_FOR_TEST".to_string()]);
+    let params = Value::Object(params);
+
+    assert_eq!(params.get("exec").and_then(Value::as_str), Some("./log.sh"));
+    assert!(params.get("cwd").is_some());
+    assert!(params.get("env").and_then(|env| env.get("PATH")).is_some());
+    assert!(params.get("env").and_then(|env| env.get("TIMR_UNSET_FOR_TEST")).is_none());
+  }
+
+  #[test]
+  fn filters() {
+    let everything = Filter::default();
+    let old = Filter {
+      older_than: Some(Duration::from_secs(3600)),
+      paused: false,
+    };
+    let paused = Filter { older_than: None, paused: true };
+
+    assert!(everything.allows(false, state::now()));
+    assert!(old.allows(false, state::now() - 7200.0));
+    assert!(!old.allows(true, state::now() - 60.0));
+    assert!(paused.allows(true, state::now()));
+    assert!(!paused.allows(false, state::now()));
+  }
+
+  #[test]
+  fn event_lines() {
+    let event = |line: &str| event_text(&crate::json::parse(line).unwrap());
+
+    assert_eq!(
+      event(r#"{"jsonrpc":"2.0","method":"pause","params":{"id":1,"label":"tea","remaining":90}}"#).as_deref(),
+      Some("tea paused with 1m30s left")
+    );
+    assert_eq!(
+      event(r#"{"jsonrpc":"2.0","method":"stage","params":{"id":2,"label":"focus","stage":"break"}}"#).as_deref(),
+      Some("focus moved on to break")
+    );
+    assert_eq!(
+      event(r#"{"jsonrpc":"2.0","method":"tick","params":{"timers":[{"label":"tea","remaining":90},{"label":"focus","remaining":5}]}}"#).as_deref(),
+      Some("tea 1m30s, focus 5s")
+    );
+    assert_eq!(event(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#), None);
+  }
+
+  #[test]
+  fn menubar_plugin() {
+    let timer = state::TimerState {
+      id: None,
+      label: "tea".to_string(),
+      stage: String::new(),
+      duration: 240.0,
+      remaining: 90.0,
+      paused: true,
+      started_at: 0.0,
+      saved_at: state::now(),
+    };
+
+    assert_eq!(menubar_text(&[], None, "timr"), "⏳\n");
+    assert_eq!(
+      menubar_text(std::slice::from_ref(&timer), Some(&timer), "/usr/local/bin/timr"),
+      "⏸ 1m30s\n---\ntea: ⏸ 1m30s\n---\nResume | bash=\"/usr/local/bin/timr\" param1=resume terminal=false refresh=true\nCancel | bash=\"/usr/local/bin/timr\" param1=cancel terminal=false refresh=true\n"
+    );
+  }
+}
@@ -0,0 +1,451 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, process::exit};
+
+use serde::Deserialize;
+
+use crate::{
+  commands::Primary,
+  home_dir,
+  notify::{DoNotDisturb, Urgency},
+  render::{Background, Emphasis, Face, PercentStyle, ThemeName},
+  sound,
+  terminal::Align,
+  timer::Stage,
+  try_parse_duration,
+  webhook::Event,
+};
+
+#[derive(Deserialize, Default)]
+pub struct Config {
+  pub profiles: Option<Vec<Profile>>,
+  /// Show a desktop notification as each stage starts and when the timer finishes.
+  pub notify: Option<bool>,
+  /// Stop the machine from going to sleep while timers run.
+  pub keep_awake: Option<bool>,
+  /// Pause media players when timers finish, so the alarm can be heard.
+  pub pause_media: Option<bool>,
+  /// Slack incoming webhook to post to when timers finish.
+  pub slack: Option<Slack>,
+  /// Discord webhook to post to when timers finish or are cancelled.
+  pub discord: Option<Discord>,
+  /// Push services to notify, to hear about timers away from the computer.
+  pub push: Option<Push>,
+  /// Which profile counts as a pomodoro, and how many to aim for each day.
+  pub pomodoro: Option<Pomodoro>,
+  /// Toggl Track account to export to, and to add finished timers to.
+  pub toggl: Option<Toggl>,
+  /// How much of the history to keep.
+  pub history: Option<History>,
+  /// Remote the history is synced with, to bring together sessions from several machines.
+  pub sync: Option<Sync>,
+  /// Serve the timers on the session bus, to be shown and controlled by desktop widgets.
+  pub dbus: Option<bool>,
+  /// Timer status bars and prompts show when several are running and none is pinned.
+  pub primary: Option<Primary>,
+  /// How long the daemon keeps listing timers after they finish or are cancelled.
+  pub keep_ended: Option<String>,
+  /// Address to serve Prometheus metrics on.
+  pub metrics_listen: Option<String>,
+  /// File to write Prometheus metrics to, for node_exporter's textfile collector.
+  pub metrics_file: Option<PathBuf>,
+  /// Token clients of the daemon's HTTP API need, in place of the one made up for them.
+  pub http_token: Option<String>,
+  /// URL to POST a JSON payload to as timers start, finish, or are cancelled.
+  pub webhook: Option<String>,
+  /// FIFO to write events to as they happen, one JSON object per line.
+  pub event_pipe: Option<PathBuf>,
+  /// Address of a relay to tell as timers finish or are cancelled, usually forwarded back to the local machine over SSH.
+  pub relay: Option<String>,
+  /// Shell command to run as timers start, pause, resume, change stage, finish, and so on, with `TIMR_*` variables describing the timer.
+  pub exec: Option<String>,
+  /// Environment variables `timr start` passes on to the hooks of timers run in the background, `PATH` if not set.
+  pub exec_env: Option<Vec<String>>,
+  /// Body to send the webhook in place of the JSON payload, with placeholders like `{label}` filled in.
+  pub webhook_body: Option<String>,
+  /// Extra headers to send the webhook, by name.
+  pub webhook_headers: Option<BTreeMap<String, String>>,
+  /// Have the terminal show a notification when timers finish, with the OSC 9 or OSC 777 escape.
+  pub osc_notify: Option<bool>,
+  /// Copy a line summing up each timer to the clipboard when it finishes.
+  pub copy_summary: Option<bool>,
+  /// Show the minutes left as an iTerm2 badge over the terminal while timers run.
+  pub badge: Option<bool>,
+  /// Keep a notification up while the timer runs, following its progress.
+  pub live_notify: Option<bool>,
+  /// How urgent notifications are.
+  pub urgency: Option<Urgency>,
+  /// Audio file to play as each stage starts and when the timer finishes.
+  pub sound: Option<PathBuf>,
+  /// How long before the end to send a warning notification.
+  pub warn: Option<String>,
+  /// Audio file to play along with the warning, usually something softer than the finishing sound.
+  pub warn_sound: Option<PathBuf>,
+  /// Set of sounds to play for the timer's events, `custom` being the ones from `sounds`.
+  pub sound_theme: Option<sound::ThemeName>,
+  /// Audio files to play for each of the timer's events.
+  pub sounds: Option<Sounds>,
+  /// Alerts to send as each thing happens to a timer, in place of the usual ones.
+  pub on: Option<Routes>,
+  /// How loud to play the sound, from 0 to 100.
+  pub volume: Option<u8>,
+  /// Pattern to ring the terminal bell in, with a `.` for each ring and a space for each pause, like `... ...`.
+  pub bell: Option<String>,
+  /// What to do with alerts while the system is in Do Not Disturb mode.
+  pub do_not_disturb: Option<DoNotDisturb>,
+  /// Keep ringing when the timer finishes until dismissed.
+  pub alarm: Option<bool>,
+  /// Say aloud when the timer finishes, with the platform's text-to-speech engine.
+  pub speak: Option<bool>,
+  /// How often to say the time remaining aloud.
+  pub speak_every: Option<String>,
+  /// Show the current time before the time remaining.
+  pub clock: Option<bool>,
+  /// Show a line naming the timer and its total duration above the progress.
+  pub header: Option<bool>,
+  /// Show the percentage next to the progress bar.
+  pub percent: Option<bool>,
+  /// How the percentage is written.
+  pub percent_style: Option<PercentStyle>,
+  /// strftime format for the current time, defaulting to the locale's convention.
+  pub clock_format: Option<String>,
+  /// How to make the time remaining stand out as the timer is about to finish.
+  pub emphasis: Option<Emphasis>,
+  /// How long before the end to start emphasizing the time remaining.
+  pub emphasis_for: Option<String>,
+  /// Colors to draw the timer with.
+  pub theme: Option<ThemeName>,
+  /// Whether the terminal background is dark or light, asking the terminal when `auto`.
+  pub background: Option<Background>,
+  /// How the progress is drawn.
+  pub face: Option<Face>,
+  /// Where the timer sits horizontally in the terminal.
+  pub align: Option<Align>,
+}
+
+#[derive(Deserialize)]
+pub struct Profile {
+  pub name: String,
+  pub duration: Option<String>,
+  /// Run through these stages in order instead of a single duration.
+  pub stages: Option<Vec<StageConfig>>,
+  /// Title of the profile's notifications, defaulting to its name.
+  pub title: Option<String>,
+  /// Message of the notification when the profile's timer finishes.
+  pub message: Option<String>,
+  /// Icon of the profile's notifications, as an icon name or a path to an image.
+  pub icon: Option<String>,
+  /// How urgent the profile's notifications are, in place of the global urgency.
+  pub urgency: Option<Urgency>,
+  /// Audio file to play as each stage starts and when the profile's timer finishes, in place of the global one.
+  pub sound: Option<PathBuf>,
+  /// How loud to play the profile's sound, from 0 to 100, in place of the global volume.
+  pub volume: Option<u8>,
+  /// Pattern to ring the terminal bell in for the profile's timer, in place of the global one.
+  pub bell: Option<String>,
+  /// Slack channel to post to when the profile's timer finishes, in place of the global one.
+  pub slack_channel: Option<String>,
+  /// Emoji starting the profile's Slack messages, in place of the global one.
+  pub slack_emoji: Option<String>,
+  /// Discord webhook to post the profile's timers to, in place of the global one.
+  pub discord_webhook: Option<String>,
+  /// Say aloud when the profile's timer finishes, in place of the global setting.
+  pub speak: Option<bool>,
+  /// What to say when the profile's timer finishes, like `pasta is ready`.
+  pub speech: Option<String>,
+  /// Turn on Do Not Disturb while the profile's timer runs, putting it back as it was once it ends.
+  pub focus: Option<bool>,
+  /// Tags given to the profile's timers, along with any from `--tag`.
+  pub tags: Option<Vec<String>>,
+}
+
+impl Profile {
+  /// Get `tags` with the profile's own added, leaving out any already there.
+  pub fn tagged(&self, tags: &[String]) -> Vec<String> {
+    let mut tagged = tags.to_vec();
+
+    for tag in self.tags.iter().flatten() {
+      if !tagged.contains(tag) {
+        tagged.push(tag.clone());
+      }
+    }
+
+    tagged
+  }
+}
+
+/// Pomodoros to keep count of.
+#[derive(Deserialize, Clone, Default)]
+pub struct Pomodoro {
+  /// Profile whose finished timers count as pomodoros, `pomodoro` if not set.
+  pub profile: Option<String>,
+  /// Pomodoros to aim for each day.
+  pub goal: Option<u32>,
+}
+
+/// How much of the history to keep, all of it unless limited.
+#[derive(Deserialize, Clone, Default)]
+pub struct History {
+  /// How long sessions are kept after they end, like `90d`.
+  pub retention: Option<String>,
+  /// Most sessions kept, letting go of the oldest first.
+  pub max_entries: Option<usize>,
+  /// Where the history is kept.
+  pub backend: Option<Backend>,
+  /// Ask why a timer was cancelled from the keyboard, keeping the answer with it.
+  pub ask_reason: Option<bool>,
+}
+
+/// Remote the history is synced with by `timr history sync`.
+#[derive(Deserialize, Clone)]
+pub struct Sync {
+  /// Git repository, WebDAV file, or HTTP endpoint to sync with.
+  pub remote: String,
+  /// What kind of remote it is, worked out from the address for git repositories.
+  pub kind: Option<SyncKind>,
+  /// User to sign in to WebDAV or the endpoint as, along with `password`.
+  pub username: Option<String>,
+  pub password: Option<String>,
+  /// Token sent to the endpoint in an `Authorization: Bearer` header.
+  pub token: Option<String>,
+}
+
+/// What kind of remote the history is synced with.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncKind {
+  /// A git repository holding `history.toml`, pushed to and pulled from.
+  Git,
+  /// The history file itself on a WebDAV server, fetched and put back.
+  Webdav,
+  /// An endpoint listing sessions as a JSON array and taking new ones POSTed to it, one at a time.
+  Http,
+}
+
+/// Where the history is kept.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+  /// `history.toml`, in the data directory.
+  #[default]
+  File,
+  /// `history.sqlite` next to it, when built with the `sqlite` feature.
+  Sqlite,
+}
+
+/// A Toggl Track account.
+#[derive(Deserialize, Clone, Default)]
+pub struct Toggl {
+  /// Email for the rows of the CSV export, which Toggl's import needs.
+  pub email: Option<String>,
+  /// API token from the Toggl profile page, to add each timer as a time entry as it finishes.
+  pub api_token: Option<String>,
+  /// Workspace the time entries are added to.
+  pub workspace_id: Option<u64>,
+}
+
+/// Where and how to post to Slack.
+#[derive(Deserialize, Clone)]
+pub struct Slack {
+  /// URL of the incoming webhook.
+  pub webhook: String,
+  /// Channel to post to in place of the webhook's own, for legacy webhooks that allow it.
+  pub channel: Option<String>,
+  /// Emoji starting each message, like `:spaghetti:`.
+  pub emoji: Option<String>,
+}
+
+/// Where to post to Discord.
+#[derive(Deserialize, Clone)]
+pub struct Discord {
+  /// URL of the webhook, from the channel's integration settings.
+  pub webhook: String,
+}
+
+/// Services to push notifications to, for a phone to hear about timers finishing.
+#[derive(Deserialize, Clone, Default)]
+pub struct Push {
+  pub ntfy: Option<Ntfy>,
+  pub gotify: Option<Gotify>,
+  pub pushover: Option<Pushover>,
+}
+
+/// An ntfy topic.
+#[derive(Deserialize, Clone)]
+pub struct Ntfy {
+  pub topic: String,
+  /// Server the topic is on, defaulting to ntfy.sh.
+  pub server: Option<String>,
+  /// Access token, for protected topics.
+  pub token: Option<String>,
+}
+
+/// A Gotify server.
+#[derive(Deserialize, Clone)]
+pub struct Gotify {
+  pub url: String,
+  /// Token of the application to send as.
+  pub token: String,
+}
+
+/// A Pushover application and user.
+#[derive(Deserialize, Clone)]
+pub struct Pushover {
+  /// API token of the application.
+  pub token: String,
+  /// Key of the user or group to send to.
+  pub user: String,
+}
+
+/// Audio files for the `custom` sound theme, each played as something happens to a timer.
+#[derive(Deserialize, Default)]
+pub struct Sounds {
+  pub start: Option<PathBuf>,
+  pub stage_end: Option<PathBuf>,
+  pub finish: Option<PathBuf>,
+  pub warning: Option<PathBuf>,
+}
+
+impl Sounds {
+  /// Get the sound theme playing these files.
+  pub fn theme(&self) -> sound::Theme {
+    let file = |path: &Option<PathBuf>| path.clone().map(sound::Sound::File);
+
+    sound::Theme {
+      start: file(&self.start),
+      stage_end: file(&self.stage_end),
+      finish: file(&self.finish),
+      warning: file(&self.warning),
+    }
+  }
+}
+
+/// Alerts for each thing that can happen to a timer, replacing the usual ones for those that are given.
+#[derive(Deserialize, Default, Clone)]
+pub struct Routes {
+  pub start: Option<Vec<Alert>>,
+  pub pause: Option<Vec<Alert>>,
+  pub resume: Option<Vec<Alert>>,
+  pub extend: Option<Vec<Alert>>,
+  pub stage: Option<Vec<Alert>>,
+  pub finish: Option<Vec<Alert>>,
+  pub cancel: Option<Vec<Alert>>,
+}
+
+impl Routes {
+  /// Get the alerts to send for `event`, if they've been set.
+  pub fn get(&self, event: Event) -> Option<&[Alert]> {
+    match event {
+      Event::Start => self.start.as_deref(),
+      Event::Pause => self.pause.as_deref(),
+      Event::Resume => self.resume.as_deref(),
+      Event::Extend => self.extend.as_deref(),
+      Event::Stage => self.stage.as_deref(),
+      Event::Finish => self.finish.as_deref(),
+      Event::Cancel => self.cancel.as_deref(),
+    }
+  }
+}
+
+/// Somewhere an event can be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alert {
+  /// The terminal bell, rung in the timer's bell pattern.
+  Bell,
+  /// A desktop notification.
+  Notify,
+  /// The timer's sound.
+  Sound,
+  Webhook,
+  /// The `exec` hook.
+  Exec,
+}
+
+#[derive(Deserialize)]
+pub struct StageConfig {
+  pub name: String,
+  pub duration: String,
+}
+
+impl Profile {
+  /// Get the stages the profile's timer runs through, exiting with an error if it doesn't have any.
+  pub fn stages(&self) -> Vec<Stage> {
+    match self.try_stages() {
+      Ok(stages) => stages,
+      Err(error) => {
+        eprintln!("{}", error);
+        exit(1);
+      }
+    }
+  }
+
+  /// Get the stages the profile's timer runs through, or say why it can't run any.
+  pub fn try_stages(&self) -> Result<Vec<Stage>, String> {
+    match (&self.stages, &self.duration) {
+      (Some(stages), _) if !stages.is_empty() => stages
+        .iter()
+        .map(|stage| {
+          Ok(Stage {
+            name: stage.name.clone(),
+            duration: try_parse_duration(&stage.duration)?,
+          })
+        })
+        .collect(),
+      (_, Some(duration)) => Ok(vec![Stage {
+        name: self.name.clone(),
+        duration: try_parse_duration(duration)?,
+      }]),
+      _ => Err(format!("Profile {} has no duration or stages", self.name)),
+    }
+  }
+}
+
+impl Config {
+  /// Get the sound to play as timers finish, from `sound` or else the sound theme, for timers run without options of their own.
+  pub fn finish_sound(&self) -> Option<sound::Sound> {
+    let theme = self.sound_theme.unwrap_or_default().theme(self.sounds.as_ref().map(Sounds::theme).unwrap_or_default());
+
+    self.sound.clone().map(sound::Sound::File).or_else(|| theme.get(sound::Event::Finish))
+  }
+
+  /// Find the profile called `name`, exiting with an error if there isn't one.
+  pub fn profile(&self, name: &str) -> &Profile {
+    let Some(profiles) = &self.profiles else {
+      eprintln!("Config does not contain any profiles");
+      exit(1);
+    };
+
+    match profiles.iter().find(|profile| profile.name == name) {
+      Some(profile) => profile,
+      None => {
+        eprintln!("No profile found matching {}", name);
+        exit(1);
+      }
+    }
+  }
+
+  /// Get where the history is kept, the file unless `[history]` says otherwise.
+  pub fn history_backend(&self) -> Backend {
+    self.history.as_ref().and_then(|history| history.backend).unwrap_or_default()
+  }
+
+  /// Find the profile called `name`, if there is one.
+  pub fn find_profile(&self, name: &str) -> Option<&Profile> {
+    self.profiles.iter().flatten().find(|profile| profile.name == name)
+  }
+}
+
+/// Get the path of the config file.
+pub fn path() -> PathBuf {
+  home_dir().expect("Failed to find user's home directory").join(".config").join("timr.toml")
+}
+
+/// Load the config file, falling back to the defaults if it doesn't exist.
+pub fn load() -> Config {
+  let config_path = path();
+
+  if !config_path.exists() {
+    return Config::default();
+  }
+
+  toml::from_str(fs::read_to_string(config_path).expect("Failed to read config file").as_str()).expect("Failed to parse config file")
+}
@@ -0,0 +1,230 @@
+use std::{
+  process::exit,
+  sync::mpsc::channel,
+  thread::sleep,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  client, format_remaining,
+  json::Value,
+  mirrored_timer,
+  render::{self, Theme},
+  terminal::{self, input::Key},
+  timer::Timer,
+  BAR_UPDATE_INTERVAL,
+};
+
+/// How often the timers are fetched from the daemon, counting down on their own in between.
+const REFRESH_EVERY: Duration = Duration::from_millis(500);
+/// How much `+` adds to the selected timer.
+const EXTEND_BY: &str = "1m";
+/// Widest a label is shown, so long ones don't squeeze out the bars.
+const LABEL_MAX_WIDTH: usize = 24;
+/// Widest the bars are drawn.
+const BAR_MAX_WIDTH: u16 = 60;
+
+/// A timer on the daemon, along with a stand-in counting down between fetches.
+struct Listed {
+  id: Value,
+  label: String,
+  timer: Timer,
+}
+
+/// Show every timer on the daemon with live bars until the user quits, pausing, extending, cancelling, and starting them from the keyboard.
+pub fn run(theme: &Theme) {
+  let Some(keys) = terminal::input::listen() else {
+    eprintln!("timr dashboard needs a terminal");
+    exit(1);
+  };
+
+  let mut daemon = client::daemon();
+
+  let (exit_tx, exit_rx) = channel();
+  ctrlc::set_handler(move || exit_tx.send(()).expect("Could not send signal on channel.")).expect("Error setting Ctrl-C handler");
+
+  let mut canvas = terminal::Canvas::fullscreen();
+  let mut listed = Vec::new();
+  let mut selected = 0;
+  // what's been typed for a new timer, while one is being started
+  let mut creating: Option<String> = None;
+  let mut message = String::new();
+  let mut last_refresh: Option<Instant> = None;
+  let mut stopped = false;
+
+  terminal::set_cursor_visible(false);
+
+  'running: loop {
+    if exit_rx.try_recv().is_ok() {
+      break;
+    }
+
+    if last_refresh.is_none_or(|refreshed| refreshed.elapsed() >= REFRESH_EVERY) {
+      match daemon.call("query", Value::Object(Vec::new())) {
+        Ok(Value::Array(timers)) => listed = timers.iter().map(list).collect(),
+        Ok(_) => {}
+        Err(_) => {
+          stopped = true;
+          break;
+        }
+      }
+
+      last_refresh = Some(Instant::now());
+    }
+
+    selected = selected.min(listed.len().saturating_sub(1));
+
+    for key in keys.try_iter() {
+      if let Some(typed) = creating.as_mut() {
+        match key {
+          Key::Enter => {
+            message = start(&mut daemon, typed);
+            creating = None;
+            last_refresh = None;
+          }
+          Key::Escape => creating = None,
+          Key::Backspace => {
+            typed.pop();
+          }
+          Key::Char(character) => typed.push(character),
+          _ => {}
+        }
+
+        continue;
+      }
+
+      let method = match (key, listed.get(selected)) {
+        (Key::Char('q') | Key::Escape, _) => break 'running,
+        (Key::Char('j') | Key::Down, _) => {
+          selected = (selected + 1).min(listed.len().saturating_sub(1));
+          continue;
+        }
+        (Key::Char('k') | Key::Up, _) => {
+          selected = selected.saturating_sub(1);
+          continue;
+        }
+        (Key::Char('n'), _) => {
+          creating = Some(String::new());
+          continue;
+        }
+        (Key::Char(' ') | Key::Char('p'), Some(current)) if current.timer.is_paused() => "resume",
+        (Key::Char(' ') | Key::Char('p'), Some(_)) => "pause",
+        (Key::Char('+'), Some(_)) => "extend",
+        (Key::Char('c') | Key::Char('x'), Some(_)) => "cancel",
+        _ => continue,
+      };
+
+      message = control(&mut daemon, method, &listed[selected]);
+      last_refresh = None;
+    }
+
+    let status = match &creating {
+      Some(typed) => format!("New timer, a duration or profile and then a name if wanted: {}_", typed),
+      None => message.clone(),
+    };
+
+    terminal::write(&canvas.draw(&lines(&listed, selected, &status, theme)));
+    sleep(Duration::from_millis(BAR_UPDATE_INTERVAL as u64));
+  }
+
+  print!("{}", canvas.clear());
+  terminal::set_cursor_visible(true);
+  terminal::input::restore();
+
+  if stopped {
+    println!("The daemon stopped");
+  }
+}
+
+/// Get the timer `described` by the daemon as listed.
+fn list(described: &Value) -> Listed {
+  Listed {
+    id: described.get("id").cloned().unwrap_or(Value::Null),
+    label: described.get("label").and_then(Value::as_str).unwrap_or_default().to_string(),
+    timer: mirrored_timer(described),
+  }
+}
+
+/// Call `method` on the daemon for the `listed` timer, getting a message saying how it went.
+fn control(daemon: &mut client::Connection, method: &str, listed: &Listed) -> String {
+  let mut params = vec![("id".to_string(), listed.id.clone())];
+
+  if method == "extend" {
+    params.push(("by".to_string(), EXTEND_BY.into()));
+  }
+
+  let done = match method {
+    "pause" => "Paused",
+    "resume" => "Resumed",
+    "extend" => "Extended",
+    _ => "Cancelled",
+  };
+
+  match daemon.call(method, Value::Object(params)) {
+    Ok(_) => format!("{} {}", done, listed.label),
+    Err(error) => error,
+  }
+}
+
+/// Start the timer `typed` out, a duration or profile and then a name if given, getting a message saying how it went.
+fn start(daemon: &mut client::Connection, typed: &str) -> String {
+  let mut words = typed.split_whitespace();
+
+  let Some(duration) = words.next() else {
+    return String::new();
+  };
+
+  let what = if duration.starts_with(|c: char| c.is_ascii_digit()) { "duration" } else { "profile" };
+  let mut params = vec![(what.to_string(), Value::from(duration))];
+  let name = words.collect::<Vec<_>>().join(" ");
+
+  if !name.is_empty() {
+    params.push(("label".to_string(), name.as_str().into()));
+  }
+
+  match daemon.call("start", Value::Object(params)) {
+    Ok(timer) => format!("Started {}", timer.get("label").and_then(Value::as_str).unwrap_or(duration)),
+    Err(error) => error,
+  }
+}
+
+/// Get the lines drawing the `listed` timers with the one at `selected` marked, and `status` along the bottom with the keys.
+fn lines(listed: &[Listed], selected: usize, status: &str, theme: &Theme) -> Vec<String> {
+  let count = match listed.len() {
+    1 => "1 timer".to_string(),
+    count => format!("{} timers", count),
+  };
+
+  let mut lines = vec![format!("{}timr{} — {}", terminal::bold(), terminal::reset_style(), count), String::new()];
+
+  if listed.is_empty() {
+    lines.push("No timers running, press n to start one".to_string());
+  }
+
+  let label_width = listed.iter().map(|listed| terminal::display_width(&listed.label)).max().unwrap_or(0).min(LABEL_MAX_WIDTH);
+
+  // the marker, label, icon, and time remaining come first, with a spare column so lines never wrap
+  let bar_width = (terminal::get_width() as usize).saturating_sub(label_width + 18).min(BAR_MAX_WIDTH as usize) as u16;
+
+  for (index, listed) in listed.iter().enumerate() {
+    let label = terminal::truncate(&listed.label, label_width);
+    let padding = " ".repeat(label_width - terminal::display_width(&label));
+    let icon = if listed.timer.is_paused() { "⏸" } else { "⏳" };
+    let remaining = format!("{:<9}", format_remaining(listed.timer.remaining().as_secs_f64()));
+    let bar = render::bar(listed.timer.progress(), bar_width, theme);
+
+    let line = if index == selected {
+      format!("› {}{}{}{}  {} {} {}", terminal::bold(), label, terminal::reset_style(), padding, icon, remaining, bar)
+    } else {
+      format!("  {}{}  {} {} {}", label, padding, icon, remaining, bar)
+    };
+
+    lines.push(line);
+  }
+
+  lines.push(String::new());
+  lines.push(format!("space pause/resume  + extend {}  c cancel  n new  ↑↓ select  q quit", EXTEND_BY));
+  lines.push(status.to_string());
+
+  lines
+}
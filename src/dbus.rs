@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+/// Well-known name the service asks for on the session bus, held by the first timr to start.
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+const NAME: &str = "dev.thenoah.timr";
+
+/// Something asked of a timer over D-Bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(all(target_os = "linux", feature = "dbus")), allow(dead_code))]
+pub enum Command {
+  Pause,
+  Resume,
+  Extend(Duration),
+  /// Stop every timer, like pressing q.
+  Cancel,
+}
+
+/// What a timer is doing, as shown in its `State` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+  Running,
+  Paused,
+  Finished,
+}
+
+impl State {
+  #[cfg_attr(not(all(target_os = "linux", feature = "dbus")), allow(dead_code))]
+  fn name(self) -> &'static str {
+    match self {
+      Self::Running => "running",
+      Self::Paused => "paused",
+      Self::Finished => "finished",
+    }
+  }
+}
+
+/// A timer's properties on D-Bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(all(target_os = "linux", feature = "dbus")), allow(dead_code))]
+pub struct Status {
+  pub label: String,
+  /// Whole seconds remaining, rounded up.
+  pub remaining: u64,
+  pub state: State,
+}
+
+/// Check whether timr was built with the D-Bus service.
+pub fn supported() -> bool {
+  cfg!(all(target_os = "linux", feature = "dbus"))
+}
+
+/// The `dev.thenoah.timr.Timer` interface, served for each timer at `/dev/thenoah/timr/<index>`.
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+struct Timer {
+  index: usize,
+  status: Status,
+  commands: std::sync::mpsc::Sender<(usize, Command)>,
+}
+
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+#[zbus::interface(name = "dev.thenoah.timr.Timer")]
+impl Timer {
+  #[zbus(property)]
+  fn label(&self) -> String {
+    self.status.label.clone()
+  }
+
+  /// Whole seconds remaining.
+  #[zbus(property)]
+  fn remaining(&self) -> u64 {
+    self.status.remaining
+  }
+
+  /// One of `running`, `paused`, or `finished`.
+  #[zbus(property)]
+  fn state(&self) -> String {
+    self.status.state.name().to_string()
+  }
+
+  fn pause(&self) {
+    let _ = self.commands.send((self.index, Command::Pause));
+  }
+
+  fn resume(&self) {
+    let _ = self.commands.send((self.index, Command::Resume));
+  }
+
+  /// Add `seconds` to the timer.
+  fn extend(&self, seconds: u64) {
+    let _ = self.commands.send((self.index, Command::Extend(Duration::from_secs(seconds))));
+  }
+
+  fn cancel(&self) {
+    let _ = self.commands.send((self.index, Command::Cancel));
+  }
+}
+
+/// Timers being served on the session bus, until dropped.
+#[cfg_attr(not(all(target_os = "linux", feature = "dbus")), allow(dead_code))]
+pub struct Service {
+  #[cfg(all(target_os = "linux", feature = "dbus"))]
+  connection: zbus::blocking::Connection,
+  #[cfg(all(target_os = "linux", feature = "dbus"))]
+  commands: std::sync::mpsc::Receiver<(usize, Command)>,
+  /// Status last sent for each timer, to only signal changes.
+  statuses: Vec<Status>,
+}
+
+/// Serve `statuses` on the session bus, one object per timer.
+///
+/// Returns `None` when there's no session bus to connect to.
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+pub fn serve(statuses: Vec<Status>) -> Option<Service> {
+  let (commands_tx, commands) = std::sync::mpsc::channel();
+
+  let mut builder = match zbus::blocking::connection::Builder::session() {
+    Ok(builder) => builder,
+    Err(error) => {
+      eprintln!("Failed to connect to D-Bus: {}", error);
+      return None;
+    }
+  };
+
+  for (index, status) in statuses.iter().enumerate() {
+    let timer = Timer {
+      index,
+      status: status.clone(),
+      commands: commands_tx.clone(),
+    };
+
+    builder = builder.serve_at(format!("/dev/thenoah/timr/{}", index), timer).ok()?;
+  }
+
+  let connection = match builder.build() {
+    Ok(connection) => connection,
+    Err(error) => {
+      eprintln!("Failed to connect to D-Bus: {}", error);
+      return None;
+    }
+  };
+
+  // another timr already has the name, its timers can still be found by this connection's unique name
+  let _ = connection.request_name(NAME);
+
+  Some(Service { connection, commands, statuses })
+}
+
+/// Serve timers on the session bus, which can't be done without the D-Bus service.
+#[cfg(not(all(target_os = "linux", feature = "dbus")))]
+pub fn serve(_statuses: Vec<Status>) -> Option<Service> {
+  None
+}
+
+impl Service {
+  /// Get the commands sent since last checked, along with the index of the timer each is for.
+  pub fn commands(&self) -> Vec<(usize, Command)> {
+    #[cfg(all(target_os = "linux", feature = "dbus"))]
+    {
+      self.commands.try_iter().collect()
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "dbus")))]
+    {
+      Vec::new()
+    }
+  }
+
+  /// Set the properties of the timer at `index`, signalling those that changed.
+  pub fn update(&mut self, index: usize, status: Status) {
+    if self.statuses[index] == status {
+      return;
+    }
+
+    #[cfg(all(target_os = "linux", feature = "dbus"))]
+    if let Ok(timer) = self.connection.object_server().interface::<_, Timer>(format!("/dev/thenoah/timr/{}", index)) {
+      let previous = std::mem::replace(&mut timer.get_mut().status, status.clone());
+      let timer_ref = timer.get();
+      let emitter = timer.signal_emitter();
+
+      // signals going missing is no reason to stop the timer
+      if previous.label != status.label {
+        let _ = zbus::block_on(timer_ref.label_changed(emitter));
+      }
+
+      if previous.remaining != status.remaining {
+        let _ = zbus::block_on(timer_ref.remaining_changed(emitter));
+      }
+
+      if previous.state != status.state {
+        let _ = zbus::block_on(timer_ref.state_changed(emitter));
+      }
+    }
+
+    self.statuses[index] = status;
+  }
+}
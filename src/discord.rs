@@ -0,0 +1,46 @@
+use crate::{format_remaining, webhook, Options, Running};
+
+/// Color down the side of the embed for timers that finished, Discord's green.
+const FINISHED_COLOR: u32 = 0x57f287;
+/// Color down the side of the embed for timers that were cancelled, Discord's red.
+const CANCELLED_COLOR: u32 = 0xed4245;
+
+/// Post to Discord, if set up, that `running` has finished or been cancelled.
+pub fn post(running: &Running, cancelled: bool, options: &Options) {
+  let Some(url) = running.discord_webhook.as_ref().or(options.discord.as_ref()) else {
+    return;
+  };
+
+  let now = chrono::Local::now();
+
+  // discord shows timestamps written like this in each viewer's own time zone
+  let mut fields = vec![
+    ("Duration", format_remaining(running.timer.duration().as_secs_f64())),
+    (if cancelled { "Cancelled" } else { "Ended" }, format!("<t:{}:t>", now.timestamp())),
+  ];
+
+  let color = if cancelled {
+    fields.push(("Remaining", format_remaining(running.timer.remaining().as_secs_f64())));
+    CANCELLED_COLOR
+  } else {
+    FINISHED_COLOR
+  };
+
+  webhook::post(url, &webhook::discord_json(&title(&running.label, cancelled), color, &fields, now));
+}
+
+/// Get the embed title saying the timer called `label` has finished or been cancelled.
+fn title(label: &str, cancelled: bool) -> String {
+  format!("{} timer {}", label, if cancelled { "cancelled" } else { "finished" })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn titles() {
+    assert_eq!(title("tea", false), "tea timer finished");
+    assert_eq!(title("write report", true), "write report timer cancelled");
+  }
+}
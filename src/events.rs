@@ -0,0 +1,57 @@
+use std::{
+  fs::{File, OpenOptions},
+  io::Write,
+  path::Path,
+  sync::Mutex,
+};
+
+/// The pipe while something is reading it, kept open so the reader doesn't see the end of the stream between events.
+static PIPE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Make a FIFO at `path` for scripts to read events from, unless something is already there.
+#[cfg(unix)]
+pub fn create(path: &Path) {
+  use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+  if path.exists() {
+    return;
+  }
+
+  let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+    eprintln!("Invalid event pipe path: {}", path.display());
+    std::process::exit(1);
+  };
+
+  if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+    eprintln!("Failed to create event pipe {}: {}", path.display(), std::io::Error::last_os_error());
+    std::process::exit(1);
+  }
+}
+
+/// Make a pipe for events, which on Windows is left to whoever reads them, like `\\.\pipe\timr`.
+#[cfg(not(unix))]
+pub fn create(_path: &Path) {}
+
+/// Write `event` to the pipe at `path` as a line of its own, dropping it when nothing is reading.
+pub fn write(path: &Path, event: &str) {
+  let mut pipe = PIPE.lock().unwrap();
+
+  if pipe.is_none() {
+    let mut options = OpenOptions::new();
+    options.append(true);
+
+    // opening a FIFO blocks until there's a reader, where this fails straight away instead
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NONBLOCK);
+
+    *pipe = options.open(path).ok();
+  }
+
+  // a line this short goes through in one piece, so readers never see half an event
+  if let Some(file) = pipe.as_mut() {
+    if file.write_all(format!("{}\n", event).as_bytes()).is_err() {
+      // the reader went away, the next one is picked up on the next event
+      *pipe = None;
+    }
+  }
+}
@@ -0,0 +1,316 @@
+use chrono::{DateTime, Local, Utc};
+
+use crate::{format_remaining, history::Session, json::Value};
+
+/// Longest line allowed in an iCalendar file, in bytes, before it has to be folded.
+const ICS_LINE_LENGTH: usize = 75;
+
+/// Format to export the history in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  /// iCalendar, to import into calendar apps.
+  Ics,
+  /// A row for each session, to open in spreadsheets.
+  Csv,
+  /// An array with an object for each session.
+  Json,
+  /// Timewarrior's JSON, to import with `timew import`.
+  Timewarrior,
+  /// CSV for Toggl Track's import.
+  Toggl,
+}
+
+impl std::str::FromStr for Format {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "ics" => Ok(Self::Ics),
+      "csv" => Ok(Self::Csv),
+      "json" => Ok(Self::Json),
+      "timewarrior" | "timew" => Ok(Self::Timewarrior),
+      "toggl" => Ok(Self::Toggl),
+      _ => Err(format!("Invalid export format: {}", value)),
+    }
+  }
+}
+
+/// Write `sessions` in `format`, with `email` for the formats that need to say whose they are.
+pub fn export(sessions: &[Session], format: Format, email: &str) -> String {
+  match format {
+    Format::Ics => ics(sessions),
+    Format::Csv => csv(sessions),
+    Format::Json => format!("{}\n", json(sessions)),
+    Format::Timewarrior => format!("{}\n", timewarrior(sessions)),
+    Format::Toggl => toggl(sessions, email),
+  }
+}
+
+/// Write `sessions` as an iCalendar file, with an event for each that finished.
+fn ics(sessions: &[Session]) -> String {
+  let mut lines = vec![
+    "BEGIN:VCALENDAR".to_string(),
+    "VERSION:2.0".to_string(),
+    concat!("PRODID:-//", env!("CARGO_PKG_NAME"), "//", env!("CARGO_PKG_VERSION"), "//EN").to_string(),
+  ];
+
+  for session in sessions.iter().filter(|session| !session.cancelled) {
+    lines.extend([
+      "BEGIN:VEVENT".to_string(),
+      // the start time is as good as unique, and stays the same across exports so imports don't duplicate events
+      format!("UID:{}@{}", (session.started_at * 1000.0) as i64, env!("CARGO_PKG_NAME")),
+      format!("DTSTAMP:{}", ics_time(session.finished_at)),
+      format!("DTSTART:{}", ics_time(session.started_at)),
+      format!("DTEND:{}", ics_time(session.finished_at)),
+      format!("SUMMARY:{}", ics_text(&session.label)),
+      format!("DESCRIPTION:{}", ics_text(&format!("Timer for {}", format_remaining(session.duration)))),
+      "END:VEVENT".to_string(),
+    ]);
+  }
+
+  lines.push("END:VCALENDAR".to_string());
+
+  lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("")
+}
+
+/// Write `sessions` as CSV with a header, a row for each, and tags separated by semicolons.
+fn csv(sessions: &[Session]) -> String {
+  let mut output = "label,profile,tags,started_at,finished_at,duration_seconds,cancelled,note,reason\r\n".to_string();
+
+  for session in sessions {
+    let row = [
+      csv_field(&session.label),
+      csv_field(session.profile.as_deref().unwrap_or_default()),
+      csv_field(&session.tags.join(";")),
+      local_time(session.started_at),
+      local_time(session.finished_at),
+      session.duration.round().to_string(),
+      session.cancelled.to_string(),
+      csv_field(session.note.as_deref().unwrap_or_default()),
+      csv_field(session.reason.as_deref().unwrap_or_default()),
+    ];
+
+    output.push_str(&row.join(","));
+    output.push_str("\r\n");
+  }
+
+  output
+}
+
+/// Quote `value` for a CSV field if it has anything that would break the row up.
+fn csv_field(value: &str) -> String {
+  if value.contains([',', '"', '\r', '\n']) {
+    format!("\"{}\"", value.replace('"', "\"\""))
+  } else {
+    value.to_string()
+  }
+}
+
+/// Write `sessions` as a JSON array, with an object for each.
+fn json(sessions: &[Session]) -> Value {
+  Value::Array(
+    sessions
+      .iter()
+      .map(|session| {
+        Value::object([
+          ("label", session.label.as_str().into()),
+          ("profile", session.profile.as_deref().map_or(Value::Null, Value::from)),
+          ("tags", Value::Array(session.tags.iter().map(|tag| tag.as_str().into()).collect())),
+          ("started_at", local_time(session.started_at).as_str().into()),
+          ("finished_at", local_time(session.finished_at).as_str().into()),
+          ("duration", session.duration.into()),
+          ("cancelled", session.cancelled.into()),
+          ("note", session.note.as_deref().map_or(Value::Null, Value::from)),
+          ("reason", session.reason.as_deref().map_or(Value::Null, Value::from)),
+        ])
+      })
+      .collect(),
+  )
+}
+
+/// Write the `sessions` that finished as Timewarrior intervals, tagged with their label and their own tags, and annotated with any note.
+fn timewarrior(sessions: &[Session]) -> Value {
+  Value::Array(
+    sessions
+      .iter()
+      .filter(|session| !session.cancelled)
+      .map(|session| {
+        let mut tags = vec![session.label.as_str()];
+        tags.extend(session.tags.iter().map(String::as_str).filter(|tag| *tag != session.label));
+
+        let mut interval = vec![
+          ("start".to_string(), ics_time(session.started_at).as_str().into()),
+          ("end".to_string(), ics_time(session.finished_at).as_str().into()),
+          ("tags".to_string(), Value::Array(tags.into_iter().map(Value::from).collect())),
+        ];
+
+        if let Some(note) = &session.note {
+          interval.push(("annotation".to_string(), note.as_str().into()));
+        }
+
+        Value::Object(interval)
+      })
+      .collect(),
+  )
+}
+
+/// Write the `sessions` that finished as CSV for Toggl Track to import, each as `email`'s in local time.
+fn toggl(sessions: &[Session], email: &str) -> String {
+  let mut output = "Email,Description,Start date,Start time,Duration,Tags\r\n".to_string();
+
+  for session in sessions.iter().filter(|session| !session.cancelled) {
+    let start = DateTime::<Utc>::from_timestamp(session.started_at as i64, 0).unwrap_or_default().with_timezone(&Local);
+    let seconds = session.duration.round() as u64;
+
+    let row = [
+      csv_field(email),
+      csv_field(&session.label),
+      start.format("%Y-%m-%d").to_string(),
+      start.format("%H:%M:%S").to_string(),
+      format!("{:02}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60),
+      csv_field(&session.tags.join(", ")),
+    ];
+
+    output.push_str(&row.join(","));
+    output.push_str("\r\n");
+  }
+
+  output
+}
+
+/// Write `seconds` since the Unix epoch as a local date and time in RFC 3339.
+fn local_time(seconds: f64) -> String {
+  DateTime::<Utc>::from_timestamp(seconds as i64, 0)
+    .unwrap_or_default()
+    .with_timezone(&Local)
+    .to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+}
+
+/// Write `seconds` since the Unix epoch as a UTC date and time.
+fn ics_time(seconds: f64) -> String {
+  DateTime::<Utc>::from_timestamp(seconds as i64, 0).unwrap_or_default().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape `value` for a text property.
+fn ics_text(value: &str) -> String {
+  value.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+/// Break `line` up to fit the line length, with each continuation starting with a space, ending it with CRLF.
+fn fold(line: &str) -> String {
+  let mut output = String::new();
+  let mut length = 0;
+
+  for c in line.chars() {
+    // keep characters whole, as the limit is in bytes
+    if length + c.len_utf8() > ICS_LINE_LENGTH {
+      output.push_str("\r\n ");
+      length = 1;
+    }
+
+    output.push(c);
+    length += c.len_utf8();
+  }
+
+  output.push_str("\r\n");
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ics_events() {
+    let sessions = [Session {
+      label: "work, then rest".to_string(),
+      duration: 1500.0,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled: false,
+      profile: None,
+      tags: Vec::new(),
+      note: None,
+      reason: None,
+    }];
+
+    let output = ics(&sessions);
+
+    assert!(output.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(output.contains("DTSTART:20240501T120000Z\r\nDTEND:20240501T122500Z\r\nSUMMARY:work\\, then rest\r\nDESCRIPTION:Timer for 25m0s\r\n"));
+    assert!(output.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+  }
+
+  #[test]
+  fn csv_and_json() {
+    let session = |label: &str, cancelled| Session {
+      label: label.to_string(),
+      duration: 1500.0,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled,
+      profile: Some("pomodoro".to_string()),
+      tags: vec!["work".to_string(), "deep".to_string()],
+      note: (!cancelled).then(|| "outline".to_string()),
+      reason: cancelled.then(|| "a call".to_string()),
+    };
+    let sessions = [session("write, \"draft\"", false), session("tea", true)];
+    let (started_at, finished_at) = (local_time(1714564800.0), local_time(1714566300.0));
+
+    assert_eq!(
+      csv(&sessions),
+      format!(
+        "label,profile,tags,started_at,finished_at,duration_seconds,cancelled,note,reason\r\n\"write, \"\"draft\"\"\",pomodoro,work;deep,{started_at},{finished_at},1500,false,outline,\r\ntea,pomodoro,work;deep,{started_at},{finished_at},1500,true,,a call\r\n"
+      )
+    );
+
+    let json = json(&sessions);
+    let Value::Array(objects) = &json else {
+      panic!("Expected an array, got {}", json);
+    };
+
+    assert_eq!(objects[1].get("cancelled"), Some(&Value::from(true)));
+    assert_eq!(objects[1].get("reason"), Some(&Value::from("a call")));
+    assert!(json.to_string().contains(r#""tags":["work","deep"]"#));
+
+    // calendars only get the sessions that finished
+    assert_eq!(ics(&sessions).matches("BEGIN:VEVENT").count(), 1);
+  }
+
+  #[test]
+  fn time_tracking() {
+    let session = |cancelled| Session {
+      label: "focus".to_string(),
+      duration: 1500.0,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled,
+      profile: None,
+      tags: vec!["work".to_string(), "deep".to_string()],
+      note: Some("outline".to_string()),
+      reason: None,
+    };
+    let sessions = [session(false), session(true)];
+    let start = DateTime::<Utc>::from_timestamp(1714564800, 0).unwrap().with_timezone(&Local);
+
+    assert_eq!(
+      timewarrior(&sessions).to_string(),
+      r#"[{"start":"20240501T120000Z","end":"20240501T122500Z","tags":["focus","work","deep"],"annotation":"outline"}]"#
+    );
+    assert_eq!(
+      toggl(&sessions, "me@example.com"),
+      format!(
+        "Email,Description,Start date,Start time,Duration,Tags\r\nme@example.com,focus,{},{},00:25:00,\"work, deep\"\r\n",
+        start.format("%Y-%m-%d"),
+        start.format("%H:%M:%S")
+      )
+    );
+  }
+
+  #[test]
+  fn long_lines_fold() {
+    let line = "x".repeat(100);
+
+    assert_eq!(fold(&line), format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(25)));
+  }
+}
@@ -0,0 +1,417 @@
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::PathBuf,
+  process::exit,
+  time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, data_dir, lock, state, try_parse_duration};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// A timer that ran to the end or was cancelled, as kept in the history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+  pub label: String,
+  /// Total duration set in seconds, not counting time spent paused, or only as far as it got when cancelled.
+  pub duration: f64,
+  /// When the timer was started, in seconds since the Unix epoch.
+  pub started_at: f64,
+  /// When the timer finished or was cancelled, in seconds since the Unix epoch.
+  pub finished_at: f64,
+  /// Whether the timer was cancelled before it finished.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub cancelled: bool,
+  /// Name of the profile the timer was started from, if any.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub profile: Option<String>,
+  /// Tags the timer was given.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub tags: Vec<String>,
+  /// What was done during the session, added afterwards with `timr note`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub note: Option<String>,
+  /// Why the timer was cancelled, if a reason was given.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub reason: Option<String>,
+}
+
+/// Which sessions `timr history` shows, every one unless narrowed down.
+#[derive(Debug, Default)]
+pub struct Query {
+  /// Only sessions started since then, in seconds since the Unix epoch.
+  pub since: Option<f64>,
+  /// Only sessions started from this profile.
+  pub profile: Option<String>,
+  /// Only sessions given this tag.
+  pub tag: Option<String>,
+}
+
+impl Query {
+  /// Check whether `session` is one of those asked for.
+  pub fn matches(&self, session: &Session) -> bool {
+    self.since.is_none_or(|since| session.started_at >= since)
+      && self.profile.as_ref().is_none_or(|profile| session.profile.as_ref() == Some(profile))
+      && self.tag.as_ref().is_none_or(|tag| session.tags.contains(tag))
+  }
+}
+
+/// Get the start of the day `now` is in, in seconds since the Unix epoch.
+pub fn start_of_day(now: DateTime<Local>) -> f64 {
+  since_date(now.date_naive())
+}
+
+/// Parse when `--since` goes back to, either a date like `2024-01-01` or a duration before `now` like `2h`, in seconds since the Unix epoch.
+pub fn parse_since(value: &str, now: DateTime<Local>) -> Result<f64, String> {
+  if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+    return Ok(since_date(date));
+  }
+
+  match try_parse_duration(value) {
+    Ok(ago) => Ok(now.timestamp_millis() as f64 / 1000.0 - ago.as_secs_f64()),
+    Err(_) => Err(format!("Invalid date: {}, expected one like 2024-01-01 or a duration like 2h", value)),
+  }
+}
+
+/// Get the local midnight starting `date`, in seconds since the Unix epoch.
+fn since_date(date: NaiveDate) -> f64 {
+  // midnight can be skipped by daylight saving, in which case the day starts a little later
+  let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+  let start = midnight
+    .and_local_timezone(Local)
+    .earliest()
+    .or_else(|| (midnight + chrono::Duration::hours(1)).and_local_timezone(Local).earliest());
+
+  start.map_or(0.0, |start| start.timestamp() as f64)
+}
+
+#[derive(Serialize, Deserialize)]
+struct History {
+  #[serde(default)]
+  sessions: Vec<Session>,
+}
+
+/// Write `sessions` the way the history file keeps them.
+pub fn to_toml(sessions: Vec<Session>) -> Result<String, toml::ser::Error> {
+  toml::to_string(&History { sessions })
+}
+
+/// Read the sessions out of `contents`, written the way the history file keeps them.
+pub fn from_toml(contents: &str) -> Result<Vec<Session>, toml::de::Error> {
+  toml::from_str::<History>(contents).map(|history| history.sessions)
+}
+
+/// Get the file the history is kept in.
+fn path() -> PathBuf {
+  data_dir().join("history.toml")
+}
+
+/// Hold the history file for a change, so a session recorded by the daemon can't land between another process reading the file and writing it back.
+fn hold() -> io::Result<lock::Lock> {
+  lock::wait(&data_dir().join("history.lock"))
+}
+
+/// Where the history is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Store {
+  /// `history.toml`, appended to as sessions end.
+  File,
+  /// A SQLite database, for those with a lot of history to query.
+  #[cfg(feature = "sqlite")]
+  Sqlite,
+}
+
+/// Get where the history is kept for `backend`, as the config asks.
+fn store(backend: config::Backend) -> Store {
+  match backend {
+    config::Backend::File => Store::File,
+    #[cfg(feature = "sqlite")]
+    config::Backend::Sqlite => Store::Sqlite,
+    #[cfg(not(feature = "sqlite"))]
+    config::Backend::Sqlite => {
+      eprintln!("timr was built without SQLite support, keeping the history in {}", path().display());
+      Store::File
+    }
+  }
+}
+
+/// Add `session` to the end of the history kept in `backend`.
+pub fn record(backend: config::Backend, session: Session) {
+  let result = match store(backend) {
+    Store::File => append(vec![session]).map_err(|error| error.to_string()),
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => sqlite::record(&session).map_err(|error| error.to_string()),
+  };
+
+  if let Err(error) = result {
+    eprintln!("Failed to record history: {}", error);
+  }
+}
+
+/// Add `sessions` to the end of the history file.
+fn append(sessions: Vec<Session>) -> io::Result<()> {
+  // each session is its own [[sessions]] table, so the file can be appended to without reading it first
+  let entry = to_toml(sessions).map_err(io::Error::other)?;
+  let _held = hold()?;
+
+  fs::create_dir_all(data_dir())?;
+  OpenOptions::new().create(true).append(true).open(path())?.write_all(format!("{}\n", entry).as_bytes())
+}
+
+/// Add `sessions` to the end of the history kept in `backend`, as when they're brought in from elsewhere.
+pub fn add(backend: config::Backend, sessions: &[Session]) -> Result<(), String> {
+  if sessions.is_empty() {
+    return Ok(());
+  }
+
+  match store(backend) {
+    Store::File => append(sessions.to_vec()),
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => sqlite::add(sessions).map_err(io::Error::other),
+  }
+  .map_err(|error| format!("Failed to add to the history: {}", error))
+}
+
+/// Replace the history file with one holding `sessions`.
+fn save_file(sessions: Vec<Session>) -> io::Result<()> {
+  let contents = to_toml(sessions).map_err(io::Error::other)?;
+
+  // written to the side and moved into place, so a crash partway through can't lose the history
+  let temporary = path().with_extension("tmp");
+  fs::create_dir_all(data_dir())?;
+  fs::write(&temporary, contents)?;
+  fs::rename(&temporary, path())
+}
+
+/// Load every session in the history kept in `backend`, oldest first.
+pub fn load(backend: config::Backend) -> Vec<Session> {
+  match read(backend) {
+    Ok(sessions) => sessions,
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
+
+/// Read every session in the history kept in `backend`, oldest first, or say why it couldn't be.
+fn read(backend: config::Backend) -> Result<Vec<Session>, String> {
+  match store(backend) {
+    Store::File => read_file(),
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => sqlite::read().map_err(|error| format!("Failed to read history from the database: {}", error)),
+  }
+}
+
+/// Load the sessions in the history kept in `backend` that `query` asks for, oldest first, each numbered by where it is in the whole history.
+///
+/// The database picks them out itself, rather than every session being read to be filtered.
+pub fn query(backend: config::Backend, query: &Query) -> Vec<(usize, Session)> {
+  let result = match store(backend) {
+    Store::File => read_file().map(|sessions| {
+      sessions
+        .into_iter()
+        .enumerate()
+        .map(|(index, session)| (index + 1, session))
+        .filter(|(_, session)| query.matches(session))
+        .collect()
+    }),
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => sqlite::query(query).map_err(|error| format!("Failed to read history from the database: {}", error)),
+  };
+
+  match result {
+    Ok(sessions) => sessions,
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
+
+/// Read every session in the history file, oldest first.
+fn read_file() -> Result<Vec<Session>, String> {
+  let Ok(contents) = fs::read_to_string(path()) else {
+    return Ok(Vec::new());
+  };
+
+  from_toml(&contents).map_err(|error| format!("Failed to read history from {}: {}", path().display(), error))
+}
+
+/// Set the note on session `number` in `backend`, or the last one if not given, taking it off if `note` is empty.
+///
+/// Gets the session's number and label, or nothing if there's no such session.
+pub fn note(backend: config::Backend, number: Option<usize>, note: &str) -> Result<Option<(usize, String)>, String> {
+  let note = (!note.is_empty()).then_some(note);
+
+  match store(backend) {
+    Store::File => {
+      let _held = hold().map_err(|error| format!("Failed to save the history: {}", error))?;
+      let mut sessions = read_file()?;
+      let noted = noted(&mut sessions, number, note);
+
+      if noted.is_some() {
+        save_file(sessions).map_err(|error| format!("Failed to save the history: {}", error))?;
+      }
+
+      Ok(noted)
+    }
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => sqlite::note(number, note).map_err(|error| format!("Failed to save the history: {}", error)),
+  }
+}
+
+/// Set the note on session `number` in `sessions`, or the last one, getting its number and label if there is one.
+fn noted(sessions: &mut [Session], number: Option<usize>, note: Option<&str>) -> Option<(usize, String)> {
+  let number = number.unwrap_or(sessions.len());
+  let session = sessions.get_mut(number.checked_sub(1)?)?;
+
+  session.note = note.map(str::to_string);
+
+  Some((number, session.label.clone()))
+}
+
+/// Let go of the sessions in `backend` that ended more than `retention` ago, then the oldest beyond `max_entries`, getting how many went.
+pub fn prune(backend: config::Backend, retention: Option<Duration>, max_entries: Option<usize>) -> Result<usize, String> {
+  match store(backend) {
+    Store::File => {
+      let _held = hold().map_err(|error| format!("Failed to prune the history: {}", error))?;
+      let sessions = read_file()?;
+      let count = sessions.len();
+      let kept = pruned(sessions, retention, max_entries, state::now());
+
+      if kept.len() == count {
+        return Ok(0);
+      }
+
+      let removed = count - kept.len();
+      save_file(kept).map_err(|error| format!("Failed to save the history: {}", error))?;
+
+      Ok(removed)
+    }
+    // only those going are touched, leaving any recorded meanwhile alone
+    #[cfg(feature = "sqlite")]
+    Store::Sqlite => {
+      sqlite::prune(retention.map(|retention| state::now() - retention.as_secs_f64()), max_entries).map_err(|error| format!("Failed to prune the history: {}", error))
+    }
+  }
+}
+
+/// Get `sessions` without those that ended more than `retention` before `now`, keeping no more than the latest `max_entries`.
+fn pruned(mut sessions: Vec<Session>, retention: Option<Duration>, max_entries: Option<usize>, now: f64) -> Vec<Session> {
+  if let Some(retention) = retention {
+    sessions.retain(|session| now - session.finished_at <= retention.as_secs_f64());
+  }
+
+  if let Some(max_entries) = max_entries {
+    sessions.drain(..sessions.len().saturating_sub(max_entries));
+  }
+
+  sessions
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+
+  use super::*;
+
+  #[test]
+  fn queries() {
+    let session = Session {
+      label: "focus".to_string(),
+      duration: 1500.0,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled: false,
+      profile: Some("pomodoro".to_string()),
+      tags: vec!["work".to_string()],
+      note: None,
+      reason: None,
+    };
+
+    assert!(Query::default().matches(&session));
+    assert!(Query {
+      since: Some(1714564800.0),
+      profile: Some("pomodoro".to_string()),
+      tag: Some("work".to_string()),
+    }
+    .matches(&session));
+    assert!(!Query {
+      since: Some(1714564801.0),
+      ..Default::default()
+    }
+    .matches(&session));
+    assert!(!Query {
+      profile: Some("tea".to_string()),
+      ..Default::default()
+    }
+    .matches(&session));
+    assert!(!Query {
+      tag: Some("chores".to_string()),
+      ..Default::default()
+    }
+    .matches(&session));
+  }
+
+  #[test]
+  fn notes() {
+    let session = |label: &str| Session {
+      label: label.to_string(),
+      duration: 60.0,
+      started_at: 0.0,
+      finished_at: 60.0,
+      cancelled: false,
+      profile: None,
+      tags: Vec::new(),
+      note: None,
+      reason: None,
+    };
+    let mut sessions = vec![session("tea"), session("coffee")];
+
+    assert_eq!(noted(&mut sessions, Some(1), Some("green")), Some((1, "tea".to_string())));
+    assert_eq!(noted(&mut sessions, None, Some("black")), Some((2, "coffee".to_string())));
+    assert_eq!(noted(&mut sessions, Some(0), None), None);
+    assert_eq!(noted(&mut sessions, Some(3), None), None);
+    assert_eq!(sessions[0].note.as_deref(), Some("green"));
+    assert_eq!(noted(&mut Vec::new(), None, None), None);
+  }
+
+  #[test]
+  fn pruning() {
+    let session = |label: &str, finished_at| Session {
+      label: label.to_string(),
+      duration: 60.0,
+      started_at: finished_at - 60.0,
+      finished_at,
+      cancelled: false,
+      profile: None,
+      tags: Vec::new(),
+      note: None,
+      reason: None,
+    };
+    let sessions = vec![session("old", 1000.0), session("middle", 5000.0), session("new", 9000.0)];
+    let labels = |sessions: Vec<Session>| sessions.into_iter().map(|session| session.label).collect::<Vec<_>>();
+
+    assert_eq!(labels(pruned(sessions.clone(), None, None, 10000.0)), ["old", "middle", "new"]);
+    assert_eq!(labels(pruned(sessions.clone(), Some(Duration::from_secs(5000)), None, 10000.0)), ["middle", "new"]);
+    assert_eq!(labels(pruned(sessions.clone(), None, Some(1), 10000.0)), ["new"]);
+    assert_eq!(labels(pruned(sessions, Some(Duration::from_secs(100)), Some(5), 10000.0)), Vec::<String>::new());
+  }
+
+  #[test]
+  fn since() {
+    let now = Local.with_ymd_and_hms(2024, 5, 1, 15, 30, 0).unwrap();
+    let midnight = Local.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap().timestamp() as f64;
+
+    assert_eq!(start_of_day(now), midnight);
+    assert_eq!(parse_since("2024-05-01", now), Ok(midnight));
+    assert_eq!(parse_since("2h", now), Ok(now.timestamp() as f64 - 7200.0));
+    assert!(parse_since("yesterday", now).is_err());
+  }
+}
@@ -0,0 +1,354 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use rusqlite::{params, Connection, Result, Row, ToSql, TransactionBehavior};
+
+use super::{read_file, Query, Session};
+use crate::{
+  data_dir,
+  json::{self, Value},
+};
+
+/// How long to wait on another timr writing to the database before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Get the database the history is kept in.
+fn path() -> PathBuf {
+  data_dir().join("history.sqlite")
+}
+
+/// Open the database, setting it up and bringing in `history.toml` the first time.
+fn open() -> Result<Connection> {
+  let _ = fs::create_dir_all(data_dir());
+
+  let new = !path().exists();
+  let connection = Connection::open(path())?;
+
+  connection.busy_timeout(BUSY_TIMEOUT)?;
+  // readers don't hold up the daemon recording a session
+  connection.pragma_update(None, "journal_mode", "WAL")?;
+  create(&connection)?;
+
+  if new {
+    match read_file() {
+      Ok(sessions) if !sessions.is_empty() => replace_in(&connection, &sessions)?,
+      Ok(_) => {}
+      Err(error) => eprintln!("{}, starting the database empty", error),
+    }
+  }
+
+  Ok(connection)
+}
+
+/// Make the table for the sessions if it isn't there yet.
+fn create(connection: &Connection) -> Result<()> {
+  connection.execute_batch(
+    "CREATE TABLE IF NOT EXISTS sessions (
+      id INTEGER PRIMARY KEY,
+      label TEXT NOT NULL,
+      duration REAL NOT NULL,
+      started_at REAL NOT NULL,
+      finished_at REAL NOT NULL,
+      cancelled INTEGER NOT NULL DEFAULT 0,
+      profile TEXT,
+      tags TEXT NOT NULL DEFAULT '[]',
+      note TEXT,
+      reason TEXT
+    );
+    CREATE INDEX IF NOT EXISTS sessions_started_at ON sessions (started_at);",
+  )?;
+
+  // databases made before sessions had reasons
+  if connection.prepare("SELECT reason FROM sessions LIMIT 0").is_err() {
+    connection.execute_batch("ALTER TABLE sessions ADD COLUMN reason TEXT;")?;
+  }
+
+  Ok(())
+}
+
+/// Add `session` to the end of the history.
+pub fn record(session: &Session) -> Result<()> {
+  insert(&open()?, session)
+}
+
+/// Read every session in the history, oldest first.
+pub fn read() -> Result<Vec<Session>> {
+  select(&open()?)
+}
+
+/// Read the sessions in the history that `query` asks for, oldest first, each with its number in the whole history.
+pub fn query(query: &Query) -> Result<Vec<(usize, Session)>> {
+  select_where(&open()?, query)
+}
+
+/// Add `sessions` to the end of the history, all or none of them.
+pub fn add(sessions: &[Session]) -> Result<()> {
+  let mut connection = open()?;
+  let transaction = connection.transaction()?;
+
+  for session in sessions {
+    insert(&transaction, session)?;
+  }
+
+  transaction.commit()
+}
+
+/// Set the note on session `number`, or the last one, getting its number and label if there is one.
+pub fn note(number: Option<usize>, note: Option<&str>) -> Result<Option<(usize, String)>> {
+  let mut connection = open()?;
+  // taken for writing up front, so the session can't move between finding it and noting it
+  let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+  let noted = note_in(&transaction, number, note)?;
+
+  transaction.commit()?;
+  Ok(noted)
+}
+
+/// Let go of the sessions that ended before `before`, then the oldest beyond `max_entries`, getting how many went.
+pub fn prune(before: Option<f64>, max_entries: Option<usize>) -> Result<usize> {
+  prune_in(&open()?, before, max_entries)
+}
+
+/// Add `session` to the table in `connection`.
+fn insert(connection: &Connection, session: &Session) -> Result<()> {
+  // the tags are kept as a JSON array, rather than in a table of their own, as they're only ever read back with the session
+  let tags = Value::Array(session.tags.iter().map(|tag| tag.as_str().into()).collect()).to_string();
+
+  connection.execute(
+    "INSERT INTO sessions (label, duration, started_at, finished_at, cancelled, profile, tags, note, reason) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    params![
+      session.label,
+      session.duration,
+      session.started_at,
+      session.finished_at,
+      session.cancelled,
+      session.profile,
+      tags,
+      session.note,
+      session.reason
+    ],
+  )?;
+
+  Ok(())
+}
+
+/// Set the note on session `number` in `connection`, or the last one, getting its number and label if there is one.
+fn note_in(connection: &Connection, number: Option<usize>, note: Option<&str>) -> Result<Option<(usize, String)>> {
+  let count = connection.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get::<_, i64>(0))? as usize;
+
+  let Some(number) = number.or(Some(count)).filter(|number| (1..=count).contains(number)) else {
+    return Ok(None);
+  };
+
+  let (id, label) = connection.query_row("SELECT id, label FROM sessions ORDER BY id LIMIT 1 OFFSET ?1", [number as i64 - 1], |row| {
+    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+  })?;
+
+  connection.execute("UPDATE sessions SET note = ?1 WHERE id = ?2", params![note, id])?;
+
+  Ok(Some((number, label)))
+}
+
+/// Delete the sessions in `connection` that ended before `before`, then the oldest beyond `max_entries`, getting how many went.
+fn prune_in(connection: &Connection, before: Option<f64>, max_entries: Option<usize>) -> Result<usize> {
+  let mut removed = 0;
+
+  if let Some(before) = before {
+    removed += connection.execute("DELETE FROM sessions WHERE finished_at < ?1", [before])?;
+  }
+
+  if let Some(max_entries) = max_entries {
+    removed += connection.execute(
+      "DELETE FROM sessions WHERE id NOT IN (SELECT id FROM sessions ORDER BY id DESC LIMIT ?1)",
+      [max_entries as i64],
+    )?;
+  }
+
+  Ok(removed)
+}
+
+/// Read every session from the table in `connection`, in the order they were added.
+fn select(connection: &Connection) -> Result<Vec<Session>> {
+  let sessions = select_where(connection, &Query::default())?;
+
+  Ok(sessions.into_iter().map(|(_, session)| session).collect())
+}
+
+/// Read the sessions `query` asks for from the table in `connection`, in the order they were added, each with its number.
+///
+/// A session's number is counted from the ids up to its own, as pruning leaves gaps in them.
+fn select_where(connection: &Connection, query: &Query) -> Result<Vec<(usize, Session)>> {
+  let mut conditions = Vec::new();
+  let mut values: Vec<&dyn ToSql> = Vec::new();
+
+  if let Some(since) = &query.since {
+    conditions.push("started_at >= ?");
+    values.push(since);
+  }
+
+  if let Some(profile) = &query.profile {
+    conditions.push("profile = ?");
+    values.push(profile);
+  }
+
+  if let Some(tag) = &query.tag {
+    conditions.push("EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?)");
+    values.push(tag);
+  }
+
+  let filter = if conditions.is_empty() {
+    String::new()
+  } else {
+    format!(" WHERE {}", conditions.join(" AND "))
+  };
+
+  let mut statement = connection.prepare(&format!(
+    "SELECT (SELECT COUNT(*) FROM sessions AS earlier WHERE earlier.id <= sessions.id), label, duration, started_at, finished_at, cancelled, profile, tags, note, reason FROM sessions{} ORDER BY id",
+    filter
+  ))?;
+
+  let sessions = statement.query_map(values.as_slice(), |row| Ok((row.get::<_, i64>(0)? as usize, session(row)?)))?;
+
+  sessions.collect()
+}
+
+/// Get the session in `row`, read from the columns after the id.
+fn session(row: &Row) -> Result<Session> {
+  let tags = row.get::<_, String>(7)?;
+
+  Ok(Session {
+    label: row.get(1)?,
+    duration: row.get(2)?,
+    started_at: row.get(3)?,
+    finished_at: row.get(4)?,
+    cancelled: row.get(5)?,
+    profile: row.get(6)?,
+    tags: match json::parse(&tags) {
+      Ok(Value::Array(tags)) => tags.iter().filter_map(|tag| tag.as_str().map(str::to_string)).collect(),
+      _ => Vec::new(),
+    },
+    note: row.get(8)?,
+    reason: row.get(9)?,
+  })
+}
+
+/// Replace every session in the table in `connection` with `sessions`, all at once.
+fn replace_in(connection: &Connection, sessions: &[Session]) -> Result<()> {
+  let transaction = connection.unchecked_transaction()?;
+
+  transaction.execute("DELETE FROM sessions", [])?;
+
+  for session in sessions {
+    insert(&transaction, session)?;
+  }
+
+  transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn storing() {
+    let connection = Connection::open_in_memory().unwrap();
+    create(&connection).unwrap();
+
+    let session = |label: &str, tags: &[&str]| Session {
+      label: label.to_string(),
+      duration: 1500.0,
+      started_at: 1000.0,
+      finished_at: 2500.0,
+      cancelled: false,
+      profile: Some("pomodoro".to_string()),
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      note: None,
+      reason: None,
+    };
+
+    insert(&connection, &session("first", &["work", "deep"])).unwrap();
+    insert(&connection, &session("second", &[])).unwrap();
+
+    let sessions = select(&connection).unwrap();
+    assert_eq!(sessions.iter().map(|session| session.label.as_str()).collect::<Vec<_>>(), ["first", "second"]);
+    assert_eq!(sessions[0].tags, ["work", "deep"]);
+    assert_eq!(sessions[0].profile.as_deref(), Some("pomodoro"));
+
+    let mut noted = session("third", &[]);
+    noted.note = Some("wrote tests".to_string());
+    noted.cancelled = true;
+    noted.reason = Some("a call".to_string());
+    replace_in(&connection, &[noted]).unwrap();
+
+    let sessions = select(&connection).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].note.as_deref(), Some("wrote tests"));
+    assert!(sessions[0].cancelled);
+    assert_eq!(sessions[0].reason.as_deref(), Some("a call"));
+  }
+
+  #[test]
+  fn querying() {
+    let connection = Connection::open_in_memory().unwrap();
+    create(&connection).unwrap();
+
+    let session = |label: &str, started_at: f64, profile: Option<&str>, tags: &[&str]| Session {
+      label: label.to_string(),
+      duration: 60.0,
+      started_at,
+      finished_at: started_at + 60.0,
+      cancelled: false,
+      profile: profile.map(str::to_string),
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      note: None,
+      reason: None,
+    };
+
+    for session in [
+      session("pruned", 0.0, None, &[]),
+      session("first", 1000.0, Some("pomodoro"), &["work"]),
+      session("second", 2000.0, None, &["work", "deep"]),
+      session("third", 3000.0, Some("pomodoro"), &[]),
+    ] {
+      insert(&connection, &session).unwrap();
+    }
+
+    // numbered from 1 still once the oldest are pruned
+    assert_eq!(prune_in(&connection, Some(100.0), None).unwrap(), 1);
+
+    let numbers = |query: Query| select_where(&connection, &query).unwrap().into_iter().map(|(number, _)| number).collect::<Vec<_>>();
+
+    assert_eq!(numbers(Query::default()), [1, 2, 3]);
+    assert_eq!(
+      numbers(Query {
+        since: Some(2000.0),
+        ..Default::default()
+      }),
+      [2, 3]
+    );
+    assert_eq!(
+      numbers(Query {
+        profile: Some("pomodoro".to_string()),
+        ..Default::default()
+      }),
+      [1, 3]
+    );
+    assert_eq!(
+      numbers(Query {
+        since: Some(1500.0),
+        tag: Some("work".to_string()),
+        ..Default::default()
+      }),
+      [2]
+    );
+
+    assert_eq!(note_in(&connection, Some(2), Some("deep work")).unwrap(), Some((2, "second".to_string())));
+    assert_eq!(note_in(&connection, None, Some("tidied up")).unwrap(), Some((3, "third".to_string())));
+    assert_eq!(note_in(&connection, Some(4), Some("nothing")).unwrap(), None);
+
+    let notes = select(&connection).unwrap().into_iter().map(|session| session.note).collect::<Vec<_>>();
+    assert_eq!(notes, [None, Some("deep work".to_string()), Some("tidied up".to_string())]);
+
+    assert_eq!(prune_in(&connection, None, Some(1)).unwrap(), 2);
+    assert_eq!(select(&connection).unwrap()[0].label, "third");
+  }
+}
@@ -0,0 +1,84 @@
+use std::{
+  path::Path,
+  process::{Command, Stdio},
+  thread,
+};
+
+use crate::webhook::Payload;
+
+/// Run `command` through the shell in the background, with the timer described in `TIMR_*` variables.
+///
+/// Like webhooks, the timer carries on without waiting for it.
+pub fn run(command: &str, payload: &Payload) {
+  run_in(command, payload, None, &[]);
+}
+
+/// Run `command` like `run`, but in `dir` if given and with `env` added, for timers started somewhere other than where they're run.
+pub fn run_in(command: &str, payload: &Payload, dir: Option<&Path>, env: &[(String, String)]) {
+  #[cfg(windows)]
+  let mut shell = Command::new("cmd");
+  #[cfg(windows)]
+  shell.arg("/C");
+
+  #[cfg(not(windows))]
+  let mut shell = Command::new("sh");
+  #[cfg(not(windows))]
+  shell.arg("-c");
+
+  // a directory that's since gone shouldn't keep the hook from running
+  if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+    shell.current_dir(dir);
+  }
+
+  match shell.arg(command).envs(env.iter().cloned()).envs(environment(payload)).stdin(Stdio::null()).spawn() {
+    // waited on out of the way, so it doesn't linger as a zombie once done
+    Ok(mut child) => {
+      thread::spawn(move || child.wait());
+    }
+    Err(error) => eprintln!("Failed to run {}: {}", command, error),
+  }
+}
+
+/// Get the variables describing the timer to hooks.
+///
+/// These are a stable interface, so are only ever added to.
+fn environment(payload: &Payload) -> [(&'static str, String); 7] {
+  [
+    ("TIMR_LABEL", payload.label.to_string()),
+    ("TIMR_TOTAL_SECS", (payload.duration.round() as u64).to_string()),
+    ("TIMR_REMAINING_SECS", (payload.remaining.round() as u64).to_string()),
+    ("TIMR_STAGE", payload.stage.to_string()),
+    ("TIMR_STATUS", payload.event.name().to_string()),
+    ("TIMR_STARTED_AT", payload.started_at.to_rfc3339()),
+    ("TIMR_ENDED_AT", payload.timestamp.to_rfc3339()),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::{DateTime, Local};
+
+  use super::*;
+  use crate::webhook::Event;
+
+  #[test]
+  fn variables() {
+    let at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap().with_timezone(&Local);
+    let payload = Payload {
+      event: Event::Pause,
+      label: "tea",
+      stage: "steep",
+      duration: 240.0,
+      remaining: 89.6,
+      started_at: at,
+      timestamp: at,
+    };
+
+    let variables = environment(&payload);
+
+    assert_eq!(variables[1], ("TIMR_TOTAL_SECS", "240".to_string()));
+    assert_eq!(variables[2], ("TIMR_REMAINING_SECS", "90".to_string()));
+    assert_eq!(variables[4], ("TIMR_STATUS", "pause".to_string()));
+    assert_eq!(variables[6], ("TIMR_ENDED_AT", at.to_rfc3339()));
+  }
+}
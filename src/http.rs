@@ -0,0 +1,374 @@
+use std::{
+  fs,
+  io::{self, BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  path::PathBuf,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  thread,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  data_dir,
+  json::{self, Value},
+};
+
+/// How long a client has to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request body taken, which is plenty for starting a timer.
+const MAX_BODY: usize = 64 * 1024;
+/// Most read from a connection, with room for the request line and headers on top of the body.
+const MAX_REQUEST: u64 = MAX_BODY as u64 + 16 * 1024;
+/// Most connections answered at once, with any more closed straight away.
+const MAX_CONNECTIONS: usize = 32;
+/// How many random bytes go into a token.
+const TOKEN_BYTES: usize = 32;
+/// Params only local clients may give, as they run commands on this machine.
+const LOCAL_PARAMS: [&str; 3] = ["exec", "cwd", "env"];
+
+/// A request, with only as much kept as the API looks at.
+#[derive(Debug, Default)]
+struct Request {
+  method: String,
+  path: String,
+  /// Token from the `Authorization: Bearer` header.
+  token: Option<String>,
+  body: String,
+}
+
+/// Serve the API on `address`, like `127.0.0.1:7227`, for clients with `token`, passing each request on to `call` as a JSON-RPC method and its params.
+pub fn listen<F>(address: &str, token: String, call: F) -> io::Result<()>
+where
+  F: Fn(&str, Value) -> Result<Value, String> + Send + Sync + 'static,
+{
+  let listener = TcpListener::bind(address)?;
+  let handler = Arc::new((token, call));
+  let connections = Arc::new(AtomicUsize::new(0));
+
+  thread::spawn(move || {
+    for stream in listener.incoming().flatten() {
+      let Some(slot) = Slot::take(&connections) else {
+        continue;
+      };
+      let handler = handler.clone();
+
+      // each on its own, so a slow client doesn't hold up the rest
+      thread::spawn(move || {
+        let _slot = slot;
+        let (token, call) = &*handler;
+        respond(stream, token, call);
+      });
+    }
+  });
+
+  Ok(())
+}
+
+/// One of the `MAX_CONNECTIONS` being answered, given back when dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+  /// Take a slot from `connections`, if they aren't all taken.
+  fn take(connections: &Arc<AtomicUsize>) -> Option<Slot> {
+    connections
+      .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |taken| (taken < MAX_CONNECTIONS).then_some(taken + 1))
+      .ok()?;
+    Some(Slot(connections.clone()))
+  }
+}
+
+impl Drop for Slot {
+  fn drop(&mut self) {
+    self.0.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
+/// A stream that gives up on reading at `until`, however the reads are spread out.
+struct Deadline<'a> {
+  stream: &'a TcpStream,
+  until: Instant,
+}
+
+impl Read for Deadline<'_> {
+  fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+    let left = self.until.saturating_duration_since(Instant::now());
+
+    if left.is_zero() {
+      return Err(io::Error::new(io::ErrorKind::TimedOut, "Took too long to send the request"));
+    }
+
+    self.stream.set_read_timeout(Some(left))?;
+    self.stream.read(buffer)
+  }
+}
+
+/// Read a request from `stream` and send back the response.
+fn respond(mut stream: TcpStream, token: &str, call: &dyn Fn(&str, Value) -> Result<Value, String>) {
+  let reader = Deadline {
+    stream: &stream,
+    until: Instant::now() + READ_TIMEOUT,
+  };
+
+  let (status, body) = match read_request(reader.take(MAX_REQUEST), token) {
+    Ok(request) => route(&request, token, call),
+    Err(error) => (400, error_body(&error)),
+  };
+
+  let reason = match status {
+    200 => "OK",
+    201 => "Created",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    _ => "Internal Server Error",
+  };
+
+  let body = body.to_string();
+  let _ = write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    reason,
+    body.len(),
+    body
+  );
+}
+
+/// Read the request line, the headers the API cares about, and the body, when the request has `token`.
+fn read_request(stream: impl Read, token: &str) -> Result<Request, String> {
+  let mut reader = BufReader::new(stream);
+  let mut line = String::new();
+
+  reader.read_line(&mut line).map_err(|error| error.to_string())?;
+
+  let mut parts = line.split_whitespace();
+  let mut request = Request {
+    method: parts.next().unwrap_or_default().to_string(),
+    path: parts.next().unwrap_or_default().to_string(),
+    ..Default::default()
+  };
+
+  let mut length = 0;
+
+  loop {
+    line.clear();
+
+    if reader.read_line(&mut line).map_err(|error| error.to_string())? == 0 || line.trim().is_empty() {
+      break;
+    }
+
+    let Some((name, value)) = line.split_once(':') else {
+      continue;
+    };
+
+    match name.trim().to_ascii_lowercase().as_str() {
+      "content-length" => length = value.trim().parse().map_err(|_| "Invalid Content-Length".to_string())?,
+      "authorization" => request.token = value.trim().strip_prefix("Bearer ").map(|token| token.trim().to_string()),
+      _ => {}
+    }
+  }
+
+  // not worth reading the body of a request that's only getting turned away
+  if !authorized(&request, token) {
+    return Ok(request);
+  }
+
+  if length > MAX_BODY {
+    return Err("Request body too large".to_string());
+  }
+
+  let mut body = vec![0; length];
+  reader.read_exact(&mut body).map_err(|error| error.to_string())?;
+  request.body = String::from_utf8(body).map_err(|_| "Request body isn't UTF-8".to_string())?;
+
+  Ok(request)
+}
+
+/// Get the status and body answering `request`, calling on the timers through `call` once it has the right `token`.
+fn route(request: &Request, token: &str, call: &dyn Fn(&str, Value) -> Result<Value, String>) -> (u16, Value) {
+  if !authorized(request, token) {
+    return (401, error_body("Missing or wrong token"));
+  }
+
+  let path = request.path.split('?').next().unwrap_or_default().trim_end_matches('/');
+  let segments = path.split('/').skip(1).collect::<Vec<_>>();
+
+  match (request.method.as_str(), segments.as_slice()) {
+    ("GET", ["timers"]) => answer(200, 500, call("query", Value::Object(Vec::new()))),
+    ("POST", ["timers"]) => match json::parse(&request.body) {
+      Ok(params @ Value::Object(_)) if LOCAL_PARAMS.iter().any(|name| params.get(name).is_some()) => (400, error_body("exec, cwd, and env can only be given from this machine")),
+      Ok(params @ Value::Object(_)) => answer(201, 400, call("start", params)),
+      _ => (400, error_body("Expected a JSON object with a duration or profile")),
+    },
+    ("DELETE", ["timers", id]) => match id.parse::<u64>() {
+      Ok(id) => answer(200, 404, call("cancel", Value::object([("id", (id as f64).into())]))),
+      Err(_) => (404, error_body(&format!("No timer with id {}", id))),
+    },
+    (_, ["timers"]) | (_, ["timers", _]) => (405, error_body("Method not allowed")),
+    _ => (404, error_body("Not found")),
+  }
+}
+
+/// Get the response for `result`, with `ok` as the status when it worked and `failed` when it didn't.
+fn answer(ok: u16, failed: u16, result: Result<Value, String>) -> (u16, Value) {
+  match result {
+    Ok(value) => (ok, value),
+    Err(error) => (failed, error_body(&error)),
+  }
+}
+
+/// Get the body for an error saying `message`.
+fn error_body(message: &str) -> Value {
+  Value::object([("error", message.into())])
+}
+
+/// Check whether `request` came with `token`.
+fn authorized(request: &Request, token: &str) -> bool {
+  request.token.as_deref().is_some_and(|given| same(given, token))
+}
+
+/// Compare tokens in the same time wherever they differ, so the time taken gives nothing away.
+fn same(given: &str, token: &str) -> bool {
+  given
+    .bytes()
+    .zip(token.bytes())
+    .fold(given.len() ^ token.len(), |difference, (a, b)| difference | usize::from(a ^ b))
+    == 0
+}
+
+/// Get the file the token is kept in, when the config doesn't give one.
+pub fn token_path() -> PathBuf {
+  data_dir().join("http-token")
+}
+
+/// Get the token clients need, made up and kept in `token_path` the first time.
+pub fn token() -> io::Result<String> {
+  let path = token_path();
+
+  if let Ok(token) = fs::read_to_string(&path) {
+    if !token.trim().is_empty() {
+      return Ok(token.trim().to_string());
+    }
+  }
+
+  let mut bytes = [0; TOKEN_BYTES];
+  random(&mut bytes)?;
+  let token = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+
+  fs::create_dir_all(data_dir())?;
+
+  let mut options = fs::OpenOptions::new();
+  options.write(true).create(true).truncate(true);
+
+  // only for the user to read
+  #[cfg(unix)]
+  std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+  options.open(&path)?.write_all(token.as_bytes())?;
+
+  Ok(token)
+}
+
+/// Fill `bytes` from the system's secure random number generator, for a token nobody can guess.
+#[cfg(unix)]
+fn random(bytes: &mut [u8]) -> io::Result<()> {
+  fs::File::open("/dev/urandom")?.read_exact(bytes)
+}
+
+/// Fill `bytes` from the system's secure random number generator, for a token nobody can guess.
+#[cfg(windows)]
+fn random(bytes: &mut [u8]) -> io::Result<()> {
+  use windows_sys::Win32::Security::Cryptography::{BCryptGenRandom, BCRYPT_USE_SYSTEM_PREFERRED_RNG};
+
+  let status = unsafe { BCryptGenRandom(std::ptr::null_mut(), bytes.as_mut_ptr(), bytes.len() as u32, BCRYPT_USE_SYSTEM_PREFERRED_RNG) };
+
+  if status == 0 {
+    Ok(())
+  } else {
+    Err(io::Error::other(format!("BCryptGenRandom failed with {:#x}", status)))
+  }
+}
+
+/// Fill `bytes` from the system's secure random number generator, which there isn't one of to use here.
+#[cfg(not(any(unix, windows)))]
+fn random(_: &mut [u8]) -> io::Result<()> {
+  Err(io::Error::new(io::ErrorKind::Unsupported, "no secure random number generator to make a token with"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn routes() {
+    let call = |method: &str, params: Value| match method {
+      "query" => Ok(Value::Array(Vec::new())),
+      "cancel" if params.get("id") == Some(&Value::from(3.0)) => Ok(Value::object([("id", 3.0.into())])),
+      "cancel" => Err("No timer with id 4".to_string()),
+      "start" => Ok(Value::object([("id", 1.0.into())])),
+      _ => Err("Unexpected".to_string()),
+    };
+    let request = |method: &str, path: &str, token: Option<&str>| Request {
+      method: method.to_string(),
+      path: path.to_string(),
+      token: token.map(str::to_string),
+      body: String::new(),
+    };
+    let start = |body: &str| Request {
+      body: body.to_string(),
+      ..request("POST", "/timers", Some("secret"))
+    };
+
+    assert_eq!(route(&request("GET", "/timers", None), "secret", &call).0, 401);
+    assert_eq!(route(&request("GET", "/timers", Some("guess!")), "secret", &call).0, 401);
+    assert_eq!(route(&request("GET", "/timers/", Some("secret")), "secret", &call), (200, Value::Array(Vec::new())));
+    assert_eq!(route(&request("DELETE", "/timers/3", Some("secret")), "secret", &call).0, 200);
+    assert_eq!(route(&request("DELETE", "/timers/4", Some("secret")), "secret", &call).0, 404);
+    assert_eq!(route(&request("POST", "/timers", Some("secret")), "secret", &call).0, 400);
+    assert_eq!(route(&request("PUT", "/timers", Some("secret")), "secret", &call).0, 405);
+    assert_eq!(route(&request("GET", "/", Some("secret")), "secret", &call).0, 404);
+
+    // running commands is left to clients on this machine
+    assert_eq!(route(&start(r#"{"duration":"25m"}"#), "secret", &call).0, 201);
+    assert_eq!(route(&start(r#"{"duration":"25m","exec":"rm -rf ~"}"#), "secret", &call).0, 400);
+    assert_eq!(route(&start(r#"{"duration":"25m","env":{"PATH":"/tmp"}}"#), "secret", &call).0, 400);
+  }
+
+  #[test]
+  fn requests() {
+    let read = |request: &str| read_request(request.as_bytes(), "secret");
+
+    let request = read("POST /timers HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: 2\r\n\r\n{}").unwrap();
+    assert_eq!((request.method.as_str(), request.path.as_str(), request.body.as_str()), ("POST", "/timers", "{}"));
+
+    // the body of a request without the token is left unread, however large it says it is
+    let request = read("POST /timers HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n").unwrap();
+    assert_eq!((request.token, request.body.as_str()), (None, ""));
+    assert!(read("POST /timers HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: 999999999\r\n\r\n").is_err());
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let slots = (0..MAX_CONNECTIONS).map_while(|_| Slot::take(&connections)).collect::<Vec<_>>();
+    assert_eq!(slots.len(), MAX_CONNECTIONS);
+    assert!(Slot::take(&connections).is_none());
+    drop(slots);
+    assert!(Slot::take(&connections).is_some());
+  }
+
+  #[test]
+  fn tokens() {
+    assert!(same("secret", "secret"));
+    assert!(!same("secreT", "secret"));
+    // a prefix of the token isn't enough
+    assert!(!same("sec", "secret"));
+    assert!(!same("", "secret"));
+
+    let (mut first, mut second) = ([0; TOKEN_BYTES], [0; TOKEN_BYTES]);
+    random(&mut first).unwrap();
+    random(&mut second).unwrap();
+    assert_ne!(first, second);
+  }
+}
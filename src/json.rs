@@ -0,0 +1,305 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+use crate::webhook::json_string;
+
+/// Most arrays and objects a value can be nested in.
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Value>),
+  /// Members in the order they were written.
+  Object(Vec<(String, Value)>),
+}
+
+impl Value {
+  /// Get the member called `key`, if this is an object with one.
+  pub fn get(&self, key: &str) -> Option<&Value> {
+    match self {
+      Self::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(value) => Some(value),
+      _ => None,
+    }
+  }
+
+  pub fn as_f64(&self) -> Option<f64> {
+    match self {
+      Self::Number(value) => Some(*value),
+      _ => None,
+    }
+  }
+
+  /// Build an object from `members`.
+  pub fn object<const N: usize>(members: [(&str, Value); N]) -> Self {
+    Self::Object(members.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Self::String(value.to_string())
+  }
+}
+
+impl From<f64> for Value {
+  fn from(value: f64) -> Self {
+    Self::Number(value)
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Self {
+    Self::Bool(value)
+  }
+}
+
+impl fmt::Display for Value {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Null => write!(f, "null"),
+      Self::Bool(value) => write!(f, "{}", value),
+      // JSON has no infinities or NaN
+      Self::Number(value) if !value.is_finite() => write!(f, "null"),
+      Self::Number(value) => write!(f, "{}", value),
+      Self::String(value) => write!(f, "{}", json_string(value)),
+      Self::Array(values) => {
+        write!(f, "[")?;
+
+        for (index, value) in values.iter().enumerate() {
+          if index > 0 {
+            write!(f, ",")?;
+          }
+
+          write!(f, "{}", value)?;
+        }
+
+        write!(f, "]")
+      }
+      Self::Object(members) => {
+        write!(f, "{{")?;
+
+        for (index, (name, value)) in members.iter().enumerate() {
+          if index > 0 {
+            write!(f, ",")?;
+          }
+
+          write!(f, "{}:{}", json_string(name), value)?;
+        }
+
+        write!(f, "}}")
+      }
+    }
+  }
+}
+
+/// Parse `text` as a single JSON value.
+pub fn parse(text: &str) -> Result<Value, String> {
+  let mut chars = text.chars().peekable();
+  let value = parse_value(&mut chars, 0)?;
+
+  skip_whitespace(&mut chars);
+
+  match chars.next() {
+    Some(c) => Err(format!("Unexpected {} after value", c)),
+    None => Ok(value),
+  }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+  while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+}
+
+/// Take `expected` from the input, which is already known to start with its first character.
+fn expect_word(chars: &mut Peekable<Chars>, expected: &str, value: Value) -> Result<Value, String> {
+  for c in expected.chars() {
+    if chars.next() != Some(c) {
+      return Err(format!("Expected {}", expected));
+    }
+  }
+
+  Ok(value)
+}
+
+/// Parse the value next in the input, `depth` arrays and objects in.
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Value, String> {
+  skip_whitespace(chars);
+
+  // input comes over the network, where enough brackets would otherwise run the stack out
+  if depth >= MAX_DEPTH && matches!(chars.peek(), Some('[' | '{')) {
+    return Err(format!("Nested more than {} deep", MAX_DEPTH));
+  }
+
+  match chars.peek() {
+    Some('n') => expect_word(chars, "null", Value::Null),
+    Some('t') => expect_word(chars, "true", Value::Bool(true)),
+    Some('f') => expect_word(chars, "false", Value::Bool(false)),
+    Some('"') => parse_string(chars).map(Value::String),
+    Some('[') => {
+      chars.next();
+      let mut values = Vec::new();
+
+      skip_whitespace(chars);
+
+      if chars.next_if_eq(&']').is_some() {
+        return Ok(Value::Array(values));
+      }
+
+      loop {
+        values.push(parse_value(chars, depth + 1)?);
+        skip_whitespace(chars);
+
+        match chars.next() {
+          Some(',') => {}
+          Some(']') => return Ok(Value::Array(values)),
+          _ => return Err("Expected , or ] in array".to_string()),
+        }
+      }
+    }
+    Some('{') => {
+      chars.next();
+      let mut members = Vec::new();
+
+      skip_whitespace(chars);
+
+      if chars.next_if_eq(&'}').is_some() {
+        return Ok(Value::Object(members));
+      }
+
+      loop {
+        skip_whitespace(chars);
+
+        if chars.peek() != Some(&'"') {
+          return Err("Expected a string key in object".to_string());
+        }
+
+        let name = parse_string(chars)?;
+        skip_whitespace(chars);
+
+        if chars.next() != Some(':') {
+          return Err("Expected : after key in object".to_string());
+        }
+
+        members.push((name, parse_value(chars, depth + 1)?));
+        skip_whitespace(chars);
+
+        match chars.next() {
+          Some(',') => {}
+          Some('}') => return Ok(Value::Object(members)),
+          _ => return Err("Expected , or } in object".to_string()),
+        }
+      }
+    }
+    Some(c) if *c == '-' || c.is_ascii_digit() => {
+      let mut number = String::new();
+
+      while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+        number.push(c);
+      }
+
+      number.parse().map(Value::Number).map_err(|_| format!("Invalid number: {}", number))
+    }
+    Some(c) => Err(format!("Unexpected {}", c)),
+    None => Err("Unexpected end of input".to_string()),
+  }
+}
+
+/// Parse a string, starting at its opening quote.
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+  chars.next();
+  let mut output = String::new();
+
+  loop {
+    match chars.next() {
+      Some('"') => return Ok(output),
+      Some('\\') => match chars.next() {
+        Some('"') => output.push('"'),
+        Some('\\') => output.push('\\'),
+        Some('/') => output.push('/'),
+        Some('b') => output.push('\u{8}'),
+        Some('f') => output.push('\u{c}'),
+        Some('n') => output.push('\n'),
+        Some('r') => output.push('\r'),
+        Some('t') => output.push('\t'),
+        Some('u') => {
+          let mut code = parse_hex(chars)?;
+
+          // characters outside the basic plane come as a pair of surrogates
+          if (0xd800..0xdc00).contains(&code) {
+            if chars.next() != Some('\\') || chars.next() != Some('u') {
+              return Err("Unpaired surrogate in string".to_string());
+            }
+
+            let low = parse_hex(chars)?;
+
+            if !(0xdc00..0xe000).contains(&low) {
+              return Err("Unpaired surrogate in string".to_string());
+            }
+
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+          }
+
+          output.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+        _ => return Err("Invalid escape in string".to_string()),
+      },
+      Some(c) => output.push(c),
+      None => return Err("Unterminated string".to_string()),
+    }
+  }
+}
+
+/// Parse the four hex digits of a `\u` escape.
+fn parse_hex(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+  let digits = chars.take(4).collect::<String>();
+
+  u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape: {}", digits))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let text = r#"{"jsonrpc":"2.0","id":1,"params":{"by":"5m","list":[true,null,-1.5e2]},"name":"tea \"time\" ⏰"}"#;
+    let value = parse(text).unwrap();
+
+    assert_eq!(value.get("id"), Some(&Value::Number(1.0)));
+    assert_eq!(value.get("params").and_then(|params| params.get("by")).and_then(Value::as_str), Some("5m"));
+    assert_eq!(value.to_string(), text.replace("-1.5e2", "-150"));
+  }
+
+  #[test]
+  fn escapes() {
+    assert_eq!(parse(r#""a\nb\u00e9\ud83d\ude00""#), Ok(Value::String("a\nbé😀".to_string())));
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(parse("{").is_err());
+    assert!(parse("[1,]").is_err());
+    assert!(parse("nul").is_err());
+    assert!(parse("1 2").is_err());
+  }
+
+  #[test]
+  fn nesting() {
+    let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    assert!(parse(&nested(MAX_DEPTH)).is_ok());
+    assert_eq!(parse(&nested(MAX_DEPTH + 1)), Err("Nested more than 128 deep".to_string()));
+    // a request body's worth of brackets is turned away rather than running the stack out
+    assert!(parse(&"[{\"a\":".repeat(32 * 1024)).is_err());
+  }
+}
@@ -0,0 +1,174 @@
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{self, Seek, Write},
+  path::{Path, PathBuf},
+  process::exit,
+  thread::sleep,
+  time::{Duration, Instant},
+};
+
+/// How long a daemon being taken over has to stop before it's killed outright.
+const TAKEOVER_GRACE: Duration = Duration::from_secs(3);
+/// How often to check whether the daemon being taken over has stopped.
+const TAKEOVER_RETRY: Duration = Duration::from_millis(50);
+/// How long to wait on another process holding a lock that's only ever held briefly, like the history's.
+const WAIT_FOR: Duration = Duration::from_secs(5);
+/// How often to check whether a lock being waited on has been let go.
+const WAIT_RETRY: Duration = Duration::from_millis(10);
+
+/// Holding a lock file, like being the daemon for this user, given up once dropped or the process ends, even if it crashes.
+pub struct Lock {
+  _file: File,
+}
+
+/// Get the lock file for the daemon serving on `socket`, next to it.
+pub fn path_for(socket: &Path) -> PathBuf {
+  socket.with_extension("lock")
+}
+
+/// Become the only daemon holding the lock at `path`, or exit with an error saying which process already is.
+///
+/// With `takeover`, a daemon already holding it is stopped first, killing it if it doesn't stop in time.
+pub fn acquire(path: &Path, takeover: bool) -> Lock {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).expect("Failed to create lock directory");
+  }
+
+  let mut stopping = None;
+
+  loop {
+    // left behind by a daemon that went without cleaning up, say when it crashed
+    let previous = holder_pid(path);
+
+    match try_lock(path) {
+      Some(mut file) => {
+        if let Some(pid) = previous.filter(|_| stopping.is_none()) {
+          eprintln!("The last daemon (pid {}) didn't shut down cleanly, taking over from it", pid);
+        }
+
+        let _ = file.set_len(0).and_then(|_| file.rewind()).and_then(|_| write!(file, "{}", std::process::id()));
+
+        return Lock { _file: file };
+      }
+      None if !takeover => {
+        match previous {
+          Some(pid) => eprintln!("timr is already serving (pid {}), use --takeover to replace it", pid),
+          None => eprintln!("timr is already serving, use --takeover to replace it"),
+        }
+
+        exit(1);
+      }
+      None => {
+        let pid = previous.unwrap_or(0);
+        let started = *stopping.get_or_insert_with(|| {
+          eprintln!("Stopping the daemon already serving (pid {})", pid);
+          stop(pid, false);
+          Instant::now()
+        });
+
+        if started.elapsed() >= TAKEOVER_GRACE {
+          stop(pid, true);
+          stopping = Some(Instant::now());
+        }
+
+        sleep(TAKEOVER_RETRY);
+      }
+    }
+  }
+}
+
+/// Take the lock at `path`, waiting on any other process holding it, for changes to a file that mustn't land partway through each other.
+pub fn wait(path: &Path) -> io::Result<Lock> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  let started = Instant::now();
+
+  loop {
+    if let Some(file) = try_lock(path) {
+      return Ok(Lock { _file: file });
+    }
+
+    if started.elapsed() >= WAIT_FOR {
+      return Err(io::Error::new(io::ErrorKind::TimedOut, format!("{} is held by another process", path.display())));
+    }
+
+    sleep(WAIT_RETRY);
+  }
+}
+
+/// Get the daemon holding the lock at `path`, if one is, for saying which one isn't answering.
+pub fn holder(path: &Path) -> Option<i32> {
+  // taking the lock means nobody had it, and it goes again as soon as it's dropped
+  if try_lock(path).is_some() {
+    return None;
+  }
+
+  holder_pid(path)
+}
+
+/// Get the process last written to the lock at `path`.
+fn holder_pid(path: &Path) -> Option<i32> {
+  fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Take the lock at `path` unless another process has it.
+#[cfg(unix)]
+fn try_lock(path: &Path) -> Option<File> {
+  use std::os::fd::AsRawFd;
+
+  let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path).ok()?;
+
+  (unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0).then_some(file)
+}
+
+/// Take the lock at `path` unless another process has it open, which Windows allows only one writer at a time to do.
+#[cfg(windows)]
+fn try_lock(path: &Path) -> Option<File> {
+  use std::os::windows::fs::OpenOptionsExt;
+
+  // others can still read which process it is
+  const FILE_SHARE_READ: u32 = 1;
+
+  OpenOptions::new()
+    .read(true)
+    .write(true)
+    .create(true)
+    .truncate(false)
+    .share_mode(FILE_SHARE_READ)
+    .open(path)
+    .ok()
+}
+
+/// Take the lock at `path`, which is always there to take without Unix or Windows.
+#[cfg(not(any(unix, windows)))]
+fn try_lock(path: &Path) -> Option<File> {
+  OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path).ok()
+}
+
+/// Ask the daemon `pid` to stop, or kill it if `force`.
+#[cfg(unix)]
+fn stop(pid: i32, force: bool) {
+  if pid > 0 {
+    unsafe {
+      libc::kill(pid, if force { libc::SIGKILL } else { libc::SIGTERM });
+    }
+  }
+}
+
+/// Stop the daemon `pid`, which Windows can only do by force.
+#[cfg(windows)]
+fn stop(pid: i32, _force: bool) {
+  if pid > 0 {
+    let _ = std::process::Command::new("taskkill")
+      .args(["/F", "/PID", &pid.to_string()])
+      .stdout(std::process::Stdio::null())
+      .stderr(std::process::Stdio::null())
+      .status();
+  }
+}
+
+/// Stop the daemon `pid`, which can't be done without Unix or Windows.
+#[cfg(not(any(unix, windows)))]
+fn stop(_pid: i32, _force: bool) {}
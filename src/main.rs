@@ -1,288 +1,2762 @@
 use std::{
-  fs,
-  io::{stdout, Write},
+  fmt::Write as _,
+  io::{stdout, IsTerminal, Write},
   path::PathBuf,
   process::exit,
-  sync::mpsc::channel,
-  thread::sleep,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver, RecvTimeoutError},
+  },
+  thread::{sleep, JoinHandle},
   time::{Duration, Instant},
 };
 
-use serde::Deserialize;
+mod alerts;
+mod awake;
+mod client;
+mod clipboard;
+mod commands;
+mod config;
+mod dashboard;
+mod dbus;
+mod discord;
+mod events;
+mod export;
+mod history;
+mod hook;
+mod http;
+mod json;
+mod lock;
+mod media;
+mod metrics;
+mod notify;
+mod push;
+mod relay;
+mod render;
+mod serve;
+mod service;
+mod share;
+mod signals;
+mod slack;
+mod sound;
+mod speech;
+mod state;
+mod stats;
+mod sync;
+mod taskbar;
+mod terminal;
+mod timer;
+mod toggl;
+mod webhook;
+
+use render::{Background, Emphasis, Face, PercentStyle, Theme};
+use terminal::{input::Key, Align, BellPattern};
+use timer::{Stage, Timer};
+
+const BAR_UPDATE_INTERVAL: u128 = 16; // milliseconds
+const BAR_MAX_WIDTH: u16 = 30;
+const BAR_MIN_WIDTH: u16 = 10; // below this we fall back to the compact spinner
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_INTERVAL: u128 = 100; // milliseconds
+const STAGE_BAR_WIDTH: u16 = 10;
+const HOURGLASS_INTERVAL: u128 = 250; // milliseconds
+const EXTEND_STEP: Duration = Duration::from_secs(60);
+const EMPHASIS_DURATION: Duration = Duration::from_secs(10);
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(30);
+const CANCEL_PROGRESS_DURATION: Duration = Duration::from_millis(500); // how long to show the error state after cancelling
+const CLOCK_FORMAT_12_HOUR: &str = "%_I:%M%P";
+const CLOCK_FORMAT_24_HOUR: &str = "%H:%M";
+const FLASH_COUNT: u8 = 3;
+const KITTY_IMAGE_ID: u32 = 1;
+const SIXEL_ROWS: u16 = 4; // three rows of digits and one for the bar
+const ACTION_WAIT: Duration = Duration::from_secs(60); // how long to keep listening to the notification's buttons once finished
+const ALARM_INTERVAL: Duration = Duration::from_secs(3); // time between rings of the alarm
+const FLASH_INTERVAL: u64 = 150; // milliseconds
+
+/// How timers are run and shown, resolved from the command line and config file.
+struct Options {
+  /// Show a single line spinner in place of the progress bar.
+  compact: bool,
+  /// Flash the screen when timers finish.
+  flash: bool,
+  /// Keep ringing when timers finish until a key is pressed or the notification is clicked.
+  alarm: bool,
+  /// What to do about alerts while the desktop is in Do Not Disturb.
+  do_not_disturb: notify::DoNotDisturb,
+  /// Say aloud when timers finish.
+  speak: bool,
+  /// How often to say the time remaining aloud.
+  speak_every: Option<Duration>,
+  /// How long before the end to send a warning notification.
+  warn: Option<Duration>,
+  /// Audio file played along with the warning.
+  warn_sound: Option<PathBuf>,
+  /// Show a desktop notification as each stage starts and when timers finish.
+  notify: bool,
+  /// Ask the terminal to show a notification when timers finish, which works over SSH.
+  osc_notify: bool,
+  /// Copy a summary of each timer to the clipboard when it finishes, for work logs.
+  copy_summary: bool,
+  /// Show the time left as an iTerm2 badge over the terminal, where it can be seen from across the room.
+  badge: bool,
+  /// Keep a notification up while timers run, following their progress.
+  live_notify: bool,
+  /// Stop the machine from going to sleep while timers run.
+  keep_awake: bool,
+  /// Pause media players when timers finish, so the alarm can be heard.
+  pause_media: bool,
+  /// Serve the timers on the session bus, to be shown and controlled by desktop widgets.
+  dbus: bool,
+  /// Address to serve Prometheus metrics on.
+  metrics_listen: Option<String>,
+  /// File to write Prometheus metrics to, for node_exporter's textfile collector.
+  metrics_file: Option<PathBuf>,
+  /// Address to share the timers on, for `timr join` to show on other machines.
+  share: Option<String>,
+  /// URL to POST to as timers start, finish, or are cancelled.
+  webhook: Option<String>,
+  /// Relay to send finished and cancelled timers to, to be alerted on another machine.
+  relay: Option<String>,
+  /// Shell command run on every event, with the timer in its environment.
+  exec: Option<String>,
+  /// Body to send the webhook in place of the JSON payload, with placeholders filled in.
+  webhook_body: Option<String>,
+  /// Extra headers to send the webhook, like `Authorization: Bearer token`.
+  webhook_headers: Vec<String>,
+  /// FIFO to write events to as they happen, one JSON object per line.
+  event_pipe: Option<PathBuf>,
+  /// Slack webhook to post to when timers finish.
+  slack: Option<config::Slack>,
+  /// Toggl Track account to add timers to as they finish.
+  toggl: Option<config::Toggl>,
+  /// Ask why the timers were cancelled, when done from the keyboard, for the history.
+  ask_reason: bool,
+  /// Profile counting as a pomodoro, with a badge saying how today's going, like `🍅 4/8 today`, for its header.
+  pomodoro: Option<(String, String)>,
+  /// Discord webhook to post to when timers finish or are cancelled.
+  discord: Option<String>,
+  /// Services to push a notification to when timers finish.
+  push: Option<config::Push>,
+  /// Where the history is kept.
+  history_backend: config::Backend,
+  /// Audio file played as each stage starts and when timers finish.
+  sound: Option<PathBuf>,
+  /// How loud to play sounds, from 0 to 100.
+  volume: u8,
+  /// Sounds for each event, played when the profile or command line don't give one.
+  sounds: sound::Theme,
+  /// Alerts for each event from the config, in place of the usual ones.
+  routes: config::Routes,
+  /// Pattern to ring the terminal bell in.
+  bell: BellPattern,
+  /// Show the elapsed and total time next to the time remaining.
+  show_elapsed: bool,
+  /// Show the time timers will finish.
+  show_eta: bool,
+  /// Print plain status lines in place of redrawing, for screen readers.
+  accessible: bool,
+  /// How often to print a status line when not redrawing.
+  interval: Duration,
+  /// Keep the timers on the bottom rows, letting other output scroll above them.
+  pin: bool,
+  /// Arguments the timers were started with, saved so `timr resume` can pick them back up if this process is lost.
+  args: Vec<String>,
+  /// Take over the screen with a column filling up along its edge and large digits beside it.
+  vertical: bool,
+  /// Show the line naming each timer above its progress.
+  show_header: bool,
+  /// Show the current time.
+  show_clock: bool,
+  /// Show the percentage done.
+  show_percent: bool,
+  /// How the percentage is written.
+  percent_style: PercentStyle,
+  /// strftime format for the current time.
+  clock_format: String,
+  /// Where timers sit across the terminal.
+  align: Align,
+  /// How the progress is drawn.
+  face: Face,
+  /// How the time remaining stands out as timers are about to finish.
+  emphasis: Emphasis,
+  /// How long before the end the emphasis starts.
+  emphasis_for: Duration,
+  /// Colors to draw timers with.
+  theme: Theme,
+}
+
+/// A timer being run, along with what it is shown as.
+struct Running {
+  label: String,
+  /// Name of the profile the timer was started from, if any.
+  profile: Option<String>,
+  /// Tags the timer was given, to group it by in the history.
+  tags: Vec<String>,
+  notification: notify::Template,
+  /// Notification following the timer's progress, while it runs.
+  live: Option<notify::Live>,
+  /// Audio file from the profile, in place of the one from the options.
+  sound: Option<PathBuf>,
+  /// Volume from the profile, in place of the one from the options.
+  volume: Option<u8>,
+  /// Bell pattern from the profile, in place of the one from the options.
+  bell: Option<BellPattern>,
+  /// Slack channel from the profile, in place of the one from the options.
+  slack_channel: Option<String>,
+  /// Slack emoji from the profile, in place of the one from the options.
+  slack_emoji: Option<String>,
+  /// Discord webhook from the profile, in place of the one from the options.
+  discord_webhook: Option<String>,
+  /// Whether the profile wants the finish said aloud, in place of the options.
+  speak: Option<bool>,
+  /// What to say when the timer finishes, in place of the default.
+  speech: Option<String>,
+  /// Whether to turn on Do Not Disturb while the timer runs, as the profile asks.
+  focus: bool,
+  /// How many intervals of `--speak-every` were left when last checked, to say the time remaining as each one passes.
+  spoken_step: Option<u64>,
+  /// Whether the warning before the end has been given.
+  warned: bool,
+  timer: Timer,
+  /// Stage the timer was on as of the last frame, to spot it moving on.
+  stage_index: usize,
+  /// When the timer was started, for webhooks.
+  started_at: chrono::DateTime<chrono::Local>,
+  /// When the timer finished, to show in place of it while the others run.
+  finished_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+fn main() {
+  // encourage control characters on Windows (https://learn.microsoft.com/en-us/windows/console/console-virtual-terminal-sequences)
+  #[cfg(target_os = "windows")]
+  {
+    use windows::Win32::System::Console::{GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE};
+
+    unsafe {
+      let handle = GetStdHandle(STD_OUTPUT_HANDLE).expect("Failed to get stdout");
+      let mut mode: CONSOLE_MODE = CONSOLE_MODE(0);
+
+      GetConsoleMode(handle, &mut mode).expect("Failed to get console mode");
+
+      if !mode.contains(ENABLE_VIRTUAL_TERMINAL_PROCESSING) {
+        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING).expect("Failed to set console mode");
+      }
+    }
+  }
+
+  let args = std::env::args().collect::<Vec<String>>();
+  let args = args.split_at(1).1; // remove self from args list
+
+  if args.is_empty() {
+    print_help();
+    return;
+  }
+
+  if args[0] == "tmux-status" {
+    commands::tmux_status();
+    return;
+  }
+
+  if args[0] == "serve" {
+    let mut stdio = false;
+    let mut socket = None;
+    let mut takeover = false;
+    let mut http = None;
+    let mut args = args[1..].iter();
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--stdio" => stdio = true,
+        "--takeover" => takeover = true,
+        "--socket" => socket = Some(PathBuf::from(next_value(&mut args, arg))),
+        "--http" => http = Some(next_value(&mut args, arg).to_string()),
+        _ => {
+          eprintln!("Unknown option: {}", arg);
+          exit(1);
+        }
+      }
+    }
+
+    if stdio {
+      if http.is_some() {
+        eprintln!("--http can't be used with --stdio");
+        exit(1);
+      }
+
+      serve::stdio();
+      return;
+    }
+
+    #[cfg(unix)]
+    {
+      serve::socket(socket.unwrap_or_else(serve::socket_path), takeover, http);
+      return;
+    }
+
+    #[cfg(windows)]
+    {
+      let _ = socket;
+      serve::pipe(takeover, http);
+      return;
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+      let _ = (socket, takeover, http);
+      eprintln!("timr serve needs --stdio on this platform");
+      exit(1);
+    }
+  }
+
+  if args[0] == "service" {
+    // only ever for the user running it, which --user spells out
+    let user = args.iter().skip(2).all(|arg| arg == "--user");
+
+    match args.get(1).map(String::as_str) {
+      Some("install") if user => service::install(),
+      Some("uninstall") if user => service::uninstall(),
+      _ => {
+        eprintln!("Usage: timr service install|uninstall [--user]");
+        exit(1);
+      }
+    }
+
+    return;
+  }
+
+  if args[0] == "relay" {
+    let mut listen = relay::DEFAULT_ADDRESS.to_string();
+    let mut args = args[1..].iter();
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--listen" => listen = next_value(&mut args, arg).to_string(),
+        _ => {
+          eprintln!("Unknown option: {}", arg);
+          exit(1);
+        }
+      }
+    }
+
+    relay::listen(&listen);
+    return;
+  }
+
+  if args[0] == "export" {
+    let Some(format) = args.get(1) else {
+      eprintln!("Missing format for export");
+      exit(1);
+    };
+
+    commands::export(parse_value(format));
+    return;
+  }
+
+  if args[0] == "history" {
+    commands::history(&args[1..]);
+    return;
+  }
+
+  if args[0] == "note" {
+    commands::note(&args[1..]);
+    return;
+  }
+
+  if args[0] == "stats" {
+    commands::stats(&args[1..]);
+    return;
+  }
+
+  if args[0] == "report" {
+    commands::report(&args[1..]);
+    return;
+  }
+
+  if args[0] == "profiles" {
+    commands::profiles(&args[1..]);
+    return;
+  }
+
+  if args[0] == "prompt" {
+    commands::prompt();
+    return;
+  }
+
+  if args[0] == "menubar" {
+    commands::menubar();
+    return;
+  }
+
+  if args[0] == "start" {
+    commands::start(&args[1..]);
+    return;
+  }
+
+  if args[0] == "pin" {
+    match args.get(1).map(String::as_str) {
+      Some("--next") => commands::pin(None, true),
+      Some(name) => commands::pin(Some(name), false),
+      None => {
+        eprintln!("timr pin takes the name of the timer to pin, or --next");
+        exit(1);
+      }
+    }
+
+    return;
+  }
+
+  if args[0] == "unpin" {
+    commands::unpin();
+    return;
+  }
+
+  if args[0] == "list" {
+    let mut filter = commands::Filter::default();
+    let mut args = args[1..].iter();
+
+    while let Some(arg) = args.next() {
+      if !parse_filter(arg, &mut args, &mut filter) {
+        eprintln!("Unknown option: {}", arg);
+        exit(1);
+      }
+    }
+
+    commands::list(filter);
+    return;
+  }
+
+  if args[0] == "extend" {
+    match &args[1..] {
+      [by] => commands::extend(None, by),
+      [name, by] => commands::extend(Some(name), by),
+      _ => {
+        eprintln!("Usage: timr extend [name] duration");
+        exit(1);
+      }
+    }
+
+    return;
+  }
+
+  if args[0] == "status" {
+    let mut json = false;
+
+    for arg in &args[1..] {
+      match arg.as_str() {
+        "--json" => json = true,
+        _ => {
+          eprintln!("Unknown option: {}", arg);
+          exit(1);
+        }
+      }
+    }
+
+    commands::status(json);
+    return;
+  }
+
+  if args[0] == "watch" {
+    let mut json = false;
+    let mut ticks = false;
+
+    for arg in &args[1..] {
+      match arg.as_str() {
+        "--json" => json = true,
+        "--ticks" => ticks = true,
+        _ => {
+          eprintln!("Unknown option: {}", arg);
+          exit(1);
+        }
+      }
+    }
+
+    commands::watch(json, ticks);
+    return;
+  }
+
+  if args[0] == "statusbar" {
+    let mut format = commands::StatusFormat::Polybar;
+    let mut args = args[1..].iter();
+
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--format" => format = parse_value(next_value(&mut args, arg)),
+        _ => {
+          eprintln!("Unknown option: {}", arg);
+          exit(1);
+        }
+      }
+    }
+
+    commands::statusbar(format);
+    return;
+  }
+
+  let lost;
+  let mut resuming = Vec::new();
+
+  let args = match args[0].parse::<state::Control>() {
+    Ok(control) => {
+      let mut name = None;
+      let mut all = false;
+      let mut filter = commands::Filter::default();
+      let mut reason = None;
+      let mut rest = args[1..].iter();
+
+      while let Some(arg) = rest.next() {
+        match arg.as_str() {
+          "--all" => all = true,
+          "--reason" if control == state::Control::Cancel => reason = Some(next_value(&mut rest, arg)),
+          _ if parse_filter(arg, &mut rest, &mut filter) => {}
+          _ if arg.starts_with('-') || name.is_some() => {
+            eprintln!("Unknown option: {}", arg);
+            exit(1);
+          }
+          _ => name = Some(arg.as_str()),
+        }
+      }
+
+      if all && name.is_some() {
+        eprintln!("Pick a timer by name or use --all, not both");
+        exit(1);
+      }
+
+      if commands::control(control, name, filter, reason) {
+        return;
+      }
+
+      // with nothing to resume, pick up the timers lost along with their terminal, started again as they were
+      let lost_wanted = control == state::Control::Resume && filter == commands::Filter::default();
+
+      let Some(found) = lost_wanted.then(|| state::take_lost(name)).flatten() else {
+        match name {
+          Some(name) => eprintln!("No timer named {}", name),
+          None if filter != commands::Filter::default() => eprintln!("No timers match"),
+          None => eprintln!("No timers running"),
+        }
+
+        exit(1);
+      };
+
+      resuming = found.timers;
+      lost = found.args;
+      &lost[..]
+    }
+    Err(_) => args,
+  };
+
+  let mut durations = Vec::new();
+  let mut name = None;
+  let mut compact = false;
+  let mut vertical = false;
+  let mut flash = false;
+  let mut notify = None;
+  let mut alarm = None;
+  let mut do_not_disturb = None;
+  let mut urgency = None;
+  let mut live_notify = None;
+  let mut osc_notify = None;
+  let mut badge = None;
+  let mut copy_summary = None;
+  let mut webhook = None;
+  let mut webhook_body = None;
+  let mut exec = None;
+  let mut relay = None;
+  let mut webhook_headers = Vec::new();
+  let mut event_pipe = None;
+  let mut keep_awake = None;
+  let mut pause_media = None;
+  let mut serve_dbus = None;
+  let mut metrics_listen = None;
+  let mut share = None;
+  let mut metrics_file = None;
+  let mut speak = None;
+  let mut speak_every = None;
+  let mut warn = None;
+  let mut warn_sound = None;
+  let mut sound = None;
+  let mut sound_theme = None;
+  let mut volume = None;
+  let mut bell = None;
+  let mut show_elapsed = false;
+  let mut show_eta = false;
+  let mut accessible = false;
+  let mut interval = ANNOUNCE_INTERVAL;
+  let mut pin = false;
+  let mut show_clock = None;
+  let mut show_percent = None;
+  let mut show_header = None;
+  let mut percent_style = None;
+  let mut clock_format = None;
+  let mut align = None;
+  let mut face = None;
+  let mut emphasis = None;
+  let mut emphasis_for = None;
+  let mut theme = None;
+  let mut background = None;
+  let mut tags = Vec::new();
+
+  // takes the display options, with the name of the timer to attach to, or the address to join, in place of durations
+  let started_with = args.to_vec();
+  let attaching = args[0] == "attach";
+  let joining = args[0] == "join";
+  let dashboarding = args[0] == "dashboard";
+  let mut args = args[usize::from(attaching || joining || dashboarding)..].iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-v" | "--version" => {
+        println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
+      }
+      "-h" | "--help" => {
+        print_help();
+        return;
+      }
+      "-c" | "--compact" => compact = true,
+      "--name" => name = Some(next_value(&mut args, arg).to_string()),
+      "--tag" => tags.push(next_value(&mut args, arg).to_string()),
+      "--vertical" => vertical = true,
+      "-f" | "--flash" => flash = true,
+      "--alarm-mode" => alarm = Some(true),
+      "--live-notify" => {
+        if !notify::live_supported() {
+          eprintln!("Live notifications are not supported on this platform");
+          exit(1);
+        }
+
+        live_notify = Some(true);
+      }
+      "--dbus" => {
+        if !dbus::supported() {
+          eprintln!("timr was built without D-Bus support");
+          exit(1);
+        }
+
+        serve_dbus = Some(true);
+      }
+      "--keep-awake" => keep_awake = Some(true),
+      "--pause-media" => pause_media = Some(true),
+      "--osc-notify" => osc_notify = Some(true),
+      "--badge" => badge = Some(true),
+      "--copy-summary" => copy_summary = Some(true),
+      "--metrics-listen" => metrics_listen = Some(next_value(&mut args, arg).to_string()),
+      "--share" => share = Some(next_value(&mut args, arg).to_string()),
+      "--metrics-file" => metrics_file = Some(PathBuf::from(next_value(&mut args, arg))),
+      "--webhook" => webhook = Some(next_value(&mut args, arg).to_string()),
+      "--webhook-body" => webhook_body = Some(next_value(&mut args, arg).to_string()),
+      "--exec" => exec = Some(next_value(&mut args, arg).to_string()),
+      "--relay" => relay = Some(next_value(&mut args, arg).to_string()),
+      "--webhook-header" => webhook_headers.push(next_value(&mut args, arg).to_string()),
+      "--event-pipe" => event_pipe = Some(PathBuf::from(next_value(&mut args, arg))),
+      "--urgency" => urgency = Some(parse_value(next_value(&mut args, arg))),
+      "--dnd" => do_not_disturb = Some(parse_value(next_value(&mut args, arg))),
+      "--speak" => speak = Some(true),
+      "--speak-every" => speak_every = Some(parse_duration(next_value(&mut args, arg))),
+      "--warn" => warn = Some(parse_duration(next_value(&mut args, arg))),
+      "--warn-sound" => {
+        if !sound::supported() {
+          eprintln!("timr was built without sound support");
+          exit(1);
+        }
+
+        warn_sound = Some(PathBuf::from(next_value(&mut args, arg)));
+      }
+      "-n" | "--notify" => {
+        if !notify::supported() {
+          eprintln!("timr was built without notification support");
+          exit(1);
+        }
+
+        notify = Some(true);
+      }
+      "--sound" => {
+        if !sound::supported() {
+          eprintln!("timr was built without sound support");
+          exit(1);
+        }
+
+        sound = Some(PathBuf::from(next_value(&mut args, arg)));
+      }
+      "--sound-theme" => sound_theme = Some(parse_value(next_value(&mut args, arg))),
+      "--volume" => match sound::parse_volume(next_value(&mut args, arg)) {
+        Ok(value) => volume = Some(value),
+        Err(error) => {
+          eprintln!("{}", error);
+          exit(1);
+        }
+      },
+      "--bell" => bell = Some(parse_value(next_value(&mut args, arg))),
+      "-e" | "--elapsed" => show_elapsed = true,
+      "--eta" => show_eta = true,
+      "-a" | "--accessible" => accessible = true,
+      "--pin" => pin = true,
+      "--no-clock" => show_clock = Some(false),
+      "--no-percent" => show_percent = Some(false),
+      "--no-header" => show_header = Some(false),
+      "--percent-style" => percent_style = Some(parse_value(next_value(&mut args, arg))),
+      "--face" => face = Some(parse_value(next_value(&mut args, arg))),
+      "--emphasis" => emphasis = Some(parse_value(next_value(&mut args, arg))),
+      "--emphasis-for" => emphasis_for = Some(parse_duration(next_value(&mut args, arg))),
+      "--theme" => theme = Some(parse_value(next_value(&mut args, arg))),
+      "--background" => background = Some(parse_value(next_value(&mut args, arg))),
+      "--align" => align = Some(parse_value(next_value(&mut args, arg))),
+      "--clock-format" => clock_format = Some(next_value(&mut args, arg).to_string()),
+      "-i" | "--interval" => interval = parse_duration(next_value(&mut args, arg)),
+      _ if arg.starts_with('-') && arg.len() > 1 => {
+        eprintln!("Unknown option: {}", arg);
+        println!("Use '{} --help' for more information", env!("CARGO_PKG_NAME"));
+
+        exit(1);
+      }
+      // every generic argument is a duration or profile, each getting its own timer
+      _ => durations.push(arg),
+    }
+  }
+
+  let attach_to = if attaching {
+    if durations.len() > 1 {
+      eprintln!("Only a single timer can be attached to");
+      exit(1);
+    }
+
+    durations.pop().cloned()
+  } else {
+    None
+  };
+
+  let join_address = if joining {
+    match durations.pop() {
+      Some(address) if durations.is_empty() => address.to_string(),
+      _ => {
+        eprintln!("timr join takes the address timers are shared at, like 192.168.1.20:7228");
+        exit(1);
+      }
+    }
+  } else {
+    String::new()
+  };
+
+  if dashboarding && !durations.is_empty() {
+    eprintln!("timr dashboard only takes display options");
+    exit(1);
+  }
+
+  if durations.is_empty() && !attaching && !joining && !dashboarding {
+    eprintln!("No duration specified");
+    exit(1);
+  }
+
+  if name.is_some() && durations.len() > 1 {
+    eprintln!("Only a single timer can be named");
+    exit(1);
+  }
+
+  let config = config::load();
+
+  let mut timers = durations
+    .into_iter()
+    .map(|duration| {
+      if duration.is_empty() {
+        eprintln!("Duration must not be empty");
+        exit(1);
+      }
+
+      let label = name.clone().unwrap_or_else(|| duration.clone());
+
+      let mut notification = notify::Template {
+        title: label.clone(),
+        urgency: urgency.or(config.urgency).unwrap_or_default(),
+        ..Default::default()
+      };
+
+      let mut profile_sound = None;
+      let mut profile_volume = None;
+      let mut profile_bell = None;
+      let mut profile_slack_channel = None;
+      let mut profile_slack_emoji = None;
+      let mut profile_discord_webhook = None;
+      let mut profile_speak = None;
+      let mut profile_speech = None;
+      let mut profile_focus = false;
+      let mut timer_tags = tags.clone();
+
+      let (stages, profile) = match duration.chars().next().unwrap() {
+        '0'..='9' => (
+          vec![Stage {
+            name: String::new(),
+            duration: parse_duration(duration),
+          }],
+          None,
+        ),
+        _ => {
+          if !config::path().exists() {
+            eprintln!("$HOME/.config/timr.toml does not exist");
+            exit(1);
+          }
+
+          let profile = config.profile(duration);
+
+          notification = notify::Template {
+            title: profile.title.clone().unwrap_or(notification.title),
+            body: profile.message.clone(),
+            icon: profile.icon.clone(),
+            urgency: profile.urgency.unwrap_or(notification.urgency),
+          };
+
+          profile_sound = profile.sound.clone();
+          profile_volume = profile.volume;
+          profile_bell = profile.bell.as_deref().map(parse_value);
+          profile_slack_channel = profile.slack_channel.clone();
+          profile_slack_emoji = profile.slack_emoji.clone();
+          profile_discord_webhook = profile.discord_webhook.clone();
+          profile_speak = profile.speak;
+          profile_speech = profile.speech.clone();
+          profile_focus = profile.focus.unwrap_or(false);
+          timer_tags = profile.tagged(&tags);
+
+          (profile.stages(), Some(duration.clone()))
+        }
+      };
+
+      let timer = Timer::new(stages);
+
+      Running {
+        label,
+        profile,
+        tags: timer_tags,
+        notification,
+        live: None,
+        sound: profile_sound,
+        volume: profile_volume,
+        bell: profile_bell,
+        slack_channel: profile_slack_channel,
+        slack_emoji: profile_slack_emoji,
+        discord_webhook: profile_discord_webhook,
+        speak: profile_speak,
+        speech: profile_speech,
+        focus: profile_focus,
+        spoken_step: None,
+        warned: false,
+        stage_index: timer.stage_index(),
+        timer,
+        started_at: chrono::Local::now(),
+        finished_at: None,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  if !resuming.is_empty() {
+    timers = resumed(timers, resuming);
+  }
+
+  // only worth asking the terminal when the timer is going to be drawn in color
+  let light = match background.or(config.background).unwrap_or_default() {
+    Background::Auto => !accessible && stdout().is_terminal() && terminal::input::query_background().is_some_and(render::is_light),
+    Background::Dark => false,
+    Background::Light => true,
+  };
+
+  // counted once up front, as the history only changes once the timer's over
+  let pomodoro_profile = stats::pomodoro_profile(&config);
+  let pomodoro = timers.iter().any(|running| running.profile.as_ref() == Some(&pomodoro_profile)).then(|| {
+    let goal = config.pomodoro.as_ref().and_then(|pomodoro| pomodoro.goal);
+    let badge = stats::pomodoros(&history::load(config.history_backend()), &pomodoro_profile, goal, chrono::Local::now().date_naive()).badge();
+
+    (pomodoro_profile.clone(), badge)
+  });
+
+  // flags take priority over the config file
+  let options = Options {
+    pomodoro,
+    toggl: config.toggl.clone(),
+    ask_reason: config.history.as_ref().and_then(|history| history.ask_reason).unwrap_or(false),
+    compact,
+    vertical,
+    flash,
+    alarm: alarm.or(config.alarm).unwrap_or(false),
+    do_not_disturb: do_not_disturb.or(config.do_not_disturb).unwrap_or_default(),
+    speak: speak.or(config.speak).unwrap_or(false),
+    speak_every: speak_every.or(config.speak_every.as_deref().map(parse_duration)).filter(|every| !every.is_zero()),
+    warn: warn.or(config.warn.as_deref().map(parse_duration)).filter(|warn| !warn.is_zero()),
+    warn_sound: warn_sound.or(config.warn_sound.clone()),
+    notify: notify.or(config.notify).unwrap_or(false) && notify::supported(),
+    webhook: webhook.or(config.webhook.clone()),
+    webhook_body: webhook_body.or(config.webhook_body.clone()),
+    exec: exec.or(config.exec.clone()),
+    relay: relay.or(config.relay.clone()),
+    event_pipe: event_pipe.or(config.event_pipe.clone()),
+    webhook_headers: if webhook_headers.is_empty() {
+      config.webhook_headers.iter().flatten().map(|(name, value)| format!("{}: {}", name, value)).collect()
+    } else {
+      webhook_headers
+    },
+    slack: config.slack.clone(),
+    discord: config.discord.as_ref().map(|discord| discord.webhook.clone()),
+    push: config.push.clone(),
+    history_backend: config.history_backend(),
+    keep_awake: keep_awake.or(config.keep_awake).unwrap_or(false),
+    pause_media: pause_media.or(config.pause_media).unwrap_or(false),
+    dbus: serve_dbus.or(config.dbus).unwrap_or(false) && dbus::supported(),
+    metrics_listen: metrics_listen.or(config.metrics_listen.clone()),
+    metrics_file: metrics_file.or(config.metrics_file.clone()),
+    share,
+    live_notify: live_notify.or(config.live_notify).unwrap_or(false) && notify::live_supported(),
+    osc_notify: osc_notify.or(config.osc_notify).unwrap_or(false),
+    badge: badge.or(config.badge).unwrap_or(false),
+    copy_summary: copy_summary.or(config.copy_summary).unwrap_or(false),
+    sound: sound.or(config.sound.clone()),
+    volume: volume.or(config.volume).unwrap_or(sound::DEFAULT_VOLUME),
+    sounds: sound_theme
+      .or(config.sound_theme)
+      .unwrap_or_default()
+      .theme(config.sounds.as_ref().map(config::Sounds::theme).unwrap_or_default()),
+    routes: config.on.clone().unwrap_or_default(),
+    bell: bell.or(config.bell.as_deref().map(parse_value)).unwrap_or_default(),
+    show_elapsed,
+    show_eta,
+    accessible,
+    interval,
+    pin,
+    args: started_with,
+    show_clock: show_clock.or(config.clock).unwrap_or(true),
+    show_percent: show_percent.or(config.percent).unwrap_or(true),
+    show_header: show_header.or(config.header).unwrap_or(true),
+    percent_style: percent_style.or(config.percent_style).unwrap_or_default(),
+    clock_format: clock_format.or(config.clock_format.clone()).unwrap_or_else(|| locale_clock_format().to_string()),
+    align: align.or(config.align).unwrap_or_default(),
+    face: face.or(config.face).unwrap_or_default(),
+    emphasis: emphasis.or(config.emphasis).unwrap_or_default(),
+    emphasis_for: emphasis_for.or(config.emphasis_for.as_deref().map(parse_duration)).unwrap_or(EMPHASIS_DURATION),
+    theme: theme.or(config.theme).unwrap_or_default().theme(light),
+  };
+
+  for volume in timers.iter().filter_map(|running| running.volume).chain([options.volume]) {
+    if volume > 100 {
+      eprintln!("Invalid volume: {}", volume);
+      exit(1);
+    }
+  }
+
+  if sound::supported() {
+    for sound in timers
+      .iter()
+      .filter_map(|running| running.sound.as_ref())
+      .chain(&options.sound)
+      .chain(&options.warn_sound)
+      .chain(options.sounds.files())
+    {
+      if !sound.is_file() {
+        eprintln!("Sound file not found: {}", sound.display());
+        exit(1);
+      }
+    }
+  }
+
+  if chrono::format::StrftimeItems::new(&options.clock_format).any(|item| item == chrono::format::Item::Error) {
+    eprintln!("Invalid clock format: {}", options.clock_format);
+    exit(1);
+  }
+
+  if attaching {
+    attach(attach_to.as_deref(), &options);
+    return;
+  }
+
+  if joining {
+    join(&join_address, &options);
+    return;
+  }
+
+  if dashboarding {
+    dashboard::run(&options.theme);
+    return;
+  }
+
+  run(timers, &options);
+}
+
+/// Show the timer called `name` on the daemon, or the one ending soonest, counting down until it ends or the user detaches.
+///
+/// Detaching leaves the timer running on the daemon.
+fn attach(name: Option<&str>, options: &Options) {
+  let Ok(mut daemon) = client::Connection::open() else {
+    eprintln!("No timers running in the background");
+    exit(1);
+  };
+
+  let described = match name {
+    Some(name) => daemon.call("query", json::Value::object([("label", name.into())])),
+    None => daemon.call("query", json::Value::Object(Vec::new())).and_then(|timers| match timers {
+      json::Value::Array(timers) => timers
+        .into_iter()
+        .min_by(|a, b| {
+          let key = |timer: &json::Value| (timer.get("paused") == Some(&json::Value::Bool(true)), timer.get("remaining").and_then(json::Value::as_f64));
+          key(a).partial_cmp(&key(b)).unwrap()
+        })
+        .ok_or_else(|| "No timers running in the background".to_string()),
+      _ => Err("Unexpected answer from the daemon".to_string()),
+    }),
+  };
+
+  let described = described.unwrap_or_else(|error| {
+    eprintln!("{}", error);
+    exit(1);
+  });
+
+  let id = described.get("id").cloned().unwrap_or(json::Value::Null);
+  let label = described.get("label").and_then(json::Value::as_str).unwrap_or_default().to_string();
+  let timer = mirrored_timer(&described);
+
+  let events = json::Value::Array(["tick", "stage", "finished", "pause", "resume", "cancelled"].map(json::Value::from).to_vec());
+
+  if let Err(error) = daemon.call("subscribe", json::Value::object([("events", events)])) {
+    eprintln!("Failed to follow the timer: {}", error);
+    exit(1);
+  }
+
+  // notifications are waited on to the side, so the timer carries on being drawn between them
+  let (message_tx, messages) = channel();
+  std::thread::spawn(move || {
+    while let Ok(message) = daemon.next() {
+      if message_tx.send(message).is_err() {
+        break;
+      }
+    }
+  });
+
+  let detached = format!("Detached, {} carries on in the background", label);
+  let timers = vec![(label.clone(), timer)];
+
+  mirror(messages, timers, &detached, "The daemon stopped", options, |message, timers| {
+    let params = message.get("params").cloned().unwrap_or(json::Value::Null);
+
+    match message.get("method").and_then(json::Value::as_str) {
+      Some("tick") => {
+        if let Some(json::Value::Array(described)) = params.get("timers") {
+          match described.iter().find(|described| described.get("id") == Some(&id)) {
+            Some(described) => timers[0].1 = mirrored_timer(described),
+            None => return Some(format!("{} has ended", label)),
+          }
+        }
+      }
+      Some(method) if params.get("id") == Some(&id) => match method {
+        "finished" => return Some(format!("{} finished", label)),
+        "cancelled" => return Some(format!("{} was cancelled", label)),
+        _ => timers[0].1 = mirrored_timer(&params),
+      },
+      _ => {}
+    }
+
+    None
+  });
+}
+
+/// Show the timers shared at `address` with `--share`, counting down in step with them until they end or the user leaves.
+fn join(address: &str, options: &Options) {
+  let messages = share::join(address).unwrap_or_else(|error| {
+    eprintln!("Failed to join {}: {}", address, error);
+    exit(1);
+  });
+
+  let left = format!("Left the timers shared at {}", address);
+  let stopped = format!("{} stopped sharing", address);
+
+  mirror(messages, Vec::new(), &left, &stopped, options, |message, timers| {
+    if let Some(ended) = message.get("ended").and_then(json::Value::as_str) {
+      return Some(ended.to_string());
+    }
+
+    if let Some(json::Value::Array(described)) = message.get("timers") {
+      *timers = described
+        .iter()
+        .map(|described| {
+          let label = described.get("label").and_then(json::Value::as_str).unwrap_or_default().to_string();
+          (label, mirrored_timer(described))
+        })
+        .collect();
+    }
+
+    None
+  });
+}
+
+/// Draw `timers` kept in step with those elsewhere, updating them with each of the `messages` through `update` until it says they've ended, printing why.
+///
+/// Leaving with Ctrl+C, q, or Escape prints `left` instead, and `gone` is printed if the messages stop coming.
+fn mirror(
+  messages: Receiver<json::Value>,
+  mut timers: Vec<(String, Timer)>,
+  left: &str,
+  gone: &str,
+  options: &Options,
+  mut update: impl FnMut(json::Value, &mut Vec<(String, Timer)>) -> Option<String>,
+) {
+  let (exit_tx, exit_rx) = channel();
+  ctrlc::set_handler(move || exit_tx.send(()).expect("Could not send signal on channel.")).expect("Error setting Ctrl-C handler");
+
+  let keys = terminal::input::listen();
+  let mut canvas = terminal::Canvas::default();
+
+  terminal::set_cursor_visible(false);
+
+  let ended = loop {
+    let detached = exit_rx.try_recv().is_ok() || keys.iter().flat_map(|keys| keys.try_iter()).any(|key| matches!(key, Key::Char('q') | Key::Escape));
+
+    if detached {
+      break left.to_string();
+    }
+
+    let mut ended = None;
+
+    while ended.is_none() {
+      match messages.try_recv() {
+        Ok(message) => ended = update(message, &mut timers),
+        Err(std::sync::mpsc::TryRecvError::Empty) => break,
+        Err(std::sync::mpsc::TryRecvError::Disconnected) => ended = Some(gone.to_string()),
+      }
+    }
+
+    if let Some(ended) = ended {
+      break ended;
+    }
+
+    let mut lines = Vec::new();
+
+    for (label, timer) in &timers {
+      if options.show_header && !options.compact {
+        lines.push(format!(
+          "{}{}{} — {}",
+          terminal::bold(),
+          label,
+          terminal::reset_style(),
+          format_remaining(timer.duration().as_secs_f64())
+        ));
+      }
+
+      lines.extend(timer_body(timer, options));
+    }
+
+    terminal::write(&canvas.draw(&lines));
+    sleep(Duration::from_millis(BAR_UPDATE_INTERVAL as u64));
+  };
+
+  print!("{}", canvas.clear());
+  terminal::set_cursor_visible(true);
+  terminal::input::restore();
+
+  println!("{}", ended);
+}
+
+/// Pick `timers` back up where the `lost` ones they were started as should be by now, leaving out any that finished before they were lost.
+fn resumed(timers: Vec<Running>, mut lost: Vec<state::TimerState>) -> Vec<Running> {
+  timers
+    .into_iter()
+    .filter_map(|mut running| {
+      let index = lost.iter().position(|state| state.label == running.label)?;
+      let state = lost.remove(index);
+
+      // time added while it ran goes on the end
+      let mut stages = running.timer.stages().to_vec();
+      let extended = Duration::from_secs_f64(state.duration).saturating_sub(running.timer.duration());
+      stages.last_mut().unwrap().duration += extended;
+
+      let elapsed = Duration::from_secs_f64(state.elapsed_now());
+
+      running.timer = Timer::resumed(stages, elapsed, false);
+      running.stage_index = running.timer.stage_index();
+      running.started_at = chrono::Local::now() - chrono::Duration::from_std(elapsed).unwrap_or_default();
+
+      Some(running)
+    })
+    .collect()
+}
+
+/// Get a timer standing in for one on the daemon, as `described` by it, to be drawn like one running here.
+fn mirrored_timer(described: &json::Value) -> Timer {
+  let number = |value: &json::Value, key| value.get(key).and_then(json::Value::as_f64).unwrap_or(0.0).max(0.0);
+
+  let mut stages = match described.get("stages") {
+    Some(json::Value::Array(stages)) => stages
+      .iter()
+      .map(|stage| Stage {
+        name: stage.get("name").and_then(json::Value::as_str).unwrap_or_default().to_string(),
+        duration: Duration::from_secs_f64(number(stage, "duration")),
+      })
+      .collect(),
+    _ => Vec::new(),
+  };
+
+  if stages.is_empty() {
+    stages.push(Stage {
+      name: String::new(),
+      duration: Duration::from_secs_f64(number(described, "duration")),
+    });
+  }
+
+  let elapsed = (number(described, "duration") - number(described, "remaining")).max(0.0);
+
+  Timer::resumed(stages, Duration::from_secs_f64(elapsed), described.get("paused") == Some(&json::Value::Bool(true)))
+}
+
+/// Count down `timers` side by side until all of them finish or the user exits early.
+fn run(mut timers: Vec<Running>, options: &Options) {
+  // setup ctrl+c handler
+  let (exit_tx, exit_rx) = channel();
+  ctrlc::set_handler(move || exit_tx.send(()).expect("Could not send signal on channel.")).expect("Error setting Ctrl-C handler");
+
+  // keyboard controls, only available when attached to a terminal
+  let keys = terminal::input::listen();
+
+  // the same controls for scripts and keybindings, by signal
+  signals::listen();
+  signals::catch_end();
+
+  // held until every round is over, including the alarm
+  let _awake = if options.keep_awake { awake::inhibit() } else { None };
+
+  if let Some(address) = &options.metrics_listen {
+    if let Err(error) = metrics::listen(address) {
+      eprintln!("Failed to serve metrics on {}: {}", address, error);
+      exit(1);
+    }
+  }
+
+  if let Some(path) = &options.metrics_file {
+    metrics::write_to(path.clone());
+  }
+
+  let mut shared = options.share.as_ref().map(|address| {
+    share::host(address).unwrap_or_else(|error| {
+      eprintln!("Failed to share the timers on {}: {}", address, error);
+      exit(1);
+    })
+  });
+
+  if let Some(path) = &options.event_pipe {
+    events::create(path);
+  }
+
+  // a button on the notification can start another round
+  while let Some(next) = countdown(timers, options, &exit_rx, keys.as_ref(), shared.as_mut()) {
+    timers = next;
+  }
+}
+
+/// Count down `timers` once, returning the timers to start next if the user asks for more from the notification.
+///
+/// Those who joined the timers shared by `shared` follow along.
+fn countdown(mut timers: Vec<Running>, options: &Options, exit_rx: &Receiver<()>, keys: Option<&Receiver<Key>>, mut shared: Option<&mut share::Host>) -> Option<Vec<Running>> {
+  // no redrawing or escape codes when asked for, or when piped into a file or log
+  let interactive = stdout().is_terminal();
+  let plain = options.accessible || !interactive;
+
+  if plain {
+    for running in &timers {
+      if timers.len() > 1 {
+        print!("{}: ", running.label);
+      }
+
+      println!("Timer started for {}", format_remaining(running.timer.duration().as_secs_f64()));
+    }
+  } else {
+    terminal::set_cursor_visible(false);
+  }
+
+  publish(&timers, options);
+
+  for running in &timers {
+    alerts::send_event(running, webhook::Event::Start, options);
+    alerts::alert(running, webhook::Event::Start, interactive, options);
+  }
+
+  // every timer starts at once, so one sound does for all of them
+  if let Some(sound) = options
+    .sounds
+    .get(sound::Event::Start)
+    .filter(|_| !quiet(options) && alerts::routed(webhook::Event::Start, config::Alert::Sound, true, options))
+  {
+    sound::play(&sound, options.volume);
+  }
+
+  if options.live_notify {
+    for running in &mut timers {
+      running.live = notify::live(&running.notification, &live_body(&running.timer), running.timer.progress());
+    }
+  }
+
+  let mut service = if options.dbus { dbus::serve(timers.iter().map(dbus_status).collect()) } else { None };
+
+  // held until the focus timers are over, putting Do Not Disturb back as it was
+  let mut focus = if timers.iter().any(|running| running.focus) { notify::focus() } else { None };
+
+  // buttons on warning notifications, along with the timer each is for
+  let mut warnings: Vec<(usize, Receiver<notify::Action>)> = Vec::new();
+
+  // the progress escape below only reaches Windows Terminal, so conhost gets it through the taskbar directly
+  let taskbar = if plain { None } else { taskbar::connect() };
+
+  // only written when it changes, as iTerm2 redraws the badge each time
+  let mut last_badge = String::new();
+
+  let mut last_announcement = Instant::now();
+  let mut canvas = if options.vertical {
+    terminal::Canvas::fullscreen()
+  } else if options.pin {
+    terminal::Canvas::pinned()
+  } else {
+    terminal::Canvas::default()
+  };
+  let mut last_update = Instant::now();
+  loop {
+    let ended = signals::ended();
+    let mut quit = exit_rx.try_recv().is_ok();
+    // whether a timer was controlled from outside the terminal
+    let mut controlled = false;
+    // why the timers were cancelled, when `timr cancel` said
+    let mut reason = None;
+
+    // to spot timers being paused or extended, however it was done
+    let before = timers.iter().map(|running| (running.timer.is_paused(), running.timer.duration())).collect::<Vec<_>>();
+
+    // keys control every timer at once, as do the signals standing in for them
+    let pressed = keys.iter().flat_map(|keys| keys.try_iter()).chain(signals::take()).collect::<Vec<_>>();
+
+    for &key in &pressed {
+      match key {
+        Key::Char('q') | Key::Escape => quit = true,
+        Key::Char(' ') | Key::Char('p') => {
+          let mut paused = false;
+
+          for running in timers.iter_mut().filter(|running| !running.timer.is_finished()) {
+            paused = running.timer.toggle_pause();
+          }
+
+          if plain {
+            println!("{}", if paused { "Paused" } else { "Resumed" });
+          }
+        }
+        Key::Char('+') => timers
+          .iter_mut()
+          .filter(|running| !running.timer.is_finished())
+          .for_each(|running| running.timer.extend(EXTEND_STEP)),
+        Key::Char('s') => timers
+          .iter_mut()
+          .filter(|running| running.timer.stages().len() > 1)
+          .for_each(|running| running.timer.skip_stage()),
+        _ => {}
+      }
+    }
+
+    for (index, command) in service.iter().flat_map(dbus::Service::commands) {
+      let timer = &mut timers[index].timer;
+
+      match command {
+        dbus::Command::Pause if !timer.is_paused() && !timer.is_finished() => {
+          timer.toggle_pause();
+        }
+        dbus::Command::Resume if timer.is_paused() => {
+          timer.toggle_pause();
+        }
+        dbus::Command::Extend(by) if !timer.is_finished() => timer.extend(by),
+        dbus::Command::Cancel => quit = true,
+        _ => continue,
+      }
+
+      controlled = true;
+    }
+
+    // sent by other timr commands, like those in the menu bar
+    if let Some((control, given)) = state::take_control() {
+      match control {
+        state::Control::Pause => timers
+          .iter_mut()
+          .filter(|running| !running.timer.is_paused() && !running.timer.is_finished())
+          .for_each(|running| {
+            running.timer.toggle_pause();
+          }),
+        state::Control::Resume => timers.iter_mut().filter(|running| running.timer.is_paused()).for_each(|running| {
+          running.timer.toggle_pause();
+        }),
+        state::Control::Cancel => {
+          quit = true;
+          reason = given;
+        }
+      }
+
+      controlled = true;
+    }
+
+    // the warning's button adds time to a timer that's still running, ready to warn again
+    for (index, buttons) in &warnings {
+      let running = &mut timers[*index];
+
+      if buttons.try_iter().any(|action| action == notify::Action::Extend) && !running.timer.is_finished() {
+        running.timer.extend(notify::EXTEND);
+        running.warned = false;
+        controlled = true;
+      }
+    }
+
+    if !pressed.is_empty() || controlled {
+      publish(&timers, options);
+
+      for (running, &(paused, duration)) in timers.iter().zip(&before) {
+        if running.timer.is_paused() != paused {
+          let event = if paused { webhook::Event::Resume } else { webhook::Event::Pause };
+
+          alerts::send_event(running, event, options);
+          alerts::alert(running, event, interactive, options);
+        }
+
+        if running.timer.duration() > duration {
+          alerts::send_event(running, webhook::Event::Extend, options);
+          alerts::alert(running, webhook::Event::Extend, interactive, options);
+        }
+      }
+    }
+
+    // the terminal closing, or timr being asked to stop, leaves the timers for `timr resume` rather than cancelling them
+    if ended {
+      publish(&timers, options);
+
+      for live in timers.iter_mut().filter_map(|running| running.live.take()) {
+        live.close();
+      }
+
+      let mut output = String::new();
+
+      if !plain {
+        output.push_str(&canvas.clear());
+
+        if options.face == Face::Kitty && terminal::graphics::kitty_supported() {
+          output.push_str(&terminal::graphics::kitty_delete(KITTY_IMAGE_ID));
+        }
+
+        output.push_str(&terminal::hide_progress());
+
+        if options.badge {
+          output.push_str(&terminal::badge(""));
+        }
+
+        output.push_str(&terminal::cursor_visibility(true));
+      }
+
+      drop(taskbar);
+      terminal::input::restore();
+
+      for running in timers.iter().filter(|running| !running.timer.is_finished()) {
+        if timers.len() > 1 {
+          let _ = write!(output, "{}: ", running.label);
+        }
+
+        let _ = writeln!(output, "Stopped with {} left", format_remaining(running.timer.remaining().as_secs_f64()));
+      }
+
+      output.push_str("Run timr resume to pick up where it left off\n");
+
+      // the terminal may well be gone, which is no reason to panic
+      let mut stdout = stdout().lock();
+      let _ = stdout.write_all(output.as_bytes()).and_then(|_| stdout.flush());
+
+      return None;
+    }
+
+    if quit {
+      state::remove();
+
+      let mut playing = Vec::new();
+
+      for running in timers.iter().filter(|running| !running.timer.is_finished()) {
+        alerts::send_event(running, webhook::Event::Cancel, options);
+        playing.extend(alerts::alert(running, webhook::Event::Cancel, interactive, options));
+        discord::post(running, true, options);
+        metrics::cancelled();
+      }
+
+      if let Some(shared) = shared.as_mut() {
+        shared.end("The host cancelled the timers");
+      }
+
+      for live in timers.iter_mut().filter_map(|running| running.live.take()) {
+        live.close();
+      }
+
+      if !plain {
+        print!("{}", canvas.clear());
+
+        if options.face == Face::Kitty && terminal::graphics::kitty_supported() {
+          print!("{}", terminal::graphics::kitty_delete(KITTY_IMAGE_ID));
+        }
+
+        // briefly turn the taskbar red so it is clear the timer didn't finish
+        let progress = (taskbar_progress(&timers) * 100.0).round() as u32;
+        terminal::write(&terminal::error_progress(progress));
+        taskbar.iter().for_each(|button| button.set(progress, taskbar::State::Error));
+        sleep(CANCEL_PROGRESS_DURATION);
+        print!("{}", terminal::hide_progress());
+        drop(taskbar);
+
+        if options.badge {
+          print!("{}", terminal::badge(""));
+        }
+
+        terminal::set_cursor_visible(true);
+      }
+
+      // asked of whoever cancelled them here, not of someone running timr cancel from elsewhere
+      if reason.is_none() && !controlled && options.ask_reason {
+        reason = keys.and_then(|keys| ask_reason(keys, exit_rx));
+      }
+
+      for running in timers.iter().filter(|running| !running.timer.is_finished()) {
+        record_history(running, true, reason.as_deref(), options);
+      }
+
+      terminal::input::restore();
+      println!("Exiting early!");
+
+      // let the cancelled alerts play out before exiting
+      for playback in playing {
+        let _ = playback.join();
+      }
+
+      stdout().flush().unwrap();
+
+      return None;
+    }
+
+    // done before the finish is noticed, so it can be heard
+    if focus.is_some() && timers.iter().filter(|running| running.focus).all(|running| running.timer.is_finished()) {
+      focus = None;
+    }
+
+    if timers.iter().all(|running| running.timer.is_finished()) {
+      break;
+    }
+
+    let mut changed = false;
+
+    for running in timers.iter_mut() {
+      // let the user know when a timer moves on to its next stage, or finishes while others carry on
+      if running.timer.is_finished() {
+        if running.finished_at.is_none() {
+          running.finished_at = Some(chrono::Local::now());
+          changed = true;
+
+          if plain {
+            println!("{} finished", running.label);
+          }
+
+          if let Some(live) = running.live.take() {
+            live.close();
+          }
+
+          alerts::send_event(running, webhook::Event::Finish, options);
+          slack::post(running, options);
+          discord::post(running, false, options);
+          metrics::completed();
+          record_history(running, false, None, options);
+
+          if options.copy_summary {
+            copy_summary(running, interactive, options);
+          }
+
+          if let Some(push) = &options.push {
+            push::send(push, &running.notification, &finished_message(&running.timer));
+          }
+
+          if quiet(options) {
+            if options.do_not_disturb == notify::DoNotDisturb::Flash && interactive {
+              std::thread::spawn(flash_screen);
+            }
+          } else {
+            if options.pause_media {
+              media::pause();
+            }
+
+            if alerts::routed(webhook::Event::Finish, config::Alert::Notify, options.notify, options) {
+              notify::send(&running.notification, &finished_message(&running.timer));
+            }
+
+            if options.osc_notify && interactive {
+              terminal::write(&osc_notification(running));
+            }
+
+            if let Some(sound) = event_sound(running, sound::Event::Finish, options).filter(|_| alerts::routed(webhook::Event::Finish, config::Alert::Sound, true, options)) {
+              sound::play(&sound, running.volume.unwrap_or(options.volume));
+            }
+
+            if running.speak.unwrap_or(options.speak) {
+              speech::say(&finished_speech(running));
+            }
+
+            if interactive && alerts::routed(webhook::Event::Finish, config::Alert::Bell, true, options) {
+              terminal::ring(running.bell.as_ref().unwrap_or(&options.bell));
+            }
+          }
+        }
+      } else if running.timer.stage_index() != running.stage_index {
+        running.stage_index = running.timer.stage_index();
+        changed = true;
+
+        if plain {
+          println!("Starting {}", running.timer.stage().name);
+        }
+
+        alerts::send_event(running, webhook::Event::Stage, options);
+
+        if quiet(options) {
+          if options.do_not_disturb == notify::DoNotDisturb::Flash && interactive {
+            std::thread::spawn(flash_screen);
+          }
+        } else {
+          if alerts::routed(webhook::Event::Stage, config::Alert::Notify, options.notify, options) {
+            let stage = running.timer.stage();
+            let template = notify::Template {
+              body: None,
+              ..running.notification.clone()
+            };
+
+            notify::send(&template, &format!("Starting {} ({})", stage.name, format_remaining(stage.duration.as_secs_f64())));
+          }
+
+          if let Some(sound) = event_sound(running, sound::Event::StageEnd, options).filter(|_| alerts::routed(webhook::Event::Stage, config::Alert::Sound, true, options)) {
+            sound::play(&sound, running.volume.unwrap_or(options.volume));
+          }
+
+          if interactive && alerts::routed(webhook::Event::Stage, config::Alert::Bell, true, options) {
+            terminal::ring(running.bell.as_ref().unwrap_or(&options.bell));
+          }
+        }
+      }
+    }
+
+    if changed {
+      publish(&timers, options);
+    }
+
+    if let Some(service) = service.as_mut() {
+      for (index, running) in timers.iter().enumerate() {
+        service.update(index, dbus_status(running));
+      }
+    }
+
+    if options.metrics_listen.is_some() || options.metrics_file.is_some() {
+      metrics::update(timers.iter().map(metrics_sample).collect());
+    }
+
+    if let Some(shared) = shared.as_mut() {
+      shared.update(timers.iter().map(|running| (running.label.as_str(), &running.timer)));
+    }
+
+    for running in &mut timers {
+      if let Some(live) = running.live.as_mut() {
+        live.update(&live_body(&running.timer), running.timer.progress());
+      }
+    }
+
+    if let Some(warn) = options.warn {
+      // timers shorter than the warning would get it straight away, which is no help
+      for (index, running) in timers
+        .iter_mut()
+        .enumerate()
+        .filter(|(_, running)| !running.warned && !running.timer.is_finished() && running.timer.duration() > warn && running.timer.remaining() <= warn)
+      {
+        running.warned = true;
+
+        if quiet(options) {
+          continue;
+        }
+
+        let message = format!("{} left on {}", speech::spoken_duration(warn.as_secs_f64()), running.label);
+
+        if plain {
+          println!("{}", message);
+        }
+
+        if notify::supported() {
+          let template = notify::Template {
+            body: None,
+            ..running.notification.clone()
+          };
+
+          warnings.extend(notify::send_with_buttons(&template, &message, &[notify::Action::Extend, notify::Action::Dismiss]).map(|buttons| (index, buttons)));
+        }
+
+        if let Some(sound) = event_sound(running, sound::Event::Warning, options) {
+          sound::play(&sound, running.volume.unwrap_or(options.volume));
+        }
+      }
+    }
+
+    if let Some(every) = options.speak_every {
+      let labelled = timers.len() > 1;
+
+      for running in timers.iter_mut().filter(|running| !running.timer.is_finished() && !running.timer.is_paused()) {
+        // count down in steps of the interval, saying the time remaining as each step is reached
+        let step = (running.timer.remaining().as_secs_f64() / every.as_secs_f64()).ceil() as u64;
+
+        if step > 0 && running.spoken_step.is_some_and(|spoken| step < spoken) && !quiet(options) {
+          let remaining = speech::spoken_duration((step * every.as_secs()) as f64);
+
+          if labelled {
+            speech::say(&format!("{}, {} remaining", running.label, remaining));
+          } else {
+            speech::say(&format!("{} remaining", remaining));
+          }
+        }
+
+        running.spoken_step = Some(step);
+      }
+    }
+
+    if last_update.elapsed().as_millis() < BAR_UPDATE_INTERVAL {
+      sleep(Duration::from_millis((BAR_UPDATE_INTERVAL - last_update.elapsed().as_millis()) as u64));
+      continue;
+    }
+
+    if plain {
+      // a fresh line every so often instead of redrawing, so screen readers and braille displays can follow along
+      if last_announcement.elapsed() >= options.interval {
+        for running in timers.iter().filter(|running| !running.timer.is_paused() && !running.timer.is_finished()) {
+          announce(running, timers.len() > 1, options);
+        }
+
+        last_announcement = Instant::now();
+      }
+
+      last_update = Instant::now();
+      continue;
+    }
+
+    let lines = if timers.len() > 1 {
+      terminal::align(&stacked_lines(&timers, options), options.align, terminal::get_width() as usize)
+    } else if options.vertical {
+      vertical_lines(&timers[0], options)
+    } else {
+      terminal::align(&timer_lines(&timers[0], options), options.align, terminal::get_width() as usize)
+    };
+
+    // the whole frame is built up here and written at once to avoid flickering
+    let mut frame = canvas.draw(&lines);
+
+    // progress for virtual terminals
+    let progress = (taskbar_progress(&timers) * 100.0).round() as u32;
+
+    if timers.iter().all(|running| running.timer.is_paused() || running.timer.is_finished()) {
+      frame.push_str(&terminal::paused_progress(progress));
+      taskbar.iter().for_each(|button| button.set(progress, taskbar::State::Paused));
+    } else {
+      frame.push_str(&terminal::progress(progress));
+      taskbar.iter().for_each(|button| button.set(progress, taskbar::State::Normal));
+    }
+
+    if options.badge {
+      let text = badge_text(&timers);
+
+      if text != last_badge {
+        frame.push_str(&terminal::badge(&text));
+        last_badge = text;
+      }
+    }
+
+    terminal::write(&frame);
+
+    last_update = Instant::now();
+  }
+
+  state::remove();
+
+  if let Some(service) = service.as_mut() {
+    for (index, running) in timers.iter().enumerate() {
+      service.update(index, dbus_status(running));
+    }
+  }
+
+  if options.metrics_listen.is_some() || options.metrics_file.is_some() {
+    metrics::update(timers.iter().map(metrics_sample).collect());
+  }
+
+  if let Some(shared) = shared.as_mut() {
+    let labels = timers.iter().map(|running| running.label.as_str()).collect::<Vec<_>>();
+    shared.end(&format!("{} finished", labels.join(", ")));
+  }
+
+  for live in timers.iter_mut().filter_map(|running| running.live.take()) {
+    live.close();
+  }
+
+  // the last timers to finish ended the loop before being noticed above, their notifications can offer to go again
+  let mut actions = Vec::new();
+  let mut playing = Vec::new();
+  let mut speaking = Vec::new();
+
+  let quiet = quiet(options);
+
+  // before anything sounds, so it isn't drowned out
+  if options.pause_media && !quiet {
+    media::pause();
+  }
+
+  for running in timers.iter().filter(|running| running.finished_at.is_none()) {
+    alerts::send_event(running, webhook::Event::Finish, options);
+    slack::post(running, options);
+    discord::post(running, false, options);
+    metrics::completed();
+    record_history(running, false, None, options);
+
+    if options.copy_summary {
+      copy_summary(running, interactive, options);
+    }
+
+    if let Some(push) = &options.push {
+      push::send(push, &running.notification, &finished_message(&running.timer));
+    }
+  }
+
+  for running in timers.iter().filter(|running| running.finished_at.is_none() && !quiet) {
+    if alerts::routed(webhook::Event::Finish, config::Alert::Notify, options.notify, options) {
+      actions.extend(notify::send_with_buttons(
+        &running.notification,
+        &finished_message(&running.timer),
+        &[notify::Action::Snooze, notify::Action::Restart, notify::Action::Dismiss],
+      ));
+    }
+
+    if options.osc_notify && interactive {
+      terminal::write(&osc_notification(running));
+    }
+
+    if let Some(sound) = event_sound(running, sound::Event::Finish, options).filter(|_| alerts::routed(webhook::Event::Finish, config::Alert::Sound, true, options)) {
+      playing.extend(sound::play(&sound, running.volume.unwrap_or(options.volume)));
+    }
+
+    if running.speak.unwrap_or(options.speak) {
+      speaking.extend(speech::say(&finished_speech(running)));
+    }
+  }
+
+  if !plain {
+    print!("{}", canvas.clear());
+
+    if options.face == Face::Kitty && terminal::graphics::kitty_supported() {
+      print!("{}", terminal::graphics::kitty_delete(KITTY_IMAGE_ID));
+    }
+
+    // reset progress bar
+    print!("{}", terminal::hide_progress());
+
+    if options.badge {
+      print!("{}", terminal::badge(""));
+    }
+  }
+
+  drop(taskbar);
+
+  if interactive && !quiet && alerts::routed(webhook::Event::Finish, config::Alert::Bell, true, options) {
+    playing.push(terminal::ring(finished_bell(&timers, options)));
+  }
+
+  // pulse the screen for those who can't hear the bell, or in place of it when it's being kept quiet
+  if (options.flash || (quiet && options.do_not_disturb == notify::DoNotDisturb::Flash)) && interactive {
+    flash_screen();
+  }
+
+  let action = if options.alarm && !quiet {
+    println!("Finished! Press any key to stop the alarm");
+    sound_alarm(&timers, options, playing, &actions, exit_rx, keys)
+  } else {
+    // let the sound and speech play out before exiting
+    for playback in playing {
+      let _ = playback.join();
+    }
+
+    for mut speech in speaking {
+      let _ = speech.wait();
+    }
+
+    if notify::has_buttons() && !actions.is_empty() {
+      println!("Finished! Snooze or restart from the notification, or press q to exit");
+      wait_for_action(&actions, exit_rx, keys)
+    } else {
+      println!("Finished!");
+      None
+    }
+  };
+
+  match action {
+    Some(notify::Action::Snooze) => return Some(timers.iter().map(snoozed).collect()),
+    Some(notify::Action::Restart) => return Some(timers.iter().map(restarted).collect()),
+    _ => {}
+  }
+
+  terminal::input::restore();
+
+  if !plain {
+    terminal::set_cursor_visible(true);
+  }
+
+  None
+}
+
+/// Get the escape asking the terminal to notify that `running` finished, worded like the desktop notification.
+fn osc_notification(running: &Running) -> String {
+  let body = running.notification.body.clone().unwrap_or_else(|| finished_message(&running.timer));
+
+  terminal::notification(&running.notification.title, &body)
+}
+
+/// Ask why the timers were cancelled, reading the answer from `keys` as it's typed, or nothing if it's skipped with enter, escape, or Ctrl+C.
+fn ask_reason(keys: &Receiver<Key>, exit_rx: &Receiver<()>) -> Option<String> {
+  terminal::write("Why were they cancelled? (enter to skip) ");
+
+  let mut typed = String::new();
+
+  loop {
+    if exit_rx.try_recv().is_ok() {
+      typed.clear();
+      break;
+    }
+
+    match keys.recv_timeout(Duration::from_millis(BAR_UPDATE_INTERVAL as u64)) {
+      Ok(Key::Enter) | Err(RecvTimeoutError::Disconnected) => break,
+      Ok(Key::Escape) => {
+        typed.clear();
+        break;
+      }
+      Ok(Key::Backspace) => {
+        if typed.pop().is_some() {
+          terminal::write("\x08 \x08");
+        }
+      }
+      Ok(Key::Char(character)) => {
+        typed.push(character);
+        terminal::write(&character.to_string());
+      }
+      Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+    }
+  }
+
+  println!();
+
+  let typed = typed.trim();
+  (!typed.is_empty()).then(|| typed.to_string())
+}
+
+/// Add `running` to the history, having just finished or been `cancelled` for the `reason` given if any, and to Toggl when it finished.
+fn record_history(running: &Running, cancelled: bool, reason: Option<&str>, options: &Options) {
+  let timer = &running.timer;
+  let session = history::Session {
+    label: running.label.clone(),
+    duration: if cancelled { timer.elapsed() } else { timer.duration() }.as_secs_f64(),
+    started_at: running.started_at.timestamp_millis() as f64 / 1000.0,
+    finished_at: state::now(),
+    cancelled,
+    profile: running.profile.clone(),
+    tags: running.tags.clone(),
+    note: None,
+    reason: reason.map(str::to_string),
+  };
+
+  if let Some(toggl) = options.toggl.as_ref().filter(|_| !cancelled) {
+    toggl::push(toggl, &session);
+  }
+
+  history::record(options.history_backend, session);
+}
+
+/// Put a line summing up `running`, having just finished, on the clipboard.
+fn copy_summary(running: &Running, interactive: bool, options: &Options) {
+  let finished_at = running.finished_at.unwrap_or_else(chrono::Local::now);
+  let summary = format!(
+    "{} — {} — finished {}",
+    running.label,
+    format_short(running.timer.duration().as_secs_f64()),
+    finished_at.format(&options.clock_format).to_string().trim()
+  );
+
+  // the terminal can still reach the clipboard without a tool, even over SSH
+  if !clipboard::copy(&summary) && interactive {
+    terminal::write(&terminal::clipboard(&summary));
+  }
+}
+
+/// Get the gauges `running` is shown with in the metrics.
+fn metrics_sample(running: &Running) -> metrics::Sample {
+  metrics::Sample {
+    label: running.label.clone(),
+    remaining: running.timer.remaining().as_secs_f64(),
+    duration: running.timer.duration().as_secs_f64(),
+    paused: running.timer.is_paused(),
+  }
+}
 
-mod terminal;
+/// Get the properties `running` is shown with over D-Bus.
+fn dbus_status(running: &Running) -> dbus::Status {
+  let state = if running.timer.is_finished() {
+    dbus::State::Finished
+  } else if running.timer.is_paused() {
+    dbus::State::Paused
+  } else {
+    dbus::State::Running
+  };
 
-const BAR_UPDATE_INTERVAL: u128 = 16; // milliseconds
-const BAR_EMPTY_CHAR: char = '▒';
-const BAR_FULL_CHAR: char = '█';
+  dbus::Status {
+    label: running.label.clone(),
+    remaining: running.timer.remaining().as_secs_f64().ceil() as u64,
+    state,
+  }
+}
+
+/// Get the sound to play for `event` on `running`, with the profile's and command line's audio files ahead of the sound theme.
+fn event_sound(running: &Running, event: sound::Event, options: &Options) -> Option<sound::Sound> {
+  let file = match event {
+    sound::Event::Start => None,
+    sound::Event::StageEnd | sound::Event::Finish => running.sound.as_ref().or(options.sound.as_ref()),
+    sound::Event::Warning => options.warn_sound.as_ref(),
+  };
 
-#[derive(Deserialize)]
-struct Config {
-  profiles: Option<Vec<Profile>>,
+  file.map(|path| sound::Sound::File(path.clone())).or_else(|| options.sounds.get(event))
 }
 
-#[derive(Deserialize)]
-struct Profile {
-  name: String,
-  duration: String,
+/// Pulse the screen a few times by swapping its colors.
+fn flash_screen() {
+  for _ in 0..FLASH_COUNT {
+    terminal::set_reverse_video(true);
+    sleep(Duration::from_millis(FLASH_INTERVAL));
+    terminal::set_reverse_video(false);
+    sleep(Duration::from_millis(FLASH_INTERVAL));
+  }
 }
 
-fn main() {
-  // encourage control characters on Windows (https://learn.microsoft.com/en-us/windows/console/console-virtual-terminal-sequences)
-  #[cfg(target_os = "windows")]
-  {
-    use windows::Win32::System::Console::{GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE};
+/// Check whether alerts should be held back, with the system in Do Not Disturb mode and asked to respect it.
+fn quiet(options: &Options) -> bool {
+  options.do_not_disturb != notify::DoNotDisturb::Ignore && notify::do_not_disturb()
+}
 
-    unsafe {
-      let handle = GetStdHandle(STD_OUTPUT_HANDLE).expect("Failed to get stdout");
-      let mut mode: CONSOLE_MODE = CONSOLE_MODE(0);
+/// Keep ringing the bell and playing the sound every so often until a key is pressed or a notification is clicked.
+fn sound_alarm(
+  timers: &[Running],
+  options: &Options,
+  mut playing: Vec<JoinHandle<()>>,
+  actions: &[Receiver<notify::Action>],
+  exit_rx: &Receiver<()>,
+  keys: Option<&Receiver<Key>>,
+) -> Option<notify::Action> {
+  let mut sounds = timers
+    .iter()
+    .filter_map(|running| Some((event_sound(running, sound::Event::Finish, options)?, running.volume.unwrap_or(options.volume))))
+    .collect::<Vec<_>>();
+  sounds.dedup();
+
+  let mut last_ring = Instant::now();
 
-      GetConsoleMode(handle, &mut mode).expect("Failed to get console mode");
+  loop {
+    if exit_rx.try_recv().is_ok() || signals::ended() || keys.is_some_and(|keys| keys.try_iter().next().is_some()) {
+      return None;
+    }
 
-      if !mode.contains(ENABLE_VIRTUAL_TERMINAL_PROCESSING) {
-        SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING).expect("Failed to set console mode");
+    if let Some(action) = actions.iter().find_map(|actions| actions.try_recv().ok()) {
+      return Some(action);
+    }
+
+    // wait for the sound to play out so rings never overlap
+    if last_ring.elapsed() >= ALARM_INTERVAL && playing.iter().all(|playback| playback.is_finished()) {
+      playing = sounds.iter().filter_map(|(sound, volume)| sound::play(sound, *volume)).collect();
+
+      if stdout().is_terminal() {
+        playing.push(terminal::ring(finished_bell(timers, options)));
       }
+      last_ring = Instant::now();
     }
+
+    sleep(Duration::from_millis(BAR_UPDATE_INTERVAL as u64));
   }
+}
 
-  let args = std::env::args().collect::<Vec<String>>();
-  let args = args.split_at(1).1; // remove self from args list
+/// Get the bell pattern to ring for the timers finishing last, taking the first one from a profile.
+fn finished_bell<'a>(timers: &'a [Running], options: &'a Options) -> &'a BellPattern {
+  timers
+    .iter()
+    .filter(|running| running.finished_at.is_none())
+    .find_map(|running| running.bell.as_ref())
+    .unwrap_or(&options.bell)
+}
 
-  if args.is_empty() {
-    print_help();
-    return;
+/// Wait for a button to be pressed on one of the finished notifications, giving up after a while or when the user exits.
+fn wait_for_action(actions: &[Receiver<notify::Action>], exit_rx: &Receiver<()>, keys: Option<&Receiver<Key>>) -> Option<notify::Action> {
+  let started = Instant::now();
+
+  while started.elapsed() < ACTION_WAIT {
+    if exit_rx.try_recv().is_ok() || signals::ended() || keys.is_some_and(|keys| keys.try_iter().any(|key| matches!(key, Key::Char('q') | Key::Escape))) {
+      return None;
+    }
+
+    if let Some(action) = actions.iter().find_map(|actions| actions.try_recv().ok()) {
+      return Some(action);
+    }
+
+    sleep(Duration::from_millis(BAR_UPDATE_INTERVAL as u64));
   }
 
-  let mut duration = None;
+  None
+}
 
-  for arg in args {
-    match arg.as_str() {
-      "-v" | "--version" => {
-        println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-        return;
-      }
-      "-h" | "--help" => {
-        print_help();
-        return;
-      }
-      _ => {
-        // first generic argument is duration, any after that causing the phone program to error
-        if duration.is_none() {
-          duration = Some(arg);
-        } else {
-          eprintln!("Unknown option: {}", arg);
-          println!("Use '{} --help' for more information", env!("CARGO_PKG_NAME"));
+/// Get a fresh copy of `running`, to count down again from the start.
+fn restarted(running: &Running) -> Running {
+  let timer = Timer::new(running.timer.stages().to_vec());
+
+  Running {
+    label: running.label.clone(),
+    profile: running.profile.clone(),
+    tags: running.tags.clone(),
+    notification: running.notification.clone(),
+    live: None,
+    sound: running.sound.clone(),
+    volume: running.volume,
+    bell: running.bell.clone(),
+    slack_channel: running.slack_channel.clone(),
+    slack_emoji: running.slack_emoji.clone(),
+    discord_webhook: running.discord_webhook.clone(),
+    speak: running.speak,
+    speech: running.speech.clone(),
+    focus: running.focus,
+    spoken_step: None,
+    warned: false,
+    stage_index: timer.stage_index(),
+    timer,
+    started_at: chrono::Local::now(),
+    finished_at: None,
+  }
+}
 
-          exit(1);
-        }
-      }
-    }
+/// Get a short timer to go off again a little later, in place of `running`.
+fn snoozed(running: &Running) -> Running {
+  let timer = Timer::new(vec![Stage {
+    name: String::new(),
+    duration: notify::SNOOZE,
+  }]);
+
+  Running {
+    label: running.label.clone(),
+    profile: None,
+    tags: running.tags.clone(),
+    notification: running.notification.clone(),
+    live: None,
+    sound: running.sound.clone(),
+    volume: running.volume,
+    bell: running.bell.clone(),
+    slack_channel: running.slack_channel.clone(),
+    slack_emoji: running.slack_emoji.clone(),
+    discord_webhook: running.discord_webhook.clone(),
+    speak: running.speak,
+    speech: running.speech.clone(),
+    // a snooze is only a reminder, not another session to focus on
+    focus: false,
+    spoken_step: None,
+    warned: false,
+    stage_index: timer.stage_index(),
+    timer,
+    started_at: chrono::Local::now(),
+    finished_at: None,
   }
+}
 
-  if duration.is_none() {
-    eprintln!("No duration specified");
-    exit(1);
+/// Get what the live notification says about `timer`, which changes once a second.
+fn live_body(timer: &Timer) -> String {
+  let mut body = format!("{} left", format_remaining(timer.remaining().as_secs_f64()));
+
+  if timer.stages().len() > 1 {
+    body = format!("{}: {}", timer.stage().name, body);
+  }
+
+  if timer.is_paused() {
+    body.push_str(" (paused)");
   }
 
-  let duration = duration.unwrap();
+  body
+}
+
+/// Get the progress to show in the taskbar, which follows whichever unfinished timer will end first.
+fn taskbar_progress(timers: &[Running]) -> f64 {
+  timers
+    .iter()
+    .filter(|running| !running.timer.is_finished())
+    .min_by_key(|running| running.timer.remaining())
+    .map(|running| running.timer.progress())
+    .unwrap_or(1.0)
+}
+
+/// Get the time left on the timer ending soonest as a badge, in whole minutes until the last one.
+fn badge_text(timers: &[Running]) -> String {
+  let Some(seconds) = timers
+    .iter()
+    .filter(|running| !running.timer.is_finished())
+    .map(|running| running.timer.remaining().as_secs())
+    .min()
+  else {
+    return String::new();
+  };
 
-  if duration.is_empty() {
-    unreachable!("Duration must not be empty");
+  if seconds < 60 {
+    format!("{}s", seconds)
+  } else {
+    // rounded up, so it reads 1m until the last minute really starts
+    format!("{}m", seconds.div_ceil(60))
   }
+}
 
-  let duration = match duration.chars().next().unwrap() {
-    '0'..='9' => parse_duration(duration),
-    _ => {
-      let config_path = home_dir().expect("Failed to find user's home directory").join(".config").join("timr.toml");
+/// Get the time remaining on `timer`, along with whatever else has been asked to be shown next to it.
+fn remaining_text(timer: &Timer, options: &Options) -> String {
+  let mut remaining = format_remaining(timer.remaining().as_secs_f64());
 
-      if !config_path.exists() {
-        eprintln!("$HOME/.config/timr.toml does not exist");
-        exit(1);
-      }
+  if options.show_elapsed {
+    remaining.push_str(&format!(
+      " ({} / {})",
+      format_padded(timer.elapsed().as_secs_f64()),
+      format_padded(timer.duration().as_secs_f64())
+    ));
+  }
 
-      let config: Config = toml::from_str(fs::read_to_string(config_path).expect("Failed to read config file").as_str()).expect("Failed to parse config file");
+  // computed from the time remaining every frame so it follows pausing and extending
+  if options.show_eta {
+    let finish = chrono::Local::now() + timer.remaining();
 
-      if config.profiles.is_none() {
-        eprint!("Config does not contain any profiles");
-        exit(1);
-      }
+    remaining.push_str(&format!(" - ends at {}", finish.format(&options.clock_format).to_string().trim()));
+  }
 
-      let profiles = config.profiles.unwrap();
+  if timer.is_paused() {
+    remaining.push_str(" (paused)");
+  }
 
-      let profile = profiles.iter().find(|profile| profile.name == *duration);
+  remaining
+}
 
-      if profile.is_none() {
-        eprint!("No profile found matching {}", duration);
-        exit(1);
-      }
+/// Get the time remaining on `timer`, emphasized if it is about to finish.
+fn emphasized_remaining_text(timer: &Timer, options: &Options) -> String {
+  let remaining = remaining_text(timer, options);
 
-      parse_duration(&profile.unwrap().duration)
-    }
-  };
+  if !timer.is_paused() && timer.remaining() <= options.emphasis_for {
+    let warning = format!("{}{}{}", render::color(options.theme.warning), remaining, terminal::reset_color());
 
-  let start = Instant::now();
-  let end = start + duration;
+    return render::emphasize(&warning, options.emphasis);
+  }
 
-  // setup ctrl+c handler
-  let (exit_tx, exit_rx) = channel();
-  ctrlc::set_handler(move || exit_tx.send(()).expect("Could not send signal on channel.")).expect("Error setting Ctrl-C handler");
+  remaining
+}
 
-  terminal::set_cursor_visible(false);
+/// Print a plain line with the status of a running timer.
+fn announce(running: &Running, labelled: bool, options: &Options) {
+  let timer = &running.timer;
+  let remaining = remaining_text(timer, options);
 
-  println!(); // create an empty line, as below we will move up and clear it
+  if labelled {
+    print!("{}: ", running.label);
+  }
 
-  let mut last_update = Instant::now();
-  loop {
-    if exit_rx.try_recv().is_ok() {
-      terminal::clear_line();
+  if timer.stages().len() > 1 {
+    print!("{}: ", timer.stage().name);
+  }
 
-      terminal::set_cursor_visible(true);
-      println!("Exiting early!");
+  if options.show_percent {
+    println!("{} remaining ({})", remaining, percent_text(timer, options));
+  } else {
+    println!("{} remaining", remaining);
+  }
+}
 
-      stdout().flush().unwrap();
+/// Get what to say aloud when `running` finishes, like `pasta timer finished`.
+fn finished_speech(running: &Running) -> String {
+  if let Some(speech) = &running.speech {
+    return speech.clone();
+  }
 
-      return;
-    }
+  match &running.profile {
+    Some(profile) => format!("{} timer finished", profile),
+    None => format!("{} timer finished", speech::spoken_duration(running.timer.duration().as_secs_f64())),
+  }
+}
 
-    let now = Instant::now();
+/// Get the default notification message for when `timer` finishes.
+fn finished_message(timer: &Timer) -> String {
+  format!("Finished after {}", format_remaining(timer.duration().as_secs_f64()))
+}
 
-    if now > end {
-      break;
+/// Publish a snapshot of `timers` for other commands, like `timr tmux-status`, to read.
+fn publish(timers: &[Running], options: &Options) {
+  // said once rather than every tick, and again only if it starts failing after working
+  static FAILING: AtomicBool = AtomicBool::new(false);
+
+  let result = state::save(
+    timers
+      .iter()
+      .filter(|running| !running.timer.is_finished())
+      .map(|running| state::TimerState {
+        id: None,
+        label: running.label.clone(),
+        stage: if running.timer.stages().len() > 1 {
+          running.timer.stage().name.clone()
+        } else {
+          String::new()
+        },
+        duration: running.timer.duration().as_secs_f64(),
+        remaining: running.timer.remaining().as_secs_f64(),
+        paused: running.timer.is_paused(),
+        started_at: running.started_at.timestamp_millis() as f64 / 1000.0,
+        saved_at: state::now(),
+      })
+      .collect(),
+    Vec::new(),
+    &options.args,
+  );
+
+  match result {
+    Ok(()) => FAILING.store(false, Ordering::Relaxed),
+    Err(error) if !FAILING.swap(true, Ordering::Relaxed) => eprintln!("Failed to publish the timers for other commands: {}", error),
+    Err(_) => {}
+  }
+}
+
+/// Get the percentage readout for `timer`, written in the chosen style.
+fn percent_text(timer: &Timer, options: &Options) -> String {
+  render::percent(options.percent_style, timer.progress(), timer.duration().as_secs_f64())
+}
+
+/// Get the widest the percentage readout for `timer` can get, so the bar beside it keeps a steady width.
+fn percent_width(timer: &Timer, options: &Options) -> usize {
+  let total = timer.duration().as_secs_f64();
+
+  [0.0, 1.0]
+    .iter()
+    .map(|&progress| terminal::display_width(&render::percent(options.percent_style, progress, total)))
+    .max()
+    .unwrap()
+}
+
+/// Get the line naming the timer and how long it runs for in total, like `pomodoro — 30m0s`.
+fn header(running: &Running, options: &Options) -> Option<String> {
+  if !options.show_header || options.compact {
+    return None;
+  }
+
+  let total = format_remaining(running.timer.duration().as_secs_f64());
+
+  Some(match &running.profile {
+    Some(profile) => match options.pomodoro.as_ref().filter(|(pomodoro, _)| pomodoro == profile) {
+      Some((_, badge)) => format!("{}{}{} — {}  {}", terminal::bold(), profile, terminal::reset_style(), total, badge),
+      None => format!("{}{}{} — {}", terminal::bold(), profile, terminal::reset_style(), total),
+    },
+    None => format!("{} timer", total),
+  })
+}
+
+/// Get the lines of the frame for a single timer.
+fn timer_lines(running: &Running, options: &Options) -> Vec<String> {
+  let mut lines = timer_body(&running.timer, options);
+
+  // drawn as part of every frame, so it sticks around however long the timer runs and however the terminal is resized
+  if let Some(header) = header(running, options) {
+    lines.insert(0, header);
+  }
+
+  lines
+}
+
+/// Get the lines of the frame showing the progress of a single timer.
+fn timer_body(timer: &Timer, options: &Options) -> Vec<String> {
+  // leave room for the percentage and a spare column so the line never wraps
+  let reserved = if options.show_percent { percent_width(timer, options) + 2 } else { 0 } + 1;
+  let glyph_width = terminal::display_width(&render::BAR_FULL_CHAR.to_string()).max(1);
+  let bar_width = (terminal::get_width().saturating_sub(reserved as u16) / glyph_width as u16).min(BAR_MAX_WIDTH);
+
+  let progress = timer.progress(); // 0-1
+  let remaining = emphasized_remaining_text(timer, options);
+
+  // only worth showing stages when there is more than one
+  let staged = timer.stages().len() > 1;
+
+  // current time (clock) and time remaining
+  let mut status = if options.show_clock {
+    format!("{} - {}", chrono::Local::now().format(&options.clock_format).to_string().trim(), remaining)
+  } else {
+    remaining.clone()
+  };
+
+  // the active stage with its own progress
+  if staged {
+    write!(
+      status,
+      "  {} ({}/{}) {}",
+      timer.stage().name,
+      timer.stage_index() + 1,
+      timer.stages().len(),
+      render::bar(timer.stage_progress(), STAGE_BAR_WIDTH, &options.theme)
+    )
+    .unwrap();
+  }
+
+  let percent = if options.show_percent { percent_text(timer, options) } else { String::new() };
+
+  let tick = timer.elapsed().as_millis();
+
+  if options.compact || bar_width < BAR_MIN_WIDTH {
+    // single line spinner for terminals too narrow to fit a useful bar
+    let spinner = (tick / SPINNER_INTERVAL) as usize % SPINNER_FRAMES.len();
+
+    if staged {
+      return vec![format!("{} {} {}", SPINNER_FRAMES[spinner], timer.stage().name, remaining)];
     }
 
-    if last_update.elapsed().as_millis() < BAR_UPDATE_INTERVAL {
-      sleep(Duration::from_millis((BAR_UPDATE_INTERVAL - last_update.elapsed().as_millis()) as u64));
-      continue;
+    return vec![format!("{} {}", SPINNER_FRAMES[spinner], remaining)];
+  }
+
+  match options.face {
+    Face::Kitty if terminal::graphics::kitty_supported() => {
+      // the image doesn't move the cursor, so step over the cells it covers
+      let image = terminal::graphics::kitty_image(KITTY_IMAGE_ID, &render::bar_image(progress, bar_width, &options.theme), bar_width, 1);
+      vec![status, format!("{}{}  {}", image, " ".repeat(bar_width as usize), percent)]
+    }
+    Face::Sixel => {
+      let columns = terminal::get_width().saturating_sub(1);
+      let text = format_digital(timer.remaining().as_secs_f64());
+      let image = render::digits_image(&text, progress, columns, SIXEL_ROWS, terminal::graphics::cell_size(), &options.theme);
+
+      // blank rows to draw the image over, it's then drawn from the row below them so the cursor ends up back inside the frame
+      let mut lines = vec![status];
+      lines.extend(vec![String::new(); SIXEL_ROWS as usize]);
+      lines.push(format!("{}{}", terminal::cursor_up(SIXEL_ROWS), terminal::graphics::sixel(&image)));
+      lines
+    }
+    Face::Bar | Face::Kitty => vec![status, format!("{}  {}", render::bar(progress, bar_width, &options.theme), percent)],
+    Face::Pie => {
+      // text sits beside the middle of the pie
+      let mut lines = render::pie(progress, &options.theme);
+      lines[1].push_str(&format!("  {}", status));
+      lines[2].push_str(&format!("  {}", percent));
+      lines
     }
+    Face::Hourglass => {
+      let mut lines = render::hourglass(progress, tick / HOURGLASS_INTERVAL, &options.theme);
+      lines[3].push_str(&format!("  {}", status));
+      lines[4].push_str(&format!("  {}", percent));
+      lines
+    }
+  }
+}
 
-    let bar_width = match terminal::get_width() - 15 {
-      n if n < 30 => n,
-      _ => 30,
-    };
+/// Get the lines of a fullscreen frame, a column filling up along the edge of the screen with large digits in the space beside it.
+///
+/// The column sits on the right edge when aligned right, and on the left otherwise.
+fn vertical_lines(running: &Running, options: &Options) -> Vec<String> {
+  let timer = &running.timer;
+  let width = terminal::get_width() as usize;
+  let height = terminal::get_height();
 
-    let progress = now.duration_since(start).as_millis() as f64 / duration.as_millis() as f64; // 0-1
-    let progress_width = (progress * bar_width as f64).round() as u16;
+  // two columns for the bar, one to separate it, and one spare on the right so lines never wrap
+  let space = width.saturating_sub(4);
 
-    let remaining = end - now;
-    let seconds = remaining.as_secs_f64();
+  let mut info = emphasized_remaining_text(timer, options);
 
-    terminal::previous_line();
-    terminal::clear_line();
+  if timer.stages().len() > 1 {
+    info = format!("{} ({}/{})  {}", timer.stage().name, timer.stage_index() + 1, timer.stages().len(), info);
+  }
 
-    // print current time (clock)
-    print!("{} - ", chrono::Local::now().format("%_I:%M%P").to_string().trim());
+  if options.show_clock {
+    info = format!("{} - {}", chrono::Local::now().format(&options.clock_format).to_string().trim(), info);
+  }
 
-    // print hours remaining (if any)
-    match (seconds / 3600.0).floor() {
-      hours if hours > 0.0 => print!("{}h", hours),
-      _ => {}
-    };
+  if options.show_percent {
+    write!(info, "  {}", percent_text(timer, options)).unwrap();
+  }
 
-    // print minutes remaining (if any)
-    match ((seconds % 3600.0) / 60.0).floor() {
-      minutes if minutes > 0.0 => print!("{}m", minutes),
-      _ => {}
+  let mut block = render::big_digits(&format_digital(timer.remaining().as_secs_f64()));
+
+  // big digits only when they fit, the line below has everything anyway
+  if block.iter().any(|line| terminal::display_width(line) > space) {
+    block.clear();
+  } else {
+    block.push(String::new());
+  }
+
+  block.push(info);
+
+  if let Some(header) = header(running, options) {
+    block.insert(0, header);
+    block.insert(1, String::new());
+  }
+
+  // center the block in the space beside the column
+  let top = (height as usize).saturating_sub(block.len()) / 2;
+  let block = terminal::align(&block, Align::Center, space);
+
+  render::column(timer.progress(), height, &options.theme)
+    .into_iter()
+    .enumerate()
+    .map(|(row, column)| {
+      let content = row.checked_sub(top).and_then(|i| block.get(i)).cloned().unwrap_or_default();
+
+      if options.align == Align::Right {
+        let padding = space.saturating_sub(terminal::display_width(&content));
+        format!("{}{} {}", content, " ".repeat(padding), column)
+      } else {
+        format!("{} {}", column, content)
+      }
+    })
+    .collect()
+}
+
+/// Get the lines of the frame for several timers at once, a labelled bar for each one.
+fn stacked_lines(timers: &[Running], options: &Options) -> Vec<String> {
+  let mut lines = Vec::new();
+
+  if options.show_clock {
+    lines.push(chrono::Local::now().format(&options.clock_format).to_string().trim().to_string());
+  }
+
+  let label_width = timers.iter().map(|running| terminal::display_width(&running.label)).max().unwrap_or(0);
+
+  for running in timers {
+    let label = format!("{}{}", running.label, " ".repeat(label_width - terminal::display_width(&running.label)));
+
+    // finished timers collapse down to a summary
+    if let Some(finished_at) = running.finished_at {
+      lines.push(format!("{}  finished at {}", label, finished_at.format(&options.clock_format).to_string().trim()));
+      continue;
     }
 
-    // print seconds remaining
-    println!("{}s", (seconds % 60.0).floor());
+    let timer = &running.timer;
+    let mut remaining = emphasized_remaining_text(timer, options);
+
+    if timer.stages().len() > 1 {
+      remaining = format!("{} {}", timer.stage().name, remaining);
+    }
 
-    terminal::clear_line();
+    if options.show_percent {
+      remaining = format!("{}  {}", percent_text(timer, options), remaining);
+    }
 
-    // print the solid progress bar
-    for i in 0..progress_width {
-      let red = lerp(90, 123, i as f64 / bar_width as f64);
-      let green = lerp(105, 90, i as f64 / bar_width as f64);
+    let bar_width = (terminal::get_width() as usize)
+      .saturating_sub(label_width + terminal::display_width(&remaining) + 5)
+      .min(BAR_MAX_WIDTH as usize) as u16;
 
-      print!("{}{}", terminal::ansi_rgb(red, green, 237), BAR_FULL_CHAR);
+    if options.compact || bar_width < BAR_MIN_WIDTH {
+      lines.push(format!("{}  {}", label, remaining));
+    } else {
+      lines.push(format!("{}  {}  {}", label, render::bar(timer.progress(), bar_width, &options.theme), remaining));
     }
+  }
 
-    // print empty progress bar and progress percent
-    print!(
-      "{}{}{}[39m  {}%",
-      terminal::ansi_rgb(100, 100, 100),
-      BAR_EMPTY_CHAR.to_string().repeat((bar_width - progress_width) as usize),
-      27 as char,
-      (progress * 100.0).round()
-    );
+  lines
+}
 
-    // output progress for virtual terminals
-    terminal::progress((progress * 100.0).round() as u32);
+/// Format the time remaining as hours, minutes, and seconds, omitting leading units that are zero.
+fn format_remaining(seconds: f64) -> String {
+  let mut output = String::new();
 
-    stdout().flush().unwrap();
+  // hours remaining (if any)
+  match (seconds / 3600.0).floor() {
+    hours if hours > 0.0 => output.push_str(&format!("{}h", hours)),
+    _ => {}
+  };
 
-    last_update = now;
+  // minutes remaining (if any)
+  match ((seconds % 3600.0) / 60.0).floor() {
+    minutes if minutes > 0.0 => output.push_str(&format!("{}m", minutes)),
+    _ => {}
   }
 
-  terminal::previous_line();
-  terminal::clear_line();
+  // seconds remaining
+  output.push_str(&format!("{}s", (seconds % 60.0).floor()));
 
-  // reset progress bar
-  terminal::hide_progress();
+  output
+}
 
-  print!("{}", 7 as char); // beep/alert
+/// Format a duration leaving out the units that are zero (e.g. `10m` or `1h30s`).
+fn format_short(seconds: f64) -> String {
+  let seconds = seconds.floor() as u64;
+  let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+  let output = [(hours, "h"), (minutes, "m"), (seconds, "s")]
+    .iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, unit)| format!("{}{}", value, unit))
+    .collect::<String>();
+
+  if output.is_empty() {
+    "0s".to_string()
+  } else {
+    output
+  }
+}
 
-  terminal::set_cursor_visible(true);
+/// Format a duration with zero-padded minutes and seconds (e.g. `12m03s`), so it keeps a steady width while ticking.
+fn format_padded(seconds: f64) -> String {
+  let seconds = seconds.floor() as u64;
+  let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+  if hours > 0 {
+    format!("{}h{:02}m{:02}s", hours, minutes, seconds)
+  } else {
+    format!("{}m{:02}s", minutes, seconds)
+  }
+}
+
+/// Format a duration like a digital clock (e.g. `12:03` or `1:02:03`).
+fn format_digital(seconds: f64) -> String {
+  let seconds = seconds.floor() as u64;
+  let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+  if hours > 0 {
+    format!("{}:{:02}:{:02}", hours, minutes, seconds)
+  } else {
+    format!("{}:{:02}", minutes, seconds)
+  }
+}
+
+/// Get the clock format matching the convention of the user's locale, 12-hour or 24-hour.
+fn locale_clock_format() -> &'static str {
+  #[cfg(target_family = "windows")]
+  {
+    use windows_sys::Win32::Globalization::{GetLocaleInfoEx, LOCALE_STIMEFORMAT};
+
+    let mut buffer = [0u16; 80];
+    let length = unsafe { GetLocaleInfoEx(std::ptr::null(), LOCALE_STIMEFORMAT, buffer.as_mut_ptr(), buffer.len() as i32) };
+
+    // hours are written as `H` in 24-hour formats and `h` in 12-hour formats
+    if length > 0 && !String::from_utf16_lossy(&buffer[..length as usize]).contains('H') {
+      return CLOCK_FORMAT_12_HOUR;
+    }
+
+    CLOCK_FORMAT_24_HOUR
+  }
+
+  #[cfg(not(target_family = "windows"))]
+  {
+    // regions that mostly use 12-hour time
+    const TWELVE_HOUR_REGIONS: [&str; 12] = ["US", "CA", "AU", "NZ", "IN", "PH", "PK", "BD", "EG", "SA", "MY", "CO"];
+
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+      .iter()
+      .filter_map(|name| std::env::var(name).ok())
+      .find(|value| !value.is_empty());
+
+    // locales look like `en_US.UTF-8`, we only care about the region
+    let region = locale
+      .as_deref()
+      .and_then(|locale| locale.split(['.', '@']).next())
+      .and_then(|locale| locale.split_once('_'))
+      .map(|(_, region)| region);
+
+    match region {
+      Some(region) if TWELVE_HOUR_REGIONS.contains(&region) => CLOCK_FORMAT_12_HOUR,
+      _ => CLOCK_FORMAT_24_HOUR,
+    }
+  }
+}
+
+/// Get the value following `option`, exiting with an error if there isn't one.
+fn next_value<'a>(args: &mut impl Iterator<Item = &'a String>, option: &str) -> &'a str {
+  match args.next() {
+    Some(value) => value,
+    None => {
+      eprintln!("Missing value for {}", option);
+      exit(1);
+    }
+  }
+}
+
+/// Parse an option's value, exiting with an error if it isn't valid.
+fn parse_value<T: std::str::FromStr<Err = String>>(value: &str) -> T {
+  match value.parse() {
+    Ok(value) => value,
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
 
-  println!("Finished!");
+/// Parse `arg` if it's one of the filters narrowing down which timers a command applies to, taking its value from `args`, returning whether it was.
+fn parse_filter<'a>(arg: &str, args: &mut impl Iterator<Item = &'a String>, filter: &mut commands::Filter) -> bool {
+  match arg {
+    "--older-than" => filter.older_than = Some(parse_duration(next_value(args, arg))),
+    "--paused" => filter.paused = true,
+    _ => return false,
+  }
 
-  terminal::clear_line();
+  true
 }
 
 fn parse_duration(duration: &str) -> Duration {
+  match try_parse_duration(duration) {
+    Ok(duration) => duration,
+    Err(error) => {
+      eprintln!("{}", error);
+      exit(1);
+    }
+  }
+}
+
+/// Parse a duration like `1h30m`, `25m`, `90d`, or `90`, where plain numbers are seconds.
+fn try_parse_duration(duration: &str) -> Result<Duration, String> {
   let mut seconds = 0;
   let mut current_number = String::new(); // temporary buffer to store the currently parsing number
 
   for character in duration.chars() {
-    match character {
-      // take our current buffer and store it as seconds
-      's' => {
-        if current_number.is_empty() {
-          eprintln!("No number found before seconds");
-          exit(1);
-        }
-
-        seconds += current_number.parse::<u64>().unwrap();
-        current_number = String::new();
-      }
-      'm' => {
-        if current_number.is_empty() {
-          eprintln!("No number found before minutes");
-          exit(1);
-        }
-
-        seconds += current_number.parse::<u64>().unwrap() * 60;
-        current_number = String::new();
-      }
-      'h' => {
-        if current_number.is_empty() {
-          eprintln!("No number found before hours");
-          exit(1);
-        }
-
-        seconds += current_number.parse::<u64>().unwrap() * 3600;
-        current_number = String::new();
-      }
+    let unit = match character {
+      's' => 1,
+      'm' => 60,
+      'h' => 3600,
+      'd' => 86400,
 
       // append to our buffer
       '0'..='9' => {
         current_number.push(character);
+        continue;
       }
 
       // invalid character found
-      _ => {
-        eprintln!("Invalid time!");
-        exit(1);
-      }
+      _ => return Err("Invalid time!".to_string()),
+    };
+
+    // take our current buffer and store it in seconds
+    if current_number.is_empty() {
+      let name = match unit {
+        1 => "seconds",
+        60 => "minutes",
+        3600 => "hours",
+        _ => "days",
+      };
+
+      return Err(format!("No number found before {}", name));
     }
+
+    seconds += current_number.parse::<u64>().map_err(|_| "Invalid time!".to_string())? * unit;
+    current_number = String::new();
   }
 
   // if there are any remaining numbers, assume seconds
   if !current_number.is_empty() {
-    seconds += current_number.parse::<u64>().unwrap();
+    seconds += current_number.parse::<u64>().map_err(|_| "Invalid time!".to_string())?;
+  }
+
+  Ok(Duration::from_secs(seconds))
+}
+
+/// Get the directory for timr's own data, following the XDG convention on Unix.
+fn data_dir() -> PathBuf {
+  #[cfg(target_family = "windows")]
+  if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+    return PathBuf::from(local).join("timr");
+  }
+
+  #[cfg(not(target_family = "windows"))]
+  if let Some(data) = std::env::var_os("XDG_DATA_HOME").filter(|data| !data.is_empty()) {
+    return PathBuf::from(data).join("timr");
   }
 
-  Duration::from_secs(seconds)
+  home_dir().expect("Failed to find user's home directory").join(".local").join("share").join("timr")
 }
 
 fn home_dir() -> Option<PathBuf> {
@@ -309,16 +2783,151 @@ fn home_dir() -> Option<PathBuf> {
 
 fn print_help() {
   println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-  println!("Usage: {} [options]", env!("CARGO_PKG_NAME"));
+  println!("Usage: {} [options] duration...", env!("CARGO_PKG_NAME"));
+  println!("       {} <command>", env!("CARGO_PKG_NAME"));
+  println!();
+  println!("Commands:");
+  println!("  tmux-status    Print the running timer for tmux's status line");
+  println!("  statusbar [--format polybar|i3blocks]");
+  println!("                 Print the running timer in color for polybar or i3blocks");
+  println!("  pin name|--next");
+  println!("                 Show the timer called name in status bars and prompts, or the next one along with --next");
+  println!("  unpin          Go back to showing the timer ending soonest, or as primary in the config says");
+  println!("  watch [--json] [--ticks]");
+  println!("                 Print every event from the daemon as it happens, with the time remaining each second if --ticks");
+  println!("  serve [--socket path] [--takeover] [--http address]");
+  println!("                 Run timers for editor plugins and other tools, controlled with JSON-RPC over a Unix socket,");
+  println!("                 replacing the daemon already running with --takeover, and over a REST API too with --http");
+  println!("  serve --stdio  Run timers for an editor plugin, controlled with JSON-RPC over stdin and stdout");
+  println!("  relay [--listen address]");
+  println!("                 Alert here when timers on other machines finish, as sent with --relay (127.0.0.1:7878 by default)");
+  println!("  service install|uninstall [--user]");
+  println!("                 Start the daemon at login with systemd, launchd, or a scheduled task, or stop it doing so");
+  println!("  history [--today] [--since date] [--profile name] [--tag tag]");
+  println!("                 Print the finished timers in the history as a table, or only those since a date like 2024-01-01 or a duration ago");
+  println!("  history export --format csv|json|ics|timewarrior|toggl [--today] [--since date] [--profile name] [--tag tag]");
+  println!("                 Print the sessions in the history for spreadsheets and other tools, picked out the same way");
+  println!("  history prune [--older-than duration] [--keep count]");
+  println!("                 Let go of sessions older than the retention in the config or given, or beyond the most to keep");
+  println!("  history sync   Bring together the history here and on the remote under [sync] in the config, sending and fetching what's missing");
+  println!("  note [number] text");
+  println!("                 Note what was done in the last session in the history, or the one numbered in timr history");
+  println!("  stats [--today|--week|--month|--since date]");
+  println!("                 Sum up the history over the last week or the period given, by profile and by day");
+  println!("  profiles list [--stats]");
+  println!("                 Print the profiles in the config, with how often each has been run and how long for against the time it's set for");
+  println!("  report [--week|--month|--since date] [--by tag|profile]");
+  println!("                 Chart the time on timers each day over the last week or the period given, colored by tag or by profile");
+  println!("  export ics|csv|json|timewarrior|toggl");
+  println!("                 Print every session in the history as an iCalendar file, CSV, JSON, or for Timewarrior or Toggl Track to import");
+  println!("  prompt         Print a tiny segment for a shell prompt, like ⏳12m, while a timer is running");
+  println!("  menubar        Print the running timers as an xbar or SwiftBar plugin, with pause and cancel in the menu");
+  println!("  start [--name name] [--tag tag]... [--exec command] [--env name]... duration...");
+  println!("                 Start timers on the daemon in the background, leaving the terminal free, running the hook here with PATH and any --env passed on");
+  println!("  list [--older-than duration] [--paused]");
+  println!("                 Print every running timer with the time it has left, or only those started long enough ago or paused");
+  println!("  status [--json]");
+  println!("                 Print the state, time remaining, and percent done of every running timer, failing when there are none");
+  println!("  attach [name]  Show the timer called name, or the one ending soonest, until it ends; q detaches and leaves it running");
+  println!("  join address   Show the timers shared at address with --share, counting down in step with them");
+  println!("  dashboard      Show every timer on the daemon full screen, to pause, extend, cancel, or start them from the keyboard");
+  println!("  pause [name]   Pause every running timer, or the one called name");
+  println!("  resume [name]  Resume every paused timer, or the one called name, or timers lost when timr was closed");
+  println!("  cancel [name|--all] [--older-than duration] [--paused] [--reason text]");
+  println!("                 Stop every running timer, or the one called name, or only those started long enough ago or paused,");
+  println!("                 keeping the reason in the history");
+  println!("  extend [name] duration");
+  println!("                 Add duration to every timer in the background, or the one called name");
   println!();
   println!("Options:");
-  println!("  duration       Start a timer for duration");
+  println!("  duration       Start a timer for duration, or from a profile by name");
+  println!("                 Several timers can be run side by side");
+  println!("  --name name    Name the timer, to find it in timr list and control it with timr cancel name");
+  println!("  --tag tag      Tag the timer, like work or chores, to group it by in timr history and timr stats; can be repeated");
+  println!("  -c, --compact  Show a single line spinner instead of the progress bar");
+  println!("  -f, --flash    Flash the screen when the timer finishes");
+  println!("  --alarm-mode   Keep ringing when the timer finishes until a key is pressed or the notification is clicked");
+  println!("  --dnd ignore|quiet|flash");
+  println!("                 While in Do Not Disturb, alert as usual, hold back notifications and sounds, or flash the screen instead");
+  println!("  --speak        Say aloud when the timer finishes");
+  println!("  --speak-every duration");
+  println!("                 Say the time remaining aloud every so often");
+  println!("  --warn duration");
+  println!("                 Send a notification when this much time is left, with a button to add 5 minutes");
+  println!("  --warn-sound file");
+  println!("                 Play an audio file along with the warning");
+  println!("  -n, --notify   Show a desktop notification as each stage starts and when the timer finishes");
+  println!("                 On Windows and Linux, the finished notification can snooze or restart the timer");
+  println!("  --live-notify  Keep a notification up while the timer runs, following its progress (Linux only)");
+  println!("  --osc-notify   Have the terminal show a notification when the timer finishes, which works over SSH");
+  println!("  --copy-summary Copy a line like \"pasta — 10m — finished 18:42\" to the clipboard when the timer finishes");
+  println!("  --badge        Show the time left as a badge over the terminal (iTerm2 only)");
+  println!("  --keep-awake   Stop the machine from going to sleep while the timer runs");
+  println!("  --pause-media  Pause any music or videos playing when the timer finishes, so the alarm can be heard");
+  println!("  --dbus         Serve the timers on the session bus as dev.thenoah.timr, for desktop widgets (Linux only)");
+  println!("  --metrics-listen address");
+  println!("                 Serve Prometheus metrics on address, like 127.0.0.1:9184");
+  println!("  --share address");
+  println!("                 Share the timers on address, like 0.0.0.0:7228, for timr join to show on other machines");
+  println!("  --metrics-file file");
+  println!("                 Write Prometheus metrics to file, for node_exporter's textfile collector");
+  println!("  --webhook url  POST a JSON payload to url as timers start, finish, or are cancelled (needs curl)");
+  println!("  --event-pipe fifo");
+  println!("                 Write events to a FIFO as they happen, one JSON object per line, creating it if need be");
+  println!("  --webhook-body template");
+  println!("                 Send the webhook this in place of the JSON payload, filling in {{label}}, {{stage}}, {{status}},");
+  println!("                 {{duration}}, {{remaining}}, {{started_at}}, and {{ended_at}}");
+  println!("  --webhook-header \"name: value\"");
+  println!("                 Send the webhook an extra header, which can be given more than once");
+  println!("  --relay address");
+  println!("                 Tell timr relay at address when the timer finishes or is cancelled, like 127.0.0.1:7878");
+  println!("                 forwarded from your own machine with ssh -R 7878:localhost:7878");
+  println!("  --exec command");
+  println!("                 Run a shell command on every start, pause, resume, extension, stage, finish, and cancel,");
+  println!("                 with TIMR_* variables describing the timer");
+  println!("  --urgency low|normal|critical");
+  println!("                 How urgent the notifications are, critical ones staying on screen until dismissed");
+  println!("  --sound file   Play an audio file (WAV, MP3, OGG, or FLAC) as each stage starts and when the timer finishes");
+  println!("  --sound-theme custom|chime|beeps");
+  println!("                 Sounds for the start, each stage, the warning, and the finish, custom being the [sounds] table");
+  println!("  --volume 0-100 How loud to play the sound");
+  println!("  --bell pattern Ring the terminal bell in a pattern, a . for each ring and a space for each pause, like '... ...'");
+  println!("  -e, --elapsed  Show the elapsed and total time next to the time remaining");
+  println!("  --eta          Show the time the timer will finish");
+  println!("  --pin          Keep the timer on the bottom row, letting other output scroll above it");
+  println!("  --vertical     Take over the screen with a column filling up along its edge and large digits beside it");
+  println!("  --no-clock     Hide the current time");
+  println!("  --no-header    Hide the line naming the timer above the progress");
+  println!("  --no-percent   Hide the percentage");
+  println!("  --percent-style whole|decimal|left|fraction");
+  println!("                 Show the percentage as 42%, 42.7%, 57% left, or 17/40 min");
+  println!("  --face bar|pie|hourglass|kitty|sixel");
+  println!("                 Draw the progress as a bar, a pie, an hourglass, or with terminal graphics");
+  println!("  --emphasis none|bold|blink");
+  println!("                 Make the time remaining stand out as the timer is about to finish");
+  println!("  --emphasis-for duration");
+  println!("                 How long before the end to start the emphasis (default 10s)");
+  println!("  --theme default|high-contrast|deuteranopia|mono");
+  println!("                 Colors to draw the timer with");
+  println!("  --background auto|dark|light");
+  println!("                 Pick colors for a dark or light terminal (default asks the terminal)");
+  println!("  --align left|center|right");
+  println!("                 Where the timer sits horizontally in the terminal");
+  println!("  --clock-format format");
+  println!("                 strftime format for the current time (default depends on locale)");
+  println!("  -a, --accessible");
+  println!("                 Print plain status lines instead of redrawing, for screen readers");
+  println!("  -i, --interval duration");
+  println!("                 How often to print a status line in accessible mode or when not");
+  println!("                 writing to a terminal (default 30s)");
   println!("  -v, --version  Print version information");
   println!("  -h, --help     Print this help message");
-}
-
-fn lerp(a: u8, b: u8, t: f64) -> u8 {
-  ((1.0 - t) * (a as f64) + t * (b as f64)).round() as u8
+  println!();
+  println!("Keys:");
+  println!("  space, p       Pause/resume the timer");
+  println!("  +              Add a minute to the timer (or current stage)");
+  println!("  s              Skip to the next stage");
+  println!("  q, esc         Exit early");
 }
 
 #[cfg(test)]
@@ -378,4 +2987,41 @@ mod tests {
     assert_eq!(parse_duration("19h"), Duration::from_secs(68400));
     assert_eq!(parse_duration("61h"), Duration::from_secs(219600));
   }
+
+  #[test]
+  fn parse_days() {
+    assert_eq!(parse_duration("1d"), Duration::from_secs(86400));
+    assert_eq!(parse_duration("90d"), Duration::from_secs(7776000));
+    assert_eq!(parse_duration("1d12h"), Duration::from_secs(129600));
+  }
+
+  #[test]
+  fn format_remaining_units() {
+    assert_eq!(format_remaining(0.0), "0s");
+    assert_eq!(format_remaining(59.9), "59s");
+    assert_eq!(format_remaining(61.0), "1m1s");
+    assert_eq!(format_remaining(3600.0), "1h0s");
+    assert_eq!(format_remaining(3661.0), "1h1m1s");
+  }
+
+  #[test]
+  fn format_padded_units() {
+    assert_eq!(format_padded(0.0), "0m00s");
+    assert_eq!(format_padded(723.4), "12m03s");
+    assert_eq!(format_padded(1500.0), "25m00s");
+    assert_eq!(format_padded(3723.0), "1h02m03s");
+  }
+
+  #[test]
+  fn format_digital_units() {
+    assert_eq!(format_digital(723.4), "12:03");
+    assert_eq!(format_digital(3723.0), "1:02:03");
+  }
+
+  #[test]
+  fn format_short_units() {
+    assert_eq!(format_short(0.0), "0s");
+    assert_eq!(format_short(600.0), "10m");
+    assert_eq!(format_short(3630.0), "1h30s");
+  }
 }
@@ -0,0 +1,72 @@
+/// Pause every MPRIS media player on the session bus, so the alarm isn't drowned out.
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+pub fn pause() {
+  let result = (|| -> zbus::Result<()> {
+    let connection = zbus::blocking::Connection::session()?;
+
+    for name in zbus::blocking::fdo::DBusProxy::new(&connection)?.list_names()? {
+      if name.starts_with("org.mpris.MediaPlayer2.") {
+        // players that are already paused or stopped ignore it, and one failing is no reason to skip the rest
+        let _ = connection.call_method(Some(name.as_str()), "/org/mpris/MediaPlayer2", Some("org.mpris.MediaPlayer2.Player"), "Pause", &());
+      }
+    }
+
+    Ok(())
+  })();
+
+  if let Err(error) = result {
+    eprintln!("Failed to pause media: {}", error);
+  }
+}
+
+/// Pause every MPRIS media player with playerctl, as timr was built without D-Bus support.
+#[cfg(all(target_os = "linux", not(feature = "dbus")))]
+pub fn pause() {
+  let result = std::process::Command::new("playerctl")
+    .args(["--all-players", "pause"])
+    .stderr(std::process::Stdio::null())
+    .status();
+
+  if let Err(error) = result {
+    eprintln!("Failed to pause media with playerctl: {}", error);
+  }
+}
+
+/// Pause Music and Spotify if they're open, without opening them if they aren't.
+#[cfg(target_os = "macos")]
+pub fn pause() {
+  for app in ["Music", "Spotify"] {
+    let script = format!("if application \"{app}\" is running then tell application \"{app}\" to pause");
+    let result = std::process::Command::new("osascript").args(["-e", &script]).stderr(std::process::Stdio::null()).status();
+
+    if let Err(error) = result {
+      eprintln!("Failed to pause media: {}", error);
+      return;
+    }
+  }
+}
+
+/// Pause every app playing media through the system media controls, like the pause key does for one.
+#[cfg(windows)]
+pub fn pause() {
+  use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager;
+
+  let result = (|| -> windows::core::Result<()> {
+    let sessions = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()?.get()?.GetSessions()?;
+
+    for session in &sessions {
+      // unlike the play/pause key, this never starts anything that was already paused
+      let _ = session.TryPauseAsync()?.get();
+    }
+
+    Ok(())
+  })();
+
+  if let Err(error) = result {
+    eprintln!("Failed to pause media: {}", error);
+  }
+}
+
+/// Pause media players, which can't be done on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn pause() {}
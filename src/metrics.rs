@@ -0,0 +1,174 @@
+use std::{
+  fmt::Write as _,
+  fs,
+  io::{Read, Write},
+  net::TcpListener,
+  path::PathBuf,
+  sync::{Mutex, OnceLock},
+  thread,
+  time::{Duration, Instant},
+};
+
+/// How often the textfile is rewritten, as the collector only reads it every so often anyway.
+const WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A timer as shown in the gauges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+  pub label: String,
+  /// Seconds remaining.
+  pub remaining: f64,
+  /// Total duration in seconds.
+  pub duration: f64,
+  pub paused: bool,
+}
+
+/// Name and help text of a gauge, along with how to get its value for a timer.
+type Gauge = (&'static str, &'static str, fn(&Sample) -> f64);
+
+/// Everything exported, kept for as long as timr runs.
+#[derive(Debug, Default)]
+struct Metrics {
+  timers: Vec<Sample>,
+  completed: u64,
+  cancelled: u64,
+}
+
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+  timers: Vec::new(),
+  completed: 0,
+  cancelled: 0,
+});
+
+/// Textfile to write the metrics to, and when it was last written.
+static TEXTFILE: OnceLock<Mutex<(PathBuf, Option<Instant>)>> = OnceLock::new();
+
+/// Serve the metrics over HTTP on `address`, like `127.0.0.1:9184`, for Prometheus to scrape.
+pub fn listen(address: &str) -> std::io::Result<()> {
+  let listener = TcpListener::bind(address)?;
+
+  thread::spawn(move || {
+    for mut stream in listener.incoming().flatten() {
+      // every path gets the metrics, so there's no need to look at the request beyond reading it
+      let mut request = [0; 1024];
+      let _ = stream.read(&mut request);
+
+      let body = render(&METRICS.lock().unwrap());
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+      );
+    }
+  });
+
+  Ok(())
+}
+
+/// Write the metrics to `path` as they change, for node_exporter's textfile collector to pick up.
+pub fn write_to(path: PathBuf) {
+  let _ = TEXTFILE.set(Mutex::new((path, None)));
+}
+
+/// Replace the timers shown in the gauges.
+pub fn update(timers: Vec<Sample>) {
+  METRICS.lock().unwrap().timers = timers;
+  write_textfile(false);
+}
+
+/// Count a timer as having finished.
+pub fn completed() {
+  METRICS.lock().unwrap().completed += 1;
+  write_textfile(true);
+}
+
+/// Count a timer as having been cancelled.
+pub fn cancelled() {
+  METRICS.lock().unwrap().cancelled += 1;
+  write_textfile(true);
+}
+
+/// Write the textfile, if there is one, unless it was written a moment ago and the change isn't worth writing straight away.
+fn write_textfile(now: bool) {
+  let Some(textfile) = TEXTFILE.get() else {
+    return;
+  };
+
+  let mut textfile = textfile.lock().unwrap();
+
+  if !now && textfile.1.is_some_and(|written| written.elapsed() < WRITE_INTERVAL) {
+    return;
+  }
+
+  // write to the side and move into place so the collector never sees half a file
+  let temporary = textfile.0.with_extension("tmp");
+  let body = render(&METRICS.lock().unwrap());
+
+  if let Err(error) = fs::write(&temporary, body).and_then(|_| fs::rename(&temporary, &textfile.0)) {
+    eprintln!("Failed to write metrics to {}: {}", textfile.0.display(), error);
+  }
+
+  textfile.1 = Some(Instant::now());
+}
+
+/// Write `metrics` in the Prometheus text format.
+fn render(metrics: &Metrics) -> String {
+  let mut output = String::new();
+
+  let gauges: [Gauge; 3] = [
+    ("timr_remaining_seconds", "Seconds left on the timer.", |sample| sample.remaining),
+    ("timr_duration_seconds", "Total duration of the timer in seconds.", |sample| sample.duration),
+    ("timr_paused", "Whether the timer is paused.", |sample| if sample.paused { 1.0 } else { 0.0 }),
+  ];
+
+  for (name, help, value) in gauges {
+    let _ = writeln!(output, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+
+    for sample in &metrics.timers {
+      let _ = writeln!(output, "{}{{label=\"{}\"}} {}", name, escape_label(&sample.label), value(sample));
+    }
+  }
+
+  let counters = [
+    ("timr_timers_completed_total", "Timers that have finished.", metrics.completed),
+    ("timr_timers_cancelled_total", "Timers that were cancelled before finishing.", metrics.cancelled),
+  ];
+
+  for (name, help, value) in counters {
+    let _ = writeln!(output, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+  }
+
+  output
+}
+
+/// Escape `value` to go in a label between quotes.
+fn escape_label(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn text_format() {
+    let metrics = Metrics {
+      timers: vec![Sample {
+        label: "tea \"green\"".to_string(),
+        remaining: 90.5,
+        duration: 240.0,
+        paused: true,
+      }],
+      completed: 2,
+      cancelled: 1,
+    };
+
+    let output = render(&metrics);
+
+    assert!(output.contains("# TYPE timr_remaining_seconds gauge\ntimr_remaining_seconds{label=\"tea \\\"green\\\"\"} 90.5\n"));
+    assert!(output.contains("timr_paused{label=\"tea \\\"green\\\"\"} 1\n"));
+    assert!(output.contains("# TYPE timr_timers_completed_total counter\ntimr_timers_completed_total 2\n"));
+    assert!(output.contains("timr_timers_cancelled_total 1\n"));
+  }
+}
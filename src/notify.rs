@@ -0,0 +1,457 @@
+use std::{sync::mpsc::Receiver, time::Duration};
+
+use serde::Deserialize;
+
+/// How long the snooze button on a finished notification puts the timer off for.
+pub const SNOOZE: Duration = Duration::from_secs(5 * 60);
+/// How much time the extend button adds to a running timer.
+pub const EXTEND: Duration = Duration::from_secs(5 * 60);
+
+/// Icon shown when the profile doesn't pick one, from the freedesktop icon naming spec.
+#[cfg(feature = "notifications")]
+const DEFAULT_ICON: &str = "appointment-soon";
+
+/// What a timer's desktop notifications look like, from its label and profile.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+pub struct Template {
+  pub title: String,
+  /// Message shown when the timer finishes, in place of the default.
+  pub body: Option<String>,
+  /// Icon name or path to an image.
+  pub icon: Option<String>,
+  /// How urgent the notifications are, which some platforms can't show.
+  #[cfg_attr(target_os = "macos", allow(dead_code))]
+  pub urgency: Urgency,
+}
+
+/// How urgently a notification asks for attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+  /// Shown without sound or popping up, where the notification daemon supports it.
+  Low,
+  #[default]
+  Normal,
+  /// Stays on screen until dismissed and gets through the daemon's quiet settings.
+  Critical,
+}
+
+impl std::str::FromStr for Urgency {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "low" => Ok(Self::Low),
+      "normal" => Ok(Self::Normal),
+      "critical" => Ok(Self::Critical),
+      _ => Err(format!("Invalid urgency: {}", value)),
+    }
+  }
+}
+
+/// A button pressed on a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(all(any(windows, all(unix, not(target_os = "macos"))), feature = "notifications")), allow(dead_code))]
+pub enum Action {
+  Snooze,
+  Restart,
+  /// Add some time to a timer that's still running.
+  Extend,
+  /// Clicked on the notification itself or its dismiss button, to say it has been seen.
+  Dismiss,
+}
+
+#[cfg_attr(not(all(any(windows, all(unix, not(target_os = "macos"))), feature = "notifications")), allow(dead_code))]
+impl Action {
+  /// Get the name the button is known by to the notification server.
+  fn id(self) -> &'static str {
+    match self {
+      Self::Snooze => "snooze",
+      Self::Restart => "restart",
+      Self::Extend => "extend",
+      Self::Dismiss => "dismiss",
+    }
+  }
+
+  /// Get the text shown on the button.
+  fn label(self) -> String {
+    match self {
+      Self::Snooze => format!("Snooze {}m", SNOOZE.as_secs() / 60),
+      Self::Restart => "Restart".to_string(),
+      Self::Extend => format!("+{}m", EXTEND.as_secs() / 60),
+      Self::Dismiss => "Dismiss".to_string(),
+    }
+  }
+
+  /// Find the button known by `id`, with anything else counting as a click on the notification itself.
+  fn from_id(id: &str) -> Self {
+    [Self::Snooze, Self::Restart, Self::Extend]
+      .into_iter()
+      .find(|action| action.id() == id)
+      .unwrap_or(Self::Dismiss)
+  }
+}
+
+/// What to do with alerts while the system is in Do Not Disturb or Focus mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DoNotDisturb {
+  /// Alert as usual.
+  #[default]
+  Ignore,
+  /// Hold back notifications, sounds, speech, and the bell.
+  Quiet,
+  /// Hold them back, flashing the screen instead.
+  Flash,
+}
+
+impl std::str::FromStr for DoNotDisturb {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "ignore" => Ok(Self::Ignore),
+      "quiet" => Ok(Self::Quiet),
+      "flash" => Ok(Self::Flash),
+      _ => Err(format!("Invalid do not disturb mode: {}", value)),
+    }
+  }
+}
+
+/// Check whether the desktop is in Do Not Disturb mode, as set in GNOME, dunst, or mako.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn do_not_disturb() -> bool {
+  command_output("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"]).is_some_and(|output| output.trim() == "false")
+    || command_output("dunstctl", &["is-paused"]).is_some_and(|output| output.trim() == "true")
+    || command_output("makoctl", &["mode"]).is_some_and(|output| output.lines().any(|mode| mode == "do-not-disturb"))
+}
+
+/// Check whether a Focus is on.
+#[cfg(target_os = "macos")]
+pub fn do_not_disturb() -> bool {
+  let Some(home) = crate::home_dir() else {
+    return false;
+  };
+
+  // active Focus assertions are kept here, reading it needs full disk access so assume all is clear when it can't be
+  std::fs::read_to_string(home.join("Library/DoNotDisturb/DB/Assertions.json")).is_ok_and(|assertions| assertions.contains("\"assertionDetails\""))
+}
+
+/// Check whether Focus Assist, presentation mode, or a full screen game is holding back notifications.
+#[cfg(windows)]
+pub fn do_not_disturb() -> bool {
+  use windows_sys::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME, QUNS_RUNNING_D3D_FULL_SCREEN};
+
+  let mut state = 0;
+
+  if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+    return false;
+  }
+
+  matches!(state, QUNS_BUSY | QUNS_PRESENTATION_MODE | QUNS_QUIET_TIME | QUNS_RUNNING_D3D_FULL_SCREEN)
+}
+
+/// Check for Do Not Disturb mode, which can't be detected on this platform.
+#[cfg(not(any(unix, windows)))]
+pub fn do_not_disturb() -> bool {
+  false
+}
+
+/// Do Not Disturb turned on for a focus session, put back as it was when dropped.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+pub struct Focus {
+  /// Commands undoing what was turned on, leaving alone anything that was already on.
+  restore: Vec<(&'static str, &'static [&'static str])>,
+}
+
+/// Turn on Do Not Disturb in GNOME, dunst, and mako, whichever are around.
+///
+/// Returns `None` when none of them could be turned on.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn focus() -> Option<Focus> {
+  let mut restore: Vec<(&str, &[&str])> = Vec::new();
+  let mut found = false;
+
+  if let Some(banners) = command_output("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"]) {
+    found = true;
+
+    if banners.trim() == "true" && command_output("gsettings", &["set", "org.gnome.desktop.notifications", "show-banners", "false"]).is_some() {
+      restore.push(("gsettings", &["set", "org.gnome.desktop.notifications", "show-banners", "true"]));
+    }
+  }
+
+  if let Some(paused) = command_output("dunstctl", &["is-paused"]) {
+    found = true;
+
+    if paused.trim() == "false" && command_output("dunstctl", &["set-paused", "true"]).is_some() {
+      restore.push(("dunstctl", &["set-paused", "false"]));
+    }
+  }
+
+  if let Some(modes) = command_output("makoctl", &["mode"]) {
+    found = true;
+
+    if !modes.lines().any(|mode| mode == "do-not-disturb") && command_output("makoctl", &["mode", "-a", "do-not-disturb"]).is_some() {
+      restore.push(("makoctl", &["mode", "-r", "do-not-disturb"]));
+    }
+  }
+
+  if !found {
+    eprintln!("Failed to turn on Do Not Disturb: no GNOME, dunst, or mako found");
+    return None;
+  }
+
+  Some(Focus { restore })
+}
+
+/// Turn on Do Not Disturb, which there's no way for apps to do on this platform.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn focus() -> Option<Focus> {
+  eprintln!("Turning on Do Not Disturb isn't supported on this platform");
+  None
+}
+
+impl Drop for Focus {
+  fn drop(&mut self) {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    for (program, args) in &self.restore {
+      if command_output(program, args).is_none() {
+        eprintln!("Failed to turn off Do Not Disturb with {}", program);
+      }
+    }
+  }
+}
+
+/// Run `program`, getting what it printed if it ran successfully.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+  let output = std::process::Command::new(program).args(args).stderr(std::process::Stdio::null()).output().ok()?;
+
+  output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// A notification kept up to date with a timer's progress, replacing itself instead of piling up.
+#[cfg_attr(not(all(unix, not(target_os = "macos"), feature = "notifications")), allow(dead_code))]
+pub struct Live {
+  #[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+  handle: notify_rust::NotificationHandle,
+  /// What the notification last said, to only update it when something changes.
+  body: String,
+}
+
+impl Live {
+  /// Replace what the notification says with `body` and its progress bar with `progress` (0-1).
+  pub fn update(&mut self, body: &str, progress: f64) {
+    if self.body == body {
+      return;
+    }
+
+    self.body = body.to_string();
+
+    #[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+    {
+      self.handle.body = self.body.clone();
+      self.handle.hints.retain(|hint| !matches!(hint, notify_rust::Hint::CustomInt(name, _) if name == "value"));
+      self.handle.hint(progress_hint(progress));
+
+      // the countdown carries on regardless, and a failure here would only repeat every second
+      let _ = self.handle.update();
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"), feature = "notifications")))]
+    let _ = progress;
+  }
+
+  /// Take the notification down.
+  pub fn close(self) {
+    #[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+    self.handle.close();
+  }
+}
+
+/// Check whether notifications can be updated in place to follow a timer's progress.
+pub fn live_supported() -> bool {
+  cfg!(all(unix, not(target_os = "macos"), feature = "notifications"))
+}
+
+/// Show a notification that stays up and follows the timer's progress, saying `body` to begin with.
+///
+/// Returns `None` where notifications can't be updated in place.
+#[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+pub fn live(template: &Template, body: &str, progress: f64) -> Option<Live> {
+  let template = Template { body: None, ..template.clone() };
+
+  // low urgency so it sits quietly in the tray until the finished notification comes along
+  let result = notification(&template, body)
+    .urgency(notify_rust::Urgency::Low)
+    .hint(notify_rust::Hint::Resident(true))
+    .hint(progress_hint(progress))
+    .timeout(notify_rust::Timeout::Never)
+    .show();
+
+  match result {
+    Ok(handle) => Some(Live { handle, body: body.to_string() }),
+    Err(error) => {
+      eprintln!("Failed to show notification: {}", error);
+      None
+    }
+  }
+}
+
+/// Show a notification following the timer's progress, which can't be done on this platform.
+#[cfg(not(all(unix, not(target_os = "macos"), feature = "notifications")))]
+pub fn live(_template: &Template, _body: &str, _progress: f64) -> Option<Live> {
+  None
+}
+
+/// Get the hint drawing a progress bar in the notification, understood by GNOME and KDE.
+#[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+fn progress_hint(progress: f64) -> notify_rust::Hint {
+  notify_rust::Hint::CustomInt("value".to_string(), (progress * 100.0).round() as i32)
+}
+
+/// Check whether notifications can have buttons that report back when pressed.
+pub fn has_buttons() -> bool {
+  cfg!(all(any(windows, all(unix, not(target_os = "macos"))), feature = "notifications"))
+}
+
+/// Check whether timr was built with desktop notification support.
+pub fn supported() -> bool {
+  cfg!(feature = "notifications")
+}
+
+/// Show a desktop notification using `template`, with `body` unless the template has its own.
+#[cfg(feature = "notifications")]
+pub fn send(template: &Template, body: &str) {
+  let result = notification(template, body).show();
+
+  // the timer itself is still useful without a notification daemon, so don't bail out
+  if let Err(error) = result {
+    eprintln!("Failed to show notification: {}", error);
+  }
+}
+
+/// Build a notification from `template`, with `body` unless the template has its own.
+#[cfg(feature = "notifications")]
+fn notification(template: &Template, body: &str) -> notify_rust::Notification {
+  let mut notification = notify_rust::Notification::new();
+
+  notification
+    .appname(env!("CARGO_PKG_NAME"))
+    .summary(&template.title)
+    .body(template.body.as_deref().unwrap_or(body))
+    .icon(template.icon.as_deref().unwrap_or(DEFAULT_ICON));
+
+  // only the freedesktop spec has urgency, Windows toasts get theirs from a scenario instead
+  #[cfg(all(unix, not(target_os = "macos")))]
+  notification.urgency(match template.urgency {
+    Urgency::Low => notify_rust::Urgency::Low,
+    Urgency::Normal => notify_rust::Urgency::Normal,
+    Urgency::Critical => notify_rust::Urgency::Critical,
+  });
+
+  notification
+}
+
+/// Show a desktop notification, which does nothing without notification support.
+#[cfg(not(feature = "notifications"))]
+pub fn send(_template: &Template, _body: &str) {}
+
+/// Show a notification with `buttons`, along with clicking on the notification itself.
+///
+/// Buttons pressed are sent back through the returned channel, which is `None` when the toast couldn't be shown.
+#[cfg(all(windows, feature = "notifications"))]
+pub fn send_with_buttons(template: &Template, body: &str, buttons: &[Action]) -> Option<Receiver<Action>> {
+  toast(template, body, buttons)
+}
+
+/// Show a notification with `buttons`, along with clicking on the notification itself.
+///
+/// Buttons pressed are sent back through the returned channel, which is `None` when the notification couldn't be shown.
+#[cfg(all(unix, not(target_os = "macos"), feature = "notifications"))]
+pub fn send_with_buttons(template: &Template, body: &str, buttons: &[Action]) -> Option<Receiver<Action>> {
+  let mut notification = notification(template, body);
+  notification.action("default", "Dismiss");
+
+  for button in buttons {
+    notification.action(button.id(), &button.label());
+  }
+
+  let handle = match notification.show() {
+    Ok(handle) => handle,
+    Err(error) => {
+      eprintln!("Failed to show notification: {}", error);
+      return None;
+    }
+  };
+
+  let (action_tx, action_rx) = std::sync::mpsc::channel();
+
+  // waiting blocks until the notification is clicked or closed
+  std::thread::spawn(move || {
+    handle.wait_for_action(|action| {
+      if action != "__closed" {
+        let _ = action_tx.send(Action::from_id(action));
+      }
+    })
+  });
+
+  Some(action_rx)
+}
+
+/// Show a notification, which can't have buttons on this platform.
+#[cfg(all(target_os = "macos", feature = "notifications"))]
+pub fn send_with_buttons(template: &Template, body: &str, _buttons: &[Action]) -> Option<Receiver<Action>> {
+  send(template, body);
+  None
+}
+
+/// Show a notification with buttons, which does nothing without notification support.
+#[cfg(not(feature = "notifications"))]
+pub fn send_with_buttons(_template: &Template, _body: &str, _buttons: &[Action]) -> Option<Receiver<Action>> {
+  None
+}
+
+/// Show a Windows toast with `buttons`.
+#[cfg(all(windows, feature = "notifications"))]
+fn toast(template: &Template, body: &str, buttons: &[Action]) -> Option<Receiver<Action>> {
+  use std::{path::Path, sync::mpsc::channel};
+
+  use tauri_winrt_notification::{IconCrop, Scenario, Toast};
+
+  let (action_tx, action_rx) = channel();
+
+  let mut toast = Toast::new(Toast::POWERSHELL_APP_ID)
+    .title(&template.title)
+    .text1(template.body.as_deref().unwrap_or(body))
+    .on_activated(move |action| {
+      // clicking the toast itself has no action
+      let _ = action_tx.send(action.as_deref().map_or(Action::Dismiss, Action::from_id));
+      Ok(())
+    });
+
+  for button in buttons {
+    toast = toast.add_button(&button.label(), button.id());
+  }
+
+  match template.urgency {
+    Urgency::Low => toast = toast.sound(None),
+    Urgency::Normal => {}
+    // stays on screen until one of the buttons is pressed
+    Urgency::Critical => toast = toast.scenario(Scenario::Reminder),
+  }
+
+  // toasts only take images, not icon names
+  if let Some(icon) = template.icon.as_deref().map(Path::new).filter(|icon| icon.exists()) {
+    toast = toast.icon(icon, IconCrop::Square, &template.title);
+  }
+
+  match toast.show() {
+    Ok(()) => Some(action_rx),
+    Err(error) => {
+      eprintln!("Failed to show notification: {}", error);
+      None
+    }
+  }
+}
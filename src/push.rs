@@ -0,0 +1,83 @@
+use crate::{
+  config,
+  notify::{Template, Urgency},
+  webhook::{self, json_string},
+};
+
+const NTFY_SERVER: &str = "https://ntfy.sh";
+const PUSHOVER_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// Push a notification to every service set up in `push`, using `template` with `body` unless the template has its own.
+pub fn send(push: &config::Push, template: &Template, body: &str) {
+  let body = template.body.as_deref().unwrap_or(body);
+
+  if let Some(ntfy) = &push.ntfy {
+    let url = format!("{}/{}", ntfy.server.as_deref().unwrap_or(NTFY_SERVER).trim_end_matches('/'), ntfy.topic);
+    let title = format!("Title: {}", template.title);
+    let priority = format!("Priority: {}", ntfy_priority(template.urgency));
+    let mut options = vec![("header", title.as_str()), ("header", &priority), ("header", "Tags: alarm_clock"), ("data-raw", body)];
+
+    let authorization = ntfy.token.as_ref().map(|token| format!("Authorization: Bearer {}", token));
+
+    if let Some(authorization) = &authorization {
+      options.push(("header", authorization));
+    }
+
+    webhook::send(&url, &options);
+  }
+
+  if let Some(gotify) = &push.gotify {
+    let url = format!("{}/message", gotify.url.trim_end_matches('/'));
+    let key = format!("X-Gotify-Key: {}", gotify.token);
+    let json = format!(
+      r#"{{"title":{},"message":{},"priority":{}}}"#,
+      json_string(&template.title),
+      json_string(body),
+      gotify_priority(template.urgency)
+    );
+
+    webhook::send(&url, &[("header", &key), ("header", "Content-Type: application/json"), ("data-raw", &json)]);
+  }
+
+  if let Some(pushover) = &push.pushover {
+    let fields = [
+      format!("token={}", pushover.token),
+      format!("user={}", pushover.user),
+      format!("title={}", template.title),
+      format!("message={}", body),
+      format!("priority={}", pushover_priority(template.urgency)),
+    ];
+
+    // form strings are sent as is, without curl reading files for values starting with @
+    let options = fields.iter().map(|field| ("form-string", field.as_str())).collect::<Vec<_>>();
+
+    webhook::send(PUSHOVER_URL, &options);
+  }
+}
+
+/// Get ntfy's priority for `urgency`, from 1 (min) to 5 (max).
+fn ntfy_priority(urgency: Urgency) -> u8 {
+  match urgency {
+    Urgency::Low => 2,
+    Urgency::Normal => 3,
+    Urgency::Critical => 5,
+  }
+}
+
+/// Get Gotify's priority for `urgency`, from 0 to 10 where 8 and above pop up on Android.
+fn gotify_priority(urgency: Urgency) -> u8 {
+  match urgency {
+    Urgency::Low => 2,
+    Urgency::Normal => 5,
+    Urgency::Critical => 8,
+  }
+}
+
+/// Get Pushover's priority for `urgency`, from -2 (lowest) to 1 (high, which bypasses quiet hours).
+fn pushover_priority(urgency: Urgency) -> i8 {
+  match urgency {
+    Urgency::Low => -1,
+    Urgency::Normal => 0,
+    Urgency::Critical => 1,
+  }
+}
@@ -0,0 +1,139 @@
+use std::{
+  io::{self, BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream, ToSocketAddrs},
+  process::exit,
+  sync::Arc,
+  thread,
+  time::Duration,
+};
+
+use crate::{
+  config, json, notify, parse_value, sound,
+  terminal::{self, BellPattern},
+};
+
+/// Address the relay listens on and timers send to by default, to be forwarded from remote machines with `ssh -R 7878:localhost:7878`.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// How long to wait on the other end before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(3);
+/// Most read from a timer before hanging up on it, far more than the events it sends.
+const MAX_READ: u64 = 64 * 1024;
+
+/// Send `event`, a JSON payload, to the relay at `address`.
+pub fn send(address: &str, event: &str) {
+  let result = (|| -> io::Result<()> {
+    let resolved = address
+      .to_socket_addrs()?
+      .next()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address didn't resolve"))?;
+
+    let mut stream = TcpStream::connect_timeout(&resolved, TIMEOUT)?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    writeln!(stream, "{}", event)
+  })();
+
+  if let Err(error) = result {
+    eprintln!("Failed to reach the relay at {}: {}", address, error);
+  }
+}
+
+/// What to do here as timers on other machines finish, as the config says.
+struct Relaying {
+  sound: Option<sound::Sound>,
+  volume: u8,
+  bell: BellPattern,
+  urgency: notify::Urgency,
+}
+
+/// Listen on `address` for timers on other machines, notifying, playing the sound, and ringing the bell here as they finish or are cancelled.
+pub fn listen(address: &str) {
+  let config = config::load();
+
+  let listener = TcpListener::bind(address).unwrap_or_else(|error| {
+    eprintln!("Failed to listen on {}: {}", address, error);
+    exit(1);
+  });
+
+  let relaying = Arc::new(Relaying {
+    sound: config.finish_sound(),
+    volume: config.volume.unwrap_or(sound::DEFAULT_VOLUME),
+    bell: config.bell.as_deref().map(parse_value).unwrap_or_default(),
+    urgency: config.urgency.unwrap_or_default(),
+  });
+
+  println!("Relaying timers on {}", address);
+
+  for stream in listener.incoming().flatten() {
+    let relaying = relaying.clone();
+
+    // each on its own, so a timer that stalls doesn't hold up the rest
+    thread::spawn(move || relay(stream, &relaying));
+  }
+}
+
+/// Relay each event a timer sends on `stream`, until it hangs up.
+fn relay(stream: TcpStream, relaying: &Relaying) {
+  // timers only send a line before hanging up, so one that stalls is let go
+  if stream.set_read_timeout(Some(TIMEOUT)).is_err() {
+    return;
+  }
+
+  for line in BufReader::new(stream.take(MAX_READ)).lines().map_while(Result::ok) {
+    let event = match json::parse(&line) {
+      Ok(event) => event,
+      Err(error) => {
+        eprintln!("Ignoring invalid event: {}", error);
+        continue;
+      }
+    };
+
+    let Some((label, message)) = message(&event) else {
+      continue;
+    };
+
+    println!("{}", message);
+
+    let template = notify::Template {
+      title: label,
+      urgency: relaying.urgency,
+      ..Default::default()
+    };
+
+    notify::send(&template, &message);
+
+    if let Some(sound) = &relaying.sound {
+      sound::play(sound, relaying.volume);
+    }
+
+    terminal::ring(&relaying.bell);
+  }
+}
+
+/// Get the label of the timer `event` is about and what to tell the user, for the events worth relaying.
+fn message(event: &json::Value) -> Option<(String, String)> {
+  let label = event.get("label").and_then(json::Value::as_str).unwrap_or("timr").to_string();
+
+  let message = match event.get("event").and_then(json::Value::as_str)? {
+    "finish" => format!("{} finished", label),
+    "cancel" => format!("{} was cancelled", label),
+    _ => return None,
+  };
+
+  Some((label, message))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn messages() {
+    let finished = json::parse(r#"{"event":"finish","label":"build","remaining":0}"#).unwrap();
+    let paused = json::parse(r#"{"event":"pause","label":"build"}"#).unwrap();
+
+    assert_eq!(message(&finished), Some(("build".to_string(), "build finished".to_string())));
+    assert_eq!(message(&paused), None);
+  }
+}
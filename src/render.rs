@@ -0,0 +1,656 @@
+use std::{f64::consts::PI, fmt::Write as _};
+
+use serde::Deserialize;
+
+use crate::terminal::{self, graphics::Image};
+
+pub const BAR_EMPTY_CHAR: char = '▒';
+pub const BAR_FULL_CHAR: char = '█';
+const PIE_DIAMETER: usize = 16; // braille dots, 8 columns by 4 rows
+const HOURGLASS_CHAMBER: [usize; 3] = [5, 3, 1]; // width of each row of the top chamber, the bottom chamber is the mirror image
+const HOURGLASS_SAND: char = ':';
+const IMAGE_CELL_WIDTH: u32 = 10; // pixels per column when drawing the bar as an image, the terminal scales it to fit
+const IMAGE_CELL_HEIGHT: u32 = 20;
+const IMAGE_SAMPLES: u32 = 4; // samples along each axis per pixel, to smooth the edges
+
+/// How the progress of the timer is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Face {
+  #[default]
+  Bar,
+  Pie,
+  Hourglass,
+  /// A smooth image bar on terminals with the kitty graphics protocol, the regular bar elsewhere.
+  Kitty,
+  /// Large digits and a bar drawn with sixel graphics.
+  Sixel,
+}
+
+impl std::str::FromStr for Face {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "bar" => Ok(Self::Bar),
+      "pie" => Ok(Self::Pie),
+      "hourglass" => Ok(Self::Hourglass),
+      "kitty" => Ok(Self::Kitty),
+      "sixel" => Ok(Self::Sixel),
+      _ => Err(format!("Invalid face: {}", value)),
+    }
+  }
+}
+
+/// A color as red, green, and blue.
+pub type Rgb = (u8, u8, u8);
+
+/// Colors used to draw the timer, `None` leaves the terminal's own color in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+  /// Colors the filled part of the bar fades between, from start to end.
+  pub gradient: Option<(Rgb, Rgb)>,
+  /// Color of the empty part of the bar.
+  pub empty: Option<Rgb>,
+  /// Color of the pie.
+  pub accent: Option<Rgb>,
+  /// Color of the sand in the hourglass.
+  pub sand: Option<Rgb>,
+  /// Color of the time remaining as the timer is about to finish.
+  pub warning: Option<Rgb>,
+}
+
+/// Built-in themes selectable by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+  #[default]
+  Default,
+  HighContrast,
+  /// Avoids telling states apart by red and green alone.
+  Deuteranopia,
+  /// No colors at all, only the shapes of the characters.
+  Mono,
+}
+
+impl std::str::FromStr for ThemeName {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "default" => Ok(Self::Default),
+      "high-contrast" => Ok(Self::HighContrast),
+      "deuteranopia" => Ok(Self::Deuteranopia),
+      "mono" => Ok(Self::Mono),
+      _ => Err(format!("Invalid theme: {}", value)),
+    }
+  }
+}
+
+impl ThemeName {
+  /// Get the colors of the theme, for a terminal with a `light` or dark background.
+  pub fn theme(self, light: bool) -> Theme {
+    match (self, light) {
+      (Self::Default, false) => Theme {
+        gradient: Some(((90, 105, 237), (123, 90, 237))),
+        empty: Some((100, 100, 100)),
+        accent: Some((107, 98, 237)),
+        sand: Some((237, 201, 107)),
+        warning: Some((237, 90, 90)),
+      },
+      (Self::Default, true) => Theme {
+        gradient: Some(((90, 105, 237), (123, 90, 237))),
+        empty: Some((200, 200, 200)),
+        accent: Some((107, 98, 237)),
+        sand: Some((191, 140, 30)),
+        warning: Some((200, 40, 40)),
+      },
+      (Self::HighContrast, false) => Theme {
+        gradient: Some(((255, 255, 255), (255, 255, 255))),
+        empty: Some((90, 90, 90)),
+        accent: Some((255, 255, 255)),
+        sand: Some((255, 255, 0)),
+        warning: Some((255, 255, 0)),
+      },
+      (Self::HighContrast, true) => Theme {
+        gradient: Some(((0, 0, 0), (0, 0, 0))),
+        empty: Some((170, 170, 170)),
+        accent: Some((0, 0, 0)),
+        sand: Some((0, 0, 0)),
+        warning: Some((0, 0, 238)),
+      },
+      // blue and orange from the Okabe-Ito palette, which stay distinct for all common kinds of color blindness
+      (Self::Deuteranopia, false) => Theme {
+        gradient: Some(((0, 114, 178), (86, 180, 233))),
+        empty: Some((100, 100, 100)),
+        accent: Some((86, 180, 233)),
+        sand: Some((240, 228, 66)),
+        warning: Some((230, 159, 0)),
+      },
+      (Self::Deuteranopia, true) => Theme {
+        gradient: Some(((0, 114, 178), (86, 180, 233))),
+        empty: Some((200, 200, 200)),
+        accent: Some((0, 114, 178)),
+        sand: Some((213, 94, 0)),
+        warning: Some((213, 94, 0)),
+      },
+      (Self::Mono, _) => Theme {
+        gradient: None,
+        empty: None,
+        accent: None,
+        sand: None,
+        warning: None,
+      },
+    }
+  }
+}
+
+/// What kind of background the terminal has, to pick colors that stand out against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Background {
+  /// Ask the terminal, assuming dark if it doesn't say.
+  #[default]
+  Auto,
+  Dark,
+  Light,
+}
+
+impl std::str::FromStr for Background {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "auto" => Ok(Self::Auto),
+      "dark" => Ok(Self::Dark),
+      "light" => Ok(Self::Light),
+      _ => Err(format!("Invalid background: {}", value)),
+    }
+  }
+}
+
+/// Check whether `color` is light enough that dark text stands out better on it.
+pub fn is_light(color: Rgb) -> bool {
+  let (red, green, blue) = color;
+
+  // relative luminance, weighted for how bright each channel looks
+  0.2126 * red as f64 + 0.7152 * green as f64 + 0.0722 * blue as f64 > 127.5
+}
+
+/// Get the ANSI code to switch to `color`, or nothing to leave the current color.
+pub fn color(color: Option<Rgb>) -> String {
+  match color {
+    Some((red, green, blue)) => terminal::ansi_rgb(red, green, blue),
+    None => String::new(),
+  }
+}
+
+/// How the time remaining is made to stand out as the timer is about to finish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Emphasis {
+  #[default]
+  None,
+  Bold,
+  Blink,
+}
+
+impl std::str::FromStr for Emphasis {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "none" => Ok(Self::None),
+      "bold" => Ok(Self::Bold),
+      "blink" => Ok(Self::Blink),
+      _ => Err(format!("Invalid emphasis: {}", value)),
+    }
+  }
+}
+
+/// Get `text` styled with `emphasis`.
+pub fn emphasize(text: &str, emphasis: Emphasis) -> String {
+  match emphasis {
+    Emphasis::None => text.to_string(),
+    Emphasis::Bold => format!("{}{}{}", terminal::bold(), text, terminal::reset_style()),
+    Emphasis::Blink => format!("{}{}{}", terminal::blink(), text, terminal::reset_style()),
+  }
+}
+
+/// How the percentage readout is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PercentStyle {
+  /// Whole percent done, like `42%`.
+  #[default]
+  Whole,
+  /// Percent done to one decimal place, like `42.7%`.
+  Decimal,
+  /// Percent still to go, like `57% left`.
+  Left,
+  /// Time done out of the total, like `17/40 min`.
+  Fraction,
+}
+
+impl std::str::FromStr for PercentStyle {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "whole" => Ok(Self::Whole),
+      "decimal" => Ok(Self::Decimal),
+      "left" => Ok(Self::Left),
+      "fraction" => Ok(Self::Fraction),
+      _ => Err(format!("Invalid percent style: {}", value)),
+    }
+  }
+}
+
+/// Get the percentage readout for `progress` (0-1) of a timer lasting `total` seconds.
+pub fn percent(style: PercentStyle, progress: f64, total: f64) -> String {
+  match style {
+    PercentStyle::Whole => format!("{}%", (progress * 100.0).round()),
+    PercentStyle::Decimal => format!("{:.1}%", progress * 100.0),
+    PercentStyle::Left => format!("{}% left", ((1.0 - progress) * 100.0).round()),
+    // minutes are too coarse for short timers
+    PercentStyle::Fraction if total < 120.0 => format!("{}/{} s", (progress * total).floor(), total.round()),
+    PercentStyle::Fraction => format!("{}/{} min", (progress * total / 60.0).floor(), (total / 60.0).round()),
+  }
+}
+
+/// Get a `width` character wide progress bar, filled with a gradient up to `progress` (0-1).
+pub fn bar(progress: f64, width: u16, theme: &Theme) -> String {
+  let progress_width = (progress * width as f64).round() as u16;
+  let mut bar = String::new();
+
+  // solid progress bar
+  for i in 0..progress_width {
+    let t = i as f64 / width as f64;
+    let gradient = theme.gradient.map(|(from, to)| (lerp(from.0, to.0, t), lerp(from.1, to.1, t), lerp(from.2, to.2, t)));
+
+    write!(bar, "{}{}", color(gradient), BAR_FULL_CHAR).unwrap();
+  }
+
+  // empty progress bar
+  write!(
+    bar,
+    "{}{}{}",
+    color(theme.empty),
+    BAR_EMPTY_CHAR.to_string().repeat((width - progress_width) as usize),
+    terminal::reset_color()
+  )
+  .unwrap();
+
+  bar
+}
+
+/// Get the rows of a small pie drawn with braille characters, with the slice for the time remaining filled in.
+///
+/// The remaining slice starts at 12 o'clock and shrinks clockwise as `progress` (0-1) goes up.
+pub fn pie(progress: f64, theme: &Theme) -> Vec<String> {
+  let radius = PIE_DIAMETER as f64 / 2.0;
+  let mut rows = Vec::new();
+
+  for row in 0..PIE_DIAMETER / 4 {
+    let mut line = color(theme.accent);
+
+    for column in 0..PIE_DIAMETER / 2 {
+      let mut dots = 0;
+
+      for (bit, (x, y)) in BRAILLE_DOTS.iter().enumerate() {
+        // measure from the middle of each dot
+        let dx = (column * 2 + x) as f64 + 0.5 - radius;
+        let dy = (row * 4 + y) as f64 + 0.5 - radius;
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        // fraction of the way around the circle, clockwise from 12 o'clock
+        let angle = (dx.atan2(-dy) + 2.0 * PI) % (2.0 * PI) / (2.0 * PI);
+
+        let outline = distance <= radius && distance > radius - 1.0;
+        let remaining = distance <= radius && angle >= progress;
+
+        if outline || remaining {
+          dots |= 1 << bit;
+        }
+      }
+
+      line.push(char::from_u32(0x2800 + dots).unwrap());
+    }
+
+    line.push_str(&terminal::reset_color());
+    rows.push(line);
+  }
+
+  rows
+}
+
+/// Get the rows of an ASCII hourglass, with the sand in the top chamber running out as `progress` (0-1) goes up.
+///
+/// `tick` should count up steadily over time, it animates the grains falling through the neck.
+pub fn hourglass(progress: f64, tick: u128, theme: &Theme) -> Vec<String> {
+  let capacity = HOURGLASS_CHAMBER.iter().sum::<usize>();
+  let top_sand = ((1.0 - progress) * capacity as f64).round() as usize;
+  let bottom_sand = capacity - top_sand;
+
+  // sand settles from the narrowest row of the top chamber and the widest row of the bottom
+  let top = fill_chamber(HOURGLASS_CHAMBER.iter().rev().copied(), top_sand);
+  let mut bottom = fill_chamber(HOURGLASS_CHAMBER.iter().copied(), bottom_sand);
+
+  // grains falling through the neck
+  if top_sand > 0 && tick.is_multiple_of(2) {
+    bottom[HOURGLASS_CHAMBER.len() - 1] = ".".to_string();
+  }
+
+  let width = HOURGLASS_CHAMBER[0] + 2;
+  let sand = color(theme.sand);
+  let reset = terminal::reset_color();
+
+  let mut rows = vec![format!(" {} ", "_".repeat(width))];
+
+  for (i, row) in top.iter().rev().enumerate() {
+    rows.push(format!("{}\\{sand}{row}{reset}/{}", " ".repeat(i + 1), " ".repeat(i + 1)));
+  }
+
+  for (i, row) in bottom.iter().rev().enumerate() {
+    let padding = HOURGLASS_CHAMBER.len() - i;
+
+    rows.push(format!("{}/{sand}{row}{reset}\\{}", " ".repeat(padding), " ".repeat(padding)));
+  }
+
+  rows.push(format!(" {} ", "‾".repeat(width)));
+
+  rows
+}
+
+/// Fill rows of the given `widths` with `sand` grains in order, centering any partly filled row.
+///
+/// Returns the rows in the same order as `widths`.
+fn fill_chamber(widths: impl Iterator<Item = usize>, mut sand: usize) -> Vec<String> {
+  widths
+    .map(|width| {
+      let grains = sand.min(width);
+      sand -= grains;
+
+      let left = (width - grains) / 2;
+
+      format!("{}{}{}", " ".repeat(left), HOURGLASS_SAND.to_string().repeat(grains), " ".repeat(width - grains - left))
+    })
+    .collect()
+}
+
+/// Position (x, y) of each dot in a braille character, in the order of their bits.
+const BRAILLE_DOTS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+
+/// Draw the progress bar as a `columns` wide image with rounded, anti-aliased ends and a smooth gradient up to `progress` (0-1).
+pub fn bar_image(progress: f64, columns: u16, theme: &Theme) -> Image {
+  let mut image = Image::new(columns as u32 * IMAGE_CELL_WIDTH, IMAGE_CELL_HEIGHT);
+  draw_bar(&mut image, 0, IMAGE_CELL_HEIGHT, progress, theme);
+
+  image
+}
+
+/// Draw the time remaining in large digits above the progress bar, as an image `columns` wide and `rows` tall for cells of `cell` pixels.
+///
+/// The bar takes up the bottom row, the digits are scaled to fill the rest.
+pub fn digits_image(text: &str, progress: f64, columns: u16, rows: u16, cell: (u32, u32), theme: &Theme) -> Image {
+  let (cell_width, cell_height) = cell;
+  let mut image = Image::new(columns as u32 * cell_width, rows as u32 * cell_height);
+  let digits_height = (rows as u32 - 1) * cell_height;
+
+  // one column of spacing after every glyph, one row of spacing under them
+  let glyphs = text.chars().filter_map(digit_glyph).collect::<Vec<_>>();
+  let scale = (digits_height / (DIGIT_HEIGHT + 1))
+    .min(image.width / (glyphs.len() as u32 * (DIGIT_WIDTH + 1)).max(1))
+    .max(1);
+
+  let (red, green, blue) = theme.accent.unwrap_or((255, 255, 255));
+
+  for (i, glyph) in glyphs.iter().enumerate() {
+    let left = i as u32 * (DIGIT_WIDTH + 1) * scale;
+
+    for (row, bits) in glyph.iter().enumerate() {
+      for column in 0..DIGIT_WIDTH {
+        if bits & (1 << (DIGIT_WIDTH - 1 - column)) == 0 {
+          continue;
+        }
+
+        for y in row as u32 * scale..(row as u32 + 1) * scale {
+          for x in left + column * scale..left + (column + 1) * scale {
+            image.set(x, y, [red, green, blue, 255]);
+          }
+        }
+      }
+    }
+  }
+
+  draw_bar(&mut image, digits_height, cell_height, progress, theme);
+
+  image
+}
+
+/// Draw a bar across the whole width of `image`, on the `height` rows starting at `top`.
+fn draw_bar(image: &mut Image, top: u32, height: u32, progress: f64, theme: &Theme) {
+  let width = image.width;
+
+  // images need some color, so themes without one get plain white on grey
+  let (from, to) = theme.gradient.unwrap_or(((255, 255, 255), (255, 255, 255)));
+  let empty = theme.empty.unwrap_or((128, 128, 128));
+
+  // a pill half the height of the line, centered vertically
+  let radius = height as f64 / 4.0;
+  let middle = height as f64 / 2.0;
+  let fill = progress * width as f64;
+
+  for y in 0..height {
+    for x in 0..width {
+      let mut covered = 0;
+      let mut filled = 0;
+
+      for sample in 0..IMAGE_SAMPLES * IMAGE_SAMPLES {
+        let sample_x = x as f64 + ((sample % IMAGE_SAMPLES) as f64 + 0.5) / IMAGE_SAMPLES as f64;
+        let sample_y = y as f64 + ((sample / IMAGE_SAMPLES) as f64 + 0.5) / IMAGE_SAMPLES as f64;
+
+        // distance to the line running through the middle of the pill
+        let dx = sample_x - sample_x.clamp(radius, width as f64 - radius);
+        let dy = sample_y - middle;
+
+        if dx * dx + dy * dy <= radius * radius {
+          covered += 1;
+
+          if sample_x < fill {
+            filled += 1;
+          }
+        }
+      }
+
+      let t = x as f64 / width as f64;
+      let full = (lerp(from.0, to.0, t), lerp(from.1, to.1, t), lerp(from.2, to.2, t));
+      let mix = if covered == 0 { 0.0 } else { filled as f64 / covered as f64 };
+
+      image.set(
+        x,
+        top + y,
+        [
+          lerp(empty.0, full.0, mix),
+          lerp(empty.1, full.1, mix),
+          lerp(empty.2, full.2, mix),
+          lerp(0, 255, covered as f64 / (IMAGE_SAMPLES * IMAGE_SAMPLES) as f64),
+        ],
+      );
+    }
+  }
+}
+
+/// Get a `height` row tall column, two characters wide, filling upward with a gradient as `progress` (0-1) goes up.
+///
+/// The top of the fill is drawn with eighth blocks so it rises smoothly rather than a whole row at a time.
+pub fn column(progress: f64, height: u16, theme: &Theme) -> Vec<String> {
+  let fill = progress * height as f64;
+
+  (0..height)
+    .rev()
+    .map(|row| {
+      let t = row as f64 / height as f64;
+      let gradient = theme.gradient.map(|(from, to)| (lerp(from.0, to.0, t), lerp(from.1, to.1, t), lerp(from.2, to.2, t)));
+      let eighths = ((fill - row as f64).clamp(0.0, 1.0) * 8.0).round() as usize;
+
+      match eighths {
+        8 => format!("{}{}{}", color(gradient), BAR_FULL_CHAR.to_string().repeat(2), terminal::reset_color()),
+        0 => format!("{}{}{}", color(theme.empty), BAR_EMPTY_CHAR.to_string().repeat(2), terminal::reset_color()),
+        _ => format!("{}{}{}", color(gradient), COLUMN_EIGHTHS[eighths - 1].to_string().repeat(2), terminal::reset_color()),
+      }
+    })
+    .collect()
+}
+
+/// Draw `text` in the large digit font with half blocks, two rows of pixels to each line.
+pub fn big_digits(text: &str) -> Vec<String> {
+  let glyphs = text.chars().filter_map(digit_glyph).collect::<Vec<_>>();
+  let lit = |glyph: &[u8; DIGIT_HEIGHT as usize], row: u32, column: u32| row < DIGIT_HEIGHT && glyph[row as usize] & (1 << (DIGIT_WIDTH - 1 - column)) != 0;
+
+  (0..DIGIT_HEIGHT.div_ceil(2))
+    .map(|line| {
+      glyphs
+        .iter()
+        .map(|glyph| {
+          let mut output = (0..DIGIT_WIDTH)
+            .map(|column| match (lit(glyph, line * 2, column), lit(glyph, line * 2 + 1, column)) {
+              (true, true) => '█',
+              (true, false) => '▀',
+              (false, true) => '▄',
+              (false, false) => ' ',
+            })
+            .collect::<String>();
+
+          output.push(' ');
+          output
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+    })
+    .collect()
+}
+
+/// Partially filled blocks, from one eighth up to seven eighths.
+const COLUMN_EIGHTHS: [char; 7] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇'];
+
+/// Size of each glyph in the large digit font.
+const DIGIT_WIDTH: u32 = 5;
+const DIGIT_HEIGHT: u32 = 7;
+
+/// Get the rows of the large digit font for `c`, the leftmost column being the highest bit.
+fn digit_glyph(c: char) -> Option<[u8; DIGIT_HEIGHT as usize]> {
+  Some(match c {
+    '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+    ':' => [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000],
+    _ => return None,
+  })
+}
+
+pub fn lerp(a: u8, b: u8, t: f64) -> u8 {
+  ((1.0 - t) * (a as f64) + t * (b as f64)).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bar_width() {
+    assert_eq!(terminal::display_width(&bar(0.0, 20, &ThemeName::Default.theme(false))), 20);
+    assert_eq!(terminal::display_width(&bar(0.5, 20, &ThemeName::Default.theme(false))), 20);
+    assert_eq!(terminal::display_width(&bar(1.0, 20, &ThemeName::Mono.theme(false))), 20);
+  }
+
+  #[test]
+  fn bar_image_fills_from_the_left() {
+    let image = bar_image(0.5, 4, &ThemeName::Mono.theme(false));
+    let pixel = |x: u32, y: u32| &image.pixels[((y * image.width + x) * 4) as usize..][..4];
+
+    assert_eq!((image.width, image.height), (40, 20));
+    assert_eq!(pixel(10, 10), [255, 255, 255, 255]);
+    assert_eq!(pixel(30, 10), [128, 128, 128, 255]);
+    // transparent above and below the pill
+    assert_eq!(pixel(10, 0)[3], 0);
+  }
+
+  #[test]
+  fn digits_image_draws_glyphs() {
+    let image = digits_image("1:00", 0.0, 4, 2, (10, 20), &ThemeName::Mono.theme(false));
+
+    assert_eq!((image.width, image.height), (40, 40));
+    // the top of the 1 is lit, the gap beside it isn't
+    assert_eq!(image.pixels[(2 * 4) as usize + 3], 255);
+    assert_eq!(image.pixels[3], 0);
+  }
+
+  #[test]
+  fn light_backgrounds() {
+    assert!(is_light((255, 255, 255)));
+    assert!(is_light((253, 246, 227)));
+    assert!(!is_light((30, 30, 46)));
+    assert_ne!(ThemeName::Default.theme(true).empty, ThemeName::Default.theme(false).empty);
+  }
+
+  #[test]
+  fn column_fills_upward() {
+    let column = column(0.5, 4, &ThemeName::Mono.theme(false));
+
+    assert!(column[0].contains(BAR_EMPTY_CHAR) && column[1].contains(BAR_EMPTY_CHAR));
+    assert!(column[2].contains(BAR_FULL_CHAR) && column[3].contains(BAR_FULL_CHAR));
+  }
+
+  #[test]
+  fn big_digits_rows() {
+    let digits = big_digits("1:0");
+
+    assert_eq!(digits.len(), 4);
+    assert_eq!(digits[0], " ▄█    ▄▄   ▄▀▀▀▄");
+  }
+
+  #[test]
+  fn percent_styles() {
+    assert_eq!(percent(PercentStyle::Whole, 0.427, 2400.0), "43%");
+    assert_eq!(percent(PercentStyle::Decimal, 0.427, 2400.0), "42.7%");
+    assert_eq!(percent(PercentStyle::Left, 0.427, 2400.0), "57% left");
+    assert_eq!(percent(PercentStyle::Fraction, 0.427, 2400.0), "17/40 min");
+    assert_eq!(percent(PercentStyle::Fraction, 0.5, 90.0), "45/90 s");
+  }
+
+  #[test]
+  fn hourglass_sand_runs_out() {
+    let full = hourglass(0.0, 1, &ThemeName::Default.theme(false));
+    let empty = hourglass(1.0, 1, &ThemeName::Default.theme(false));
+
+    assert_eq!(full.len(), HOURGLASS_CHAMBER.len() * 2 + 2);
+    assert!(full.iter().all(|row| terminal::display_width(row) == HOURGLASS_CHAMBER[0] + 4));
+
+    // all the sand starts in the top chamber and ends up in the bottom
+    assert_eq!(full[1].matches(HOURGLASS_SAND).count(), HOURGLASS_CHAMBER[0]);
+    assert_eq!(empty[1].matches(HOURGLASS_SAND).count(), 0);
+    assert_eq!(empty[full.len() - 2].matches(HOURGLASS_SAND).count(), HOURGLASS_CHAMBER[0]);
+  }
+
+  #[test]
+  fn pie_fills_remaining() {
+    let full = pie(0.0, &ThemeName::Default.theme(false));
+    let empty = pie(1.0, &ThemeName::Default.theme(false));
+
+    assert_eq!(full.len(), PIE_DIAMETER / 4);
+    assert!(full.iter().all(|row| terminal::display_width(row) == PIE_DIAMETER / 2));
+
+    // a finished pie is only an outline, so the middle of it is blank
+    assert!(full[1].contains('⣿'));
+    assert!(!empty[1].contains('⣿'));
+  }
+}
@@ -0,0 +1,1244 @@
+use std::{
+  fs,
+  io::{stdin, stdout, BufRead, Write},
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender},
+  },
+  thread::{self, JoinHandle},
+  time::{Duration, Instant},
+};
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  config::{self, Config},
+  history, hook, http,
+  json::{self, Value},
+  lock, notify, parse_duration, signals, sound, state,
+  timer::{Stage, Timer},
+  toggl, try_parse_duration,
+  webhook::{self, Event},
+};
+
+/// Version of the protocol spoken over the socket, raised whenever a change would break existing clients.
+pub const PROTOCOL: u32 = 1;
+
+/// How often timers are checked for moving on between requests.
+const TICK: Duration = Duration::from_millis(100);
+/// How often subscribers are sent the time remaining.
+const TICK_EVERY: Duration = Duration::from_secs(1);
+/// How many messages a client may fall behind on reading before it's let go.
+const BACKLOG: usize = 256;
+
+/// How long timers that ended are still listed when the config doesn't say.
+const KEEP_ENDED: Duration = Duration::from_secs(60 * 60);
+/// How often the history is pruned, as the config asks, while the daemon runs.
+const PRUNE_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Notifications clients can subscribe to.
+const EVENTS: [&str; 8] = ["tick", "stage", "finished", "pause", "resume", "cancelled", "started", "extended"];
+
+// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// An error to send back in place of a result, with its JSON-RPC code.
+type Error = (i32, String);
+
+/// A notification to send subscribers of the event it's named after.
+type Notification = (&'static str, String);
+
+/// What a client has subscribed to.
+pub struct Client {
+  events: Vec<String>,
+}
+
+impl Client {
+  /// Get a client subscribed to `events`.
+  fn subscribed(events: &[&str]) -> Self {
+    Self {
+      events: events.iter().map(ToString::to_string).collect(),
+    }
+  }
+
+  fn wants(&self, event: &str) -> bool {
+    self.events.iter().any(|subscribed| subscribed == event)
+  }
+}
+
+/// A timer started by a client.
+struct Served {
+  id: u64,
+  label: String,
+  timer: Timer,
+  /// Stage the timer was on when last checked, to spot it moving on.
+  stage_index: usize,
+  /// When the timer was started, in seconds since the Unix epoch.
+  started_at: f64,
+  /// Name of the profile the timer was started from, if any.
+  profile: Option<String>,
+  tags: Vec<String>,
+  origin: Origin,
+}
+
+/// Where a timer was started from, so its hook runs there rather than wherever the daemon happens to be.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Origin {
+  /// Shell command run as the timer starts, pauses, finishes, and so on, in place of `exec` from the config.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  exec: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  dir: Option<PathBuf>,
+  /// Environment variables passed on to the hook.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  env: Vec<(String, String)>,
+}
+
+/// Timers kept for the next daemon, should this one stop without finishing them.
+#[derive(Serialize, Deserialize)]
+struct Saved {
+  timers: Vec<SavedTimer>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedTimer {
+  label: String,
+  stages: Vec<SavedStage>,
+  /// Seconds counted down when saved.
+  elapsed: f64,
+  paused: bool,
+  /// When the timer was started, in seconds since the Unix epoch.
+  started_at: f64,
+  /// When the timer was saved, in seconds since the Unix epoch, from which its deadline follows.
+  saved_at: f64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  profile: Option<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  tags: Vec<String>,
+  #[serde(default)]
+  origin: Origin,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStage {
+  name: String,
+  /// Length in seconds.
+  duration: f64,
+}
+
+/// Timers run on behalf of clients, like editor plugins, and controlled with JSON-RPC requests.
+pub struct Server {
+  config: Config,
+  timers: Vec<Served>,
+  next_id: u64,
+  /// Notify and play the sound as timers finish, for those started in the background with nobody watching.
+  alert: bool,
+  /// Notifications about timers controlled by a client, for the other clients to hear on the next tick.
+  pending: Vec<Notification>,
+  /// Where the timers are kept for the next daemon, for the one running in the background.
+  saved: Option<PathBuf>,
+  /// Timers that finished or were cancelled lately, still shown by `timr list`.
+  ended: Vec<state::EndedState>,
+  /// How long ended timers are kept before they're let go.
+  keep_ended: Duration,
+  /// Whether timers are added to the history as they finish or are cancelled.
+  history: bool,
+  /// When the history was last pruned.
+  pruned_at: Option<Instant>,
+}
+
+impl Server {
+  pub fn new(config: Config, alert: bool) -> Self {
+    Self {
+      keep_ended: config.keep_ended.as_deref().map_or(KEEP_ENDED, parse_duration),
+      config,
+      timers: Vec::new(),
+      next_id: 1,
+      alert,
+      pending: Vec::new(),
+      saved: None,
+      ended: Vec::new(),
+      history: true,
+      pruned_at: None,
+    }
+  }
+
+  /// Pick back up the timers a daemon before this one kept at `path`, going on to keep them there too.
+  ///
+  /// Timers that should have finished while no daemon was running finish on the next tick, so they're still alerted.
+  pub fn recover(&mut self, path: PathBuf) {
+    let saved = fs::read_to_string(&path).ok().and_then(|contents| toml::from_str::<Saved>(&contents).ok());
+
+    for saved in saved.map(|saved| saved.timers).unwrap_or_default() {
+      let stages = saved
+        .stages
+        .into_iter()
+        .map(|stage| Stage {
+          name: stage.name,
+          duration: Duration::from_secs_f64(stage.duration.max(0.0)),
+        })
+        .collect();
+
+      // the time passed since counts too, unless it was paused
+      let elapsed = if saved.paused { saved.elapsed } else { saved.elapsed + (state::now() - saved.saved_at) };
+      let timer = Timer::resumed(stages, Duration::from_secs_f64(elapsed.max(0.0)), saved.paused);
+
+      self.timers.push(Served {
+        id: self.next_id,
+        label: saved.label,
+        stage_index: timer.stage_index(),
+        timer,
+        started_at: saved.started_at,
+        profile: saved.profile,
+        tags: saved.tags,
+        origin: saved.origin,
+      });
+
+      self.next_id += 1;
+    }
+
+    self.saved = Some(path);
+  }
+
+  /// Handle a line holding a JSON-RPC request, getting the response to send back, or nothing for a notification.
+  pub fn handle(&mut self, line: &str, client: &mut Client) -> Option<String> {
+    let request = match json::parse(line) {
+      Ok(request) => request,
+      Err(error) => return Some(response(Value::Null, Err((PARSE_ERROR, error)))),
+    };
+
+    let id = request.get("id").cloned();
+
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+      return Some(response(id.unwrap_or(Value::Null), Err((INVALID_REQUEST, "Missing method".to_string()))));
+    };
+
+    let params = request.get("params").cloned().unwrap_or(Value::Object(Vec::new()));
+    let result = match method {
+      "subscribe" | "unsubscribe" => subscribe(client, method == "subscribe", &params),
+      _ => self.call(method, &params),
+    };
+
+    // notifications don't get a response, even when they fail
+    id.map(|id| response(id, result))
+  }
+
+  fn call(&mut self, method: &str, params: &Value) -> Result<Value, Error> {
+    match method {
+      "version" => Ok(Value::object([
+        ("protocol", (PROTOCOL as f64).into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+        ("pid", (std::process::id() as f64).into()),
+      ])),
+      "start" => self.start(params),
+      "pause" | "resume" => {
+        let served = Self::find(&mut self.timers, params)?;
+
+        if served.timer.is_paused() != (method == "pause") && !served.timer.is_finished() {
+          served.timer.toggle_pause();
+          hook(&self.config, self.alert, served, if method == "pause" { Event::Pause } else { Event::Resume });
+
+          let described = describe(served);
+          let event = if method == "pause" { "pause" } else { "resume" };
+          self.pending.push((event, notification(event, described.clone())));
+
+          return Ok(described);
+        }
+
+        Ok(describe(served))
+      }
+      "extend" => {
+        let by = params.get("by").and_then(Value::as_str).ok_or((INVALID_PARAMS, "Missing by".to_string()))?;
+        let by = try_parse_duration(by).map_err(|error| (INVALID_PARAMS, error))?;
+        let served = Self::find(&mut self.timers, params)?;
+
+        served.timer.extend(by);
+        hook(&self.config, self.alert, served, Event::Extend);
+
+        let described = describe(served);
+        self.pending.push(("extended", notification("extended", described.clone())));
+
+        Ok(described)
+      }
+      "cancel" => {
+        let id = Self::find(&mut self.timers, params)?.id;
+        let index = self.timers.iter().position(|served| served.id == id).unwrap();
+        let served = self.timers.remove(index);
+        let described = describe(&served);
+
+        hook(&self.config, self.alert, &served, Event::Cancel);
+        if self.history {
+          let mut session = session(&served, true);
+          session.reason = params.get("reason").and_then(Value::as_str).map(str::to_string);
+          history::record(self.config.history_backend(), session);
+        }
+
+        self.ended.push(ended(&served, state::Outcome::Cancelled));
+        self.pending.push(("cancelled", notification("cancelled", described.clone())));
+
+        Ok(described)
+      }
+      "query" if params.get("id").is_some() || params.get("label").is_some() => Ok(describe(Self::find(&mut self.timers, params)?)),
+      "query" => Ok(Value::Array(self.timers.iter().map(describe).collect())),
+      _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    }
+  }
+
+  /// Start a timer for the `duration` or `profile` in `params`, labelled with `label` and tagged with `tags` if given.
+  ///
+  /// Its hook is `exec` if given, run in `cwd` with the variables in `env`, which is where the client started it from.
+  fn start(&mut self, params: &Value) -> Result<Value, Error> {
+    let tags = match params.get("tags") {
+      Some(Value::Array(tags)) => tags.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+      _ => Vec::new(),
+    };
+
+    let (stages, name, tags) = if let Some(name) = params.get("profile").and_then(Value::as_str) {
+      let profile = self.config.find_profile(name).ok_or((INVALID_PARAMS, format!("No profile found matching {}", name)))?;
+
+      let stages = profile.try_stages().map_err(|error| (INVALID_PARAMS, error))?;
+
+      (stages, name, profile.tagged(&tags))
+    } else if let Some(duration) = params.get("duration").and_then(Value::as_str) {
+      let stage = Stage {
+        name: String::new(),
+        duration: try_parse_duration(duration).map_err(|error| (INVALID_PARAMS, error))?,
+      };
+
+      (vec![stage], duration, tags)
+    } else {
+      return Err((INVALID_PARAMS, "Missing duration or profile".to_string()));
+    };
+
+    // timers are told apart by name, so a name can only be used once, and those without get one of their own
+    let label = match params.get("label").and_then(Value::as_str) {
+      Some(label) if self.timers.iter().any(|served| served.label == label) => {
+        return Err((INVALID_PARAMS, format!("A timer named {} is already running", label)));
+      }
+      Some(label) => label.to_string(),
+      None => (1..)
+        .map(|number| if number == 1 { name.to_string() } else { format!("{}-{}", name, number) })
+        .find(|label| !self.timers.iter().any(|served| &served.label == label))
+        .unwrap(),
+    };
+
+    let env = match params.get("env") {
+      Some(Value::Object(env)) => env.iter().filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string()))).collect(),
+      _ => Vec::new(),
+    };
+
+    let origin = Origin {
+      exec: params.get("exec").and_then(Value::as_str).map(str::to_string),
+      dir: params.get("cwd").and_then(Value::as_str).map(PathBuf::from),
+      env,
+    };
+
+    let timer = Timer::new(stages);
+
+    self.timers.push(Served {
+      id: self.next_id,
+      label,
+      stage_index: timer.stage_index(),
+      timer,
+      started_at: state::now(),
+      profile: params.get("profile").and_then(Value::as_str).map(str::to_string),
+      tags,
+      origin,
+    });
+
+    self.next_id += 1;
+
+    let served = self.timers.last().unwrap();
+    hook(&self.config, self.alert, served, Event::Start);
+
+    let described = describe(served);
+    self.pending.push(("started", notification("started", described.clone())));
+
+    Ok(described)
+  }
+
+  /// Find the timer among `timers` with the `id` in `params`, or else the one with its `label`, which can also be an id when no timer goes by it.
+  fn find<'a>(timers: &'a mut [Served], params: &Value) -> Result<&'a mut Served, Error> {
+    if let Some(label) = params.get("label").and_then(Value::as_str).filter(|_| params.get("id").is_none()) {
+      let id = label.parse::<u64>().ok();
+
+      return timers
+        .iter()
+        .position(|served| served.label == label)
+        .or_else(|| timers.iter().position(|served| Some(served.id) == id))
+        .map(|index| &mut timers[index])
+        .ok_or((INVALID_PARAMS, format!("No timer named {}", label)));
+    }
+
+    let id = params.get("id").and_then(Value::as_f64).ok_or((INVALID_PARAMS, "Missing id or label".to_string()))?;
+
+    timers
+      .iter_mut()
+      .find(|served| served.id as f64 == id)
+      .ok_or((INVALID_PARAMS, format!("No timer with id {}", id)))
+  }
+
+  /// Check on the timers, getting notifications for those that moved on to their next stage, finished, or were controlled by other commands.
+  ///
+  /// Finished timers are added to the history and let go.
+  pub fn tick(&mut self) -> Vec<Notification> {
+    let mut notifications = std::mem::take(&mut self.pending);
+
+    // sent by other timr commands, like those in the menu bar
+    match state::take_control() {
+      Some((state::Control::Cancel, reason)) => {
+        for served in std::mem::take(&mut self.timers) {
+          hook(&self.config, self.alert, &served, Event::Cancel);
+          if self.history {
+            let mut session = session(&served, true);
+            session.reason = reason.clone();
+            history::record(self.config.history_backend(), session);
+          }
+          self.ended.push(ended(&served, state::Outcome::Cancelled));
+          notifications.push(("cancelled", notification("cancelled", describe(&served))));
+        }
+      }
+      Some((control, _)) => {
+        let pause = control == state::Control::Pause;
+
+        for served in self.timers.iter_mut().filter(|served| served.timer.is_paused() != pause && !served.timer.is_finished()) {
+          served.timer.toggle_pause();
+          hook(&self.config, self.alert, served, if pause { Event::Pause } else { Event::Resume });
+          notifications.push((control.name(), notification(control.name(), describe(served))));
+        }
+      }
+      None => {}
+    }
+
+    for served in &mut self.timers {
+      if served.timer.is_finished() {
+        notifications.push(("finished", notification("finished", describe(served))));
+        hook(&self.config, self.alert, served, Event::Finish);
+
+        if self.alert {
+          alert(&self.config, &served.label);
+        }
+
+        if let Some(toggl) = &self.config.toggl {
+          toggl::push(toggl, &session(served, false));
+        }
+
+        if self.history {
+          history::record(self.config.history_backend(), session(served, false));
+        }
+      } else if served.timer.stage_index() != served.stage_index {
+        served.stage_index = served.timer.stage_index();
+        notifications.push(("stage", notification("stage", describe(served))));
+        hook(&self.config, self.alert, served, Event::Stage);
+      }
+    }
+
+    let finished = self.timers.iter().filter(|served| served.timer.is_finished());
+    self.ended.extend(finished.map(|served| ended(served, state::Outcome::Finished)));
+
+    self.timers.retain(|served| !served.timer.is_finished());
+
+    if self.history && self.pruned_at.is_none_or(|pruned| pruned.elapsed() >= PRUNE_EVERY) {
+      self.pruned_at = Some(Instant::now());
+      self.prune();
+    }
+
+    // let go once they've been listed long enough, which doesn't make for a notification of its own
+    let ended = self.ended.len();
+    self.ended.retain(|timer| state::now() - timer.ended_at < self.keep_ended.as_secs_f64());
+
+    if self.ended.len() != ended && notifications.is_empty() {
+      self.publish();
+    }
+
+    notifications
+  }
+
+  /// Prune the history as the config asks, if it does.
+  fn prune(&self) {
+    let Some(limits) = &self.config.history else {
+      return;
+    };
+
+    let retention = match limits.retention.as_deref().map(try_parse_duration).transpose() {
+      Ok(retention) => retention,
+      Err(error) => {
+        eprintln!("Invalid history retention: {}", error);
+        return;
+      }
+    };
+
+    if retention.is_none() && limits.max_entries.is_none() {
+      return;
+    }
+
+    if let Err(error) = history::prune(self.config.history_backend(), retention, limits.max_entries) {
+      eprintln!("{}", error);
+    }
+  }
+
+  /// Get the notification telling subscribers the time remaining on every timer, unless there are none.
+  fn ticking(&self) -> Option<Notification> {
+    if self.timers.is_empty() {
+      return None;
+    }
+
+    let timers = Value::Array(self.timers.iter().map(describe).collect());
+
+    Some(("tick", notification("tick", Value::object([("timers", timers)]))))
+  }
+
+  /// Publish a snapshot of the timers for other commands, like `timr tmux-status`, to read, and keep them for the next daemon.
+  pub fn publish(&self) {
+    if let Some(path) = &self.saved {
+      self.save(path);
+    }
+
+    if self.timers.is_empty() && self.ended.is_empty() {
+      state::remove();
+      return;
+    }
+
+    // said once rather than every tick, and again only if it starts failing after working
+    static FAILING: AtomicBool = AtomicBool::new(false);
+
+    let result = state::save(
+      self
+        .timers
+        .iter()
+        .map(|served| state::TimerState {
+          id: Some(served.id),
+          label: served.label.clone(),
+          stage: stage_name(&served.timer).to_string(),
+          duration: served.timer.duration().as_secs_f64(),
+          remaining: served.timer.remaining().as_secs_f64(),
+          paused: served.timer.is_paused(),
+          started_at: served.started_at,
+          saved_at: state::now(),
+        })
+        .collect(),
+      self.ended.clone(),
+      &[],
+    );
+
+    match result {
+      Ok(()) => FAILING.store(false, Ordering::Relaxed),
+      Err(error) if !FAILING.swap(true, Ordering::Relaxed) => eprintln!("Failed to publish the timers for other commands: {}", error),
+      Err(_) => {}
+    }
+  }
+
+  /// Keep the timers at `path` for the next daemon, should this one stop without finishing them.
+  fn save(&self, path: &Path) {
+    if self.timers.is_empty() {
+      let _ = fs::remove_file(path);
+      return;
+    }
+
+    let saved = Saved {
+      timers: self
+        .timers
+        .iter()
+        .map(|served| SavedTimer {
+          label: served.label.clone(),
+          stages: served
+            .timer
+            .stages()
+            .iter()
+            .map(|stage| SavedStage {
+              name: stage.name.clone(),
+              duration: stage.duration.as_secs_f64(),
+            })
+            .collect(),
+          elapsed: served.timer.elapsed().as_secs_f64(),
+          paused: served.timer.is_paused(),
+          started_at: served.started_at,
+          saved_at: state::now(),
+          profile: served.profile.clone(),
+          tags: served.tags.clone(),
+          origin: served.origin.clone(),
+        })
+        .collect(),
+    };
+
+    // written to the side and moved into place, so a crash partway through can't lose the last ones
+    let temporary = path.with_extension("tmp");
+
+    if let Err(error) = fs::write(&temporary, toml::to_string(&saved).expect("Failed to serialize timers")).and_then(|_| fs::rename(&temporary, path)) {
+      eprintln!("Failed to save timers: {}", error);
+    }
+  }
+}
+
+/// Notify that the timer `label` has finished and play the sound from `config`.
+fn alert(config: &Config, label: &str) {
+  let template = notify::Template {
+    title: label.to_string(),
+    urgency: config.urgency.unwrap_or_default(),
+    ..Default::default()
+  };
+
+  notify::send(&template, &format!("{} finished", label));
+
+  if let Some(sound) = config.finish_sound() {
+    sound::play(&sound, config.volume.unwrap_or(sound::DEFAULT_VOLUME));
+  }
+}
+
+/// Run the hook for `event` on `served` where it was started from, its own or else `exec` from `config` for timers nobody's watching when `alert`.
+fn hook(config: &Config, alert: bool, served: &Served, event: Event) {
+  let Some(command) = served.origin.exec.as_ref().or(config.exec.as_ref().filter(|_| alert)) else {
+    return;
+  };
+
+  if !config
+    .on
+    .as_ref()
+    .and_then(|routes| routes.get(event))
+    .is_none_or(|alerts| alerts.contains(&config::Alert::Exec))
+  {
+    return;
+  }
+
+  let payload = webhook::Payload {
+    event,
+    label: &served.label,
+    stage: stage_name(&served.timer),
+    duration: served.timer.duration().as_secs_f64(),
+    remaining: served.timer.remaining().as_secs_f64(),
+    started_at: Local.timestamp_millis_opt((served.started_at * 1000.0) as i64).single().unwrap_or_else(Local::now),
+    timestamp: Local::now(),
+  };
+
+  hook::run_in(command, &payload, served.origin.dir.as_deref(), &served.origin.env);
+}
+
+/// Subscribe `client` to the `events` in `params`, or unsubscribe them, taking every event when none are given.
+fn subscribe(client: &mut Client, subscribe: bool, params: &Value) -> Result<Value, Error> {
+  let events = match params.get("events") {
+    Some(Value::Array(events)) => events
+      .iter()
+      .map(|event| match event.as_str() {
+        Some(event) if EVENTS.contains(&event) => Ok(event),
+        _ => Err((INVALID_PARAMS, format!("Unknown event: {}", event))),
+      })
+      .collect::<Result<Vec<_>, _>>()?,
+    Some(_) => return Err((INVALID_PARAMS, "Events must be an array".to_string())),
+    None => EVENTS.to_vec(),
+  };
+
+  if subscribe {
+    client
+      .events
+      .extend(events.iter().filter(|event| !client.wants(event)).map(ToString::to_string).collect::<Vec<_>>());
+  } else {
+    client.events.retain(|subscribed| !events.contains(&subscribed.as_str()));
+  }
+
+  Ok(Value::Array(client.events.iter().map(|event| event.as_str().into()).collect()))
+}
+
+/// Get the name of the stage `timer` is on, empty for a single duration.
+fn stage_name(timer: &Timer) -> &str {
+  if timer.stages().len() > 1 {
+    &timer.stage().name
+  } else {
+    ""
+  }
+}
+
+/// Get `served` as sent to clients.
+fn describe(served: &Served) -> Value {
+  Value::object([
+    ("id", (served.id as f64).into()),
+    ("label", served.label.as_str().into()),
+    ("stage", stage_name(&served.timer).into()),
+    ("duration", served.timer.duration().as_secs_f64().into()),
+    ("remaining", served.timer.remaining().as_secs_f64().into()),
+    ("paused", served.timer.is_paused().into()),
+    ("finished", served.timer.is_finished().into()),
+    ("started_at", served.started_at.into()),
+    ("tags", Value::Array(served.tags.iter().map(|tag| tag.as_str().into()).collect())),
+    (
+      "stages",
+      Value::Array(
+        served
+          .timer
+          .stages()
+          .iter()
+          .map(|stage| Value::object([("name", stage.name.as_str().into()), ("duration", stage.duration.as_secs_f64().into())]))
+          .collect(),
+      ),
+    ),
+  ])
+}
+
+/// Get `served` as a timer that has ended with `outcome`.
+fn ended(served: &Served, outcome: state::Outcome) -> state::EndedState {
+  state::EndedState {
+    id: served.id,
+    label: served.label.clone(),
+    outcome,
+    ended_at: state::now(),
+  }
+}
+
+/// Get `served` as kept in the history, having just finished or been `cancelled`.
+fn session(served: &Served, cancelled: bool) -> history::Session {
+  let timer = &served.timer;
+
+  history::Session {
+    label: served.label.clone(),
+    duration: if cancelled { timer.elapsed() } else { timer.duration() }.as_secs_f64(),
+    started_at: served.started_at,
+    finished_at: state::now(),
+    cancelled,
+    profile: served.profile.clone(),
+    tags: served.tags.clone(),
+    note: None,
+    reason: None,
+  }
+}
+
+/// Write the response to the request `id`.
+fn response(id: Value, result: Result<Value, Error>) -> String {
+  let outcome = match result {
+    Ok(result) => ("result", result),
+    Err((code, message)) => ("error", Value::object([("code", (code as f64).into()), ("message", message.as_str().into())])),
+  };
+
+  Value::object([("jsonrpc", "2.0".into()), ("id", id), outcome]).to_string()
+}
+
+/// Write a notification calling `method` on the client with the timer in `params`.
+fn notification(method: &str, params: Value) -> String {
+  Value::object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)]).to_string()
+}
+
+/// Something heard from a client.
+enum Incoming {
+  /// A client connected, along with where to send it responses and notifications.
+  Opened(usize, Box<dyn Write + Send>, Client),
+  Line(usize, String),
+  Closed(usize),
+  /// A method called over HTTP, along with where to send its result.
+  Call(String, Value, Sender<Result<Value, Error>>),
+}
+
+/// A client that's connected.
+struct Connected {
+  id: usize,
+  /// Messages waiting to be written to the client, by `writing`.
+  writer: SyncSender<String>,
+  writing: JoinHandle<()>,
+  client: Client,
+}
+
+impl Connected {
+  /// Connect the client `id`, writing what's sent to it to `writer` on the side.
+  fn new(id: usize, mut writer: Box<dyn Write + Send>, client: Client) -> Self {
+    let (message_tx, messages) = sync_channel::<String>(BACKLOG);
+
+    // written on the side so a client that stops reading can't hold up the timers for everyone else
+    let writing = thread::spawn(move || {
+      for message in messages {
+        if writeln!(writer, "{}", message).and_then(|_| writer.flush()).is_err() {
+          break;
+        }
+      }
+    });
+
+    Self {
+      id,
+      writer: message_tx,
+      writing,
+      client,
+    }
+  }
+
+  /// Send `message` to the client as a line of its own, returning whether it's still there and keeping up.
+  fn send(&self, message: &str) -> bool {
+    self.writer.try_send(message.to_string()).is_ok()
+  }
+}
+
+/// Pass each line read from `reader` along to `incoming` as coming from the client `id`, until it closes.
+fn read_lines(id: usize, reader: impl BufRead, incoming: &Sender<Incoming>) {
+  for line in reader.lines() {
+    let Ok(line) = line else {
+      break;
+    };
+
+    if incoming.send(Incoming::Line(id, line)).is_err() {
+      return;
+    }
+  }
+
+  let _ = incoming.send(Incoming::Closed(id));
+}
+
+/// Run timers for the clients heard from on `incoming`, stopping once every client has gone if `until_closed` and alerting as they finish if `alert`.
+///
+/// Timers are kept at `saved`, if given, and picked back up from there to begin with.
+fn run(incoming: Receiver<Incoming>, until_closed: bool, alert: bool, saved: Option<PathBuf>) {
+  let mut server = Server::new(config::load(), alert);
+
+  if let Some(saved) = saved {
+    server.recover(saved);
+    server.publish();
+  }
+
+  // caught so signals meant for timers in a terminal, like `pkill -USR1 timr`, don't stop the daemon
+  signals::listen();
+  let mut clients: Vec<Connected> = Vec::new();
+  // writers of clients that have gone, still finishing what was sent to them
+  let mut closing: Vec<JoinHandle<()>> = Vec::new();
+  let mut last_tick = Instant::now();
+
+  loop {
+    match incoming.recv_timeout(TICK) {
+      Ok(Incoming::Opened(id, writer, client)) => clients.push(Connected::new(id, writer, client)),
+      Ok(Incoming::Line(_, line)) if line.trim().is_empty() => {}
+      Ok(Incoming::Line(id, line)) => {
+        let Some(connected) = clients.iter_mut().find(|connected| connected.id == id) else {
+          continue;
+        };
+
+        let response = server.handle(&line, &mut connected.client);
+
+        // published first, so commands reading the state files after hearing back see the change
+        server.publish();
+
+        if response.is_some_and(|response| !connected.send(&response)) {
+          clients.retain(|connected| connected.id != id);
+        }
+      }
+      Ok(Incoming::Closed(id)) => {
+        if let Some(index) = clients.iter().position(|connected| connected.id == id) {
+          closing.push(clients.remove(index).writing);
+        }
+
+        closing.retain(|writing| !writing.is_finished());
+
+        if until_closed && clients.is_empty() {
+          break;
+        }
+      }
+      Ok(Incoming::Call(method, params, reply)) => {
+        let result = server.call(&method, &params);
+        server.publish();
+        let _ = reply.send(result);
+      }
+      Err(RecvTimeoutError::Timeout) => {}
+      Err(RecvTimeoutError::Disconnected) => break,
+    }
+
+    let mut notifications = server.tick();
+
+    if !notifications.is_empty() {
+      server.publish();
+    }
+
+    if last_tick.elapsed() >= TICK_EVERY {
+      last_tick = Instant::now();
+      notifications.extend(server.ticking());
+    }
+
+    for (event, message) in notifications {
+      clients.retain(|connected| !connected.client.wants(event) || connected.send(&message));
+    }
+  }
+
+  // the last responses written before stopping, like those to stdout
+  closing.extend(clients.into_iter().map(|connected| connected.writing));
+
+  for writing in closing {
+    let _ = writing.join();
+  }
+
+  state::remove();
+}
+
+/// Serve timers over stdin and stdout, a request or notification per line, until stdin closes.
+///
+/// Every notification but the ticks is sent without subscribing.
+pub fn stdio() {
+  let (incoming_tx, incoming) = channel();
+  let client = Client::subscribed(&EVENTS[1..]);
+
+  incoming_tx.send(Incoming::Opened(0, Box::new(stdout()), client)).unwrap();
+
+  // reading blocks, so it's done on the side to keep checking on the timers
+  thread::spawn(move || read_lines(0, stdin().lock(), &incoming_tx));
+
+  run(incoming, true, false, None);
+}
+
+/// Serve the HTTP API on `address` alongside the socket or pipe, passing its calls along to `incoming`.
+fn serve_http(address: &str, incoming: Sender<Incoming>) {
+  use std::process::exit;
+
+  let configured = config::load().http_token;
+  let token = match configured.clone() {
+    Some(token) => token,
+    None => http::token().unwrap_or_else(|error| {
+      eprintln!("Failed to create a token for the HTTP API: {}", error);
+      exit(1);
+    }),
+  };
+
+  let call = move |method: &str, params: Value| {
+    let (reply_tx, reply) = channel();
+
+    incoming
+      .send(Incoming::Call(method.to_string(), params, reply_tx))
+      .map_err(|_| "The daemon is stopping".to_string())?;
+
+    match reply.recv() {
+      Ok(result) => result.map_err(|(_, message)| message),
+      Err(_) => Err("The daemon is stopping".to_string()),
+    }
+  };
+
+  if let Err(error) = http::listen(address, token, call) {
+    eprintln!("Failed to serve the HTTP API on {}: {}", address, error);
+    exit(1);
+  }
+
+  if configured.is_some() {
+    eprintln!("Serving the HTTP API on {}", address);
+  } else {
+    eprintln!("Serving the HTTP API on {}, for clients with the token in {}", address, http::token_path().display());
+  }
+}
+
+/// Get the socket `timr serve` listens on when not told otherwise.
+#[cfg(unix)]
+pub fn socket_path() -> std::path::PathBuf {
+  crate::data_dir().join("serve.sock")
+}
+
+/// Serve timers on a Unix socket at `path` for any number of clients, each sending a request per line, replacing any daemon already serving there if `takeover`.
+///
+/// Clients only get notifications once they subscribe to them. The HTTP API is served on `http` too, if given.
+#[cfg(unix)]
+pub fn socket(path: std::path::PathBuf, takeover: bool, http: Option<String>) {
+  use std::{fs, io::BufReader, os::unix::net::UnixListener, process::exit};
+
+  let lock = lock::path_for(&path);
+  let _lock = lock::acquire(&lock, takeover);
+
+  // left behind by a server that didn't get to clean up, as the lock says no other is running
+  if path.exists() {
+    let _ = fs::remove_file(&path);
+  }
+
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).expect("Failed to create socket directory");
+  }
+
+  let listener = match UnixListener::bind(&path) {
+    Ok(listener) => listener,
+    Err(error) => {
+      eprintln!("Failed to listen on {}: {}", path.display(), error);
+      exit(1);
+    }
+  };
+
+  let socket = path.clone();
+  ctrlc::set_handler(move || {
+    let _ = fs::remove_file(&socket);
+    let _ = fs::remove_file(&lock);
+    state::remove();
+    exit(0);
+  })
+  .expect("Error setting Ctrl-C handler");
+
+  let (incoming_tx, incoming) = channel();
+
+  if let Some(address) = http {
+    serve_http(&address, incoming_tx.clone());
+  }
+
+  thread::spawn(move || {
+    for (id, stream) in listener.incoming().flatten().enumerate() {
+      let Ok(writer) = stream.try_clone() else {
+        continue;
+      };
+
+      if incoming_tx.send(Incoming::Opened(id, Box::new(writer), Client::subscribed(&[]))).is_err() {
+        break;
+      }
+
+      let incoming_tx = incoming_tx.clone();
+      thread::spawn(move || read_lines(id, BufReader::new(stream), &incoming_tx));
+    }
+  });
+
+  run(incoming, false, true, Some(path.with_extension("toml")));
+}
+
+/// Get the named pipe `timr serve` listens on.
+#[cfg(windows)]
+pub const PIPE: &str = r"\\.\pipe\timr";
+
+/// Serve timers on the named pipe for any number of clients, each sending a request per line, like `socket` does elsewhere.
+#[cfg(windows)]
+pub fn pipe(takeover: bool, http: Option<String>) {
+  use std::{
+    fs::File,
+    io::BufReader,
+    os::windows::io::{AsRawHandle, FromRawHandle, RawHandle},
+    process::exit,
+  };
+
+  use windows_sys::Win32::{
+    Foundation::{GetLastError, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+    System::Pipes::{ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT},
+  };
+
+  /// Create an instance of the pipe for the next client, the first making sure no other server has it.
+  fn create(name: &[u16], first: bool) -> Option<File> {
+    let flags = if first {
+      PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+      PIPE_ACCESS_DUPLEX
+    };
+    let handle = unsafe {
+      CreateNamedPipeW(
+        name.as_ptr(),
+        flags,
+        PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+        PIPE_UNLIMITED_INSTANCES,
+        4096,
+        4096,
+        0,
+        std::ptr::null(),
+      )
+    };
+
+    (handle != INVALID_HANDLE_VALUE).then(|| unsafe { File::from_raw_handle(handle as RawHandle) })
+  }
+
+  let lock = lock::path_for(&socket_path());
+  let _lock = lock::acquire(&lock, takeover);
+
+  ctrlc::set_handler(move || {
+    let _ = std::fs::remove_file(&lock);
+    state::remove();
+    exit(0);
+  })
+  .expect("Error setting Ctrl-C handler");
+
+  let name = PIPE.encode_utf16().chain([0]).collect::<Vec<_>>();
+
+  let Some(mut listening) = create(&name, true) else {
+    eprintln!("timr is already serving on {}", PIPE);
+    exit(1);
+  };
+
+  let (incoming_tx, incoming) = channel();
+
+  if let Some(address) = http {
+    serve_http(&address, incoming_tx.clone());
+  }
+
+  thread::spawn(move || {
+    for id in 0.. {
+      // a client connecting before this is called shows up as an error saying it's already connected
+      let connected = unsafe { ConnectNamedPipe(listening.as_raw_handle() as _, std::ptr::null_mut()) } != 0 || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+
+      let Some(next) = create(&name, false) else {
+        break;
+      };
+
+      let pipe = std::mem::replace(&mut listening, next);
+
+      if !connected {
+        continue;
+      }
+
+      let Ok(writer) = pipe.try_clone() else {
+        continue;
+      };
+
+      if incoming_tx.send(Incoming::Opened(id, Box::new(writer), Client::subscribed(&[]))).is_err() {
+        break;
+      }
+
+      let incoming_tx = incoming_tx.clone();
+      thread::spawn(move || read_lines(id, BufReader::new(pipe), &incoming_tx));
+    }
+  });
+
+  run(incoming, false, true, Some(socket_path().with_extension("toml")));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A client that never reads, until `unstuck` is dropped.
+  struct Stuck {
+    unstuck: Receiver<()>,
+  }
+
+  impl Write for Stuck {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+      let _ = self.unstuck.recv();
+      Err(std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn falling_behind() {
+    let (unstick, unstuck) = channel();
+    let connected = Connected::new(0, Box::new(Stuck { unstuck }), Client::subscribed(&[]));
+
+    // let go once the backlog fills behind the message being written, without waiting on the client
+    assert!((0..BACKLOG).all(|_| connected.send("{}")));
+    assert!(!(0..2).all(|_| connected.send("{}")));
+
+    drop(unstick);
+    connected.writing.join().unwrap();
+  }
+
+  #[test]
+  fn requests() {
+    let mut server = Server::new(Config::default(), false);
+    // kept out of the real history
+    server.history = false;
+    let mut client = Client::subscribed(&[]);
+    let mut handle = |line: &str| server.handle(line, &mut client);
+
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"start","params":{"duration":"25m","label":"write"}}"#)
+      .unwrap()
+      .starts_with(r#"{"jsonrpc":"2.0","id":1,"result":{"id":1,"label":"write","stage":"","duration":1500,"remaining":"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":2,"method":"extend","params":{"id":1,"by":"5m"}}"#)
+      .unwrap()
+      .contains(r#""duration":1800"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":3,"method":"pause","params":{"id":1}}"#)
+      .unwrap()
+      .contains(r#""paused":true"#));
+    assert_eq!(handle(r#"{"jsonrpc":"2.0","method":"cancel","params":{"id":1}}"#), None);
+    assert_eq!(
+      handle(r#"{"jsonrpc":"2.0","id":"q","method":"query"}"#),
+      Some(r#"{"jsonrpc":"2.0","id":"q","result":[]}"#.to_string())
+    );
+
+    // still listed for a while, saying how it ended
+    assert_eq!(server.ended.len(), 1);
+    assert_eq!(server.ended[0].outcome, state::Outcome::Cancelled);
+  }
+
+  #[test]
+  fn versions_and_labels() {
+    let mut server = Server::new(Config::default(), false);
+    let mut client = Client::subscribed(&[]);
+    let mut handle = |line: &str| server.handle(line, &mut client).unwrap();
+
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"version"}"#).contains(r#""result":{"protocol":1,"#));
+
+    handle(r#"{"jsonrpc":"2.0","id":2,"method":"start","params":{"duration":"25m","label":"focus"}}"#);
+    assert!(handle(r#"{"jsonrpc":"2.0","id":3,"method":"pause","params":{"label":"focus"}}"#).contains(r#""paused":true"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":4,"method":"pause","params":{"label":"tea"}}"#).contains("No timer named tea"));
+  }
+
+  #[test]
+  fn broken_profiles() {
+    let config = toml::from_str::<Config>("[[profiles]]\nname = \"tea\"\nduration = \"3x\"\n\n[[profiles]]\nname = \"empty\"\n").unwrap();
+    let mut server = Server::new(config, false);
+    let mut client = Client::subscribed(&[]);
+    let mut handle = |line: &str| server.handle(line, &mut client).unwrap();
+
+    // answered with an error, rather than taking the daemon down
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"start","params":{"profile":"tea"}}"#).contains(r#""code":-32602"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":2,"method":"start","params":{"profile":"empty"}}"#).contains("Profile empty has no duration or stages"));
+  }
+
+  #[test]
+  fn names() {
+    let mut server = Server::new(Config::default(), false);
+    let mut client = Client::subscribed(&[]);
+    let mut handle = |line: &str| server.handle(line, &mut client).unwrap();
+
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"start","params":{"duration":"5m"}}"#).contains(r#""label":"5m""#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":2,"method":"start","params":{"duration":"5m"}}"#).contains(r#""label":"5m-2""#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":3,"method":"start","params":{"duration":"1m","label":"5m"}}"#).contains("A timer named 5m is already running"));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":4,"method":"query","params":{"label":"2"}}"#).contains(r#""label":"5m-2""#));
+  }
+
+  #[test]
+  fn recovery() {
+    let path = std::env::temp_dir().join(format!("timr-recovery-{}.toml", std::process::id()));
+    let saved = |label: &str, paused| SavedTimer {
+      label: label.to_string(),
+      stages: vec![SavedStage {
+        name: String::new(),
+        duration: 60.0,
+      }],
+      elapsed: 30.0,
+      paused,
+      started_at: state::now() - 120.0,
+      saved_at: state::now() - 90.0,
+      profile: None,
+      tags: Vec::new(),
+      origin: Origin {
+        exec: Some("./log.sh".to_string()),
+        dir: Some(PathBuf::from("/src/project")),
+        env: vec![("PATH".to_string(), "/src/project/bin".to_string())],
+      },
+    };
+
+    let timers = vec![saved("tea", false), saved("focus", true)];
+    fs::write(&path, toml::to_string(&Saved { timers }).unwrap()).unwrap();
+
+    let mut server = Server::new(Config::default(), false);
+    server.recover(path.clone());
+    let _ = fs::remove_file(&path);
+
+    assert!(server.timers[0].timer.is_finished());
+    assert_eq!(server.timers[1].timer.remaining(), Duration::from_secs(30));
+    assert!(server.timers[1].timer.is_paused());
+    assert_eq!(server.timers[1].origin.dir.as_deref(), Some(Path::new("/src/project")));
+    assert_eq!(server.timers[1].origin.env, [("PATH".to_string(), "/src/project/bin".to_string())]);
+  }
+
+  #[test]
+  fn errors() {
+    let mut server = Server::new(Config::default(), false);
+    let mut client = Client::subscribed(&[]);
+    let mut handle = |line: &str| server.handle(line, &mut client).unwrap();
+
+    assert!(handle("{").contains(r#""code":-32700"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"nap"}"#).contains(r#""code":-32601"#));
+    assert!(handle(r#"{"jsonrpc":"2.0","id":1,"method":"pause","params":{"id":7}}"#).contains(r#""code":-32602"#));
+  }
+
+  #[test]
+  fn subscriptions() {
+    let mut server = Server::new(Config::default(), false);
+    let mut client = Client::subscribed(&[]);
+
+    server.handle(r#"{"jsonrpc":"2.0","id":1,"method":"subscribe","params":{"events":["tick","finished"]}}"#, &mut client);
+    assert!(client.wants("tick") && client.wants("finished") && !client.wants("stage"));
+
+    server.handle(r#"{"jsonrpc":"2.0","id":2,"method":"unsubscribe","params":{"events":["tick"]}}"#, &mut client);
+    assert!(!client.wants("tick") && client.wants("finished"));
+
+    assert!(server
+      .handle(r#"{"jsonrpc":"2.0","id":3,"method":"subscribe","params":{"events":["lunch"]}}"#, &mut client)
+      .unwrap()
+      .contains(r#""code":-32602"#));
+  }
+}
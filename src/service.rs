@@ -0,0 +1,166 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  process::{exit, Command, Stdio},
+};
+
+use crate::home_dir;
+
+/// What the daemon is known as to launchd and the Task Scheduler.
+const LABEL: &str = "com.github.the-noah.timr";
+/// Name of the systemd user unit.
+const UNIT: &str = "timr.service";
+
+/// Set the daemon up to start at login for the current user, with systemd, launchd, or the Task Scheduler.
+pub fn install() {
+  let exe = std::env::current_exe().unwrap_or_else(|error| {
+    eprintln!("Failed to find timr: {}", error);
+    exit(1);
+  });
+
+  if cfg!(windows) {
+    let command = format!("\"{}\" serve", exe.display());
+
+    if !run("schtasks", &["/Create", "/F", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", "timr", "/TR", &command]) {
+      eprintln!("Failed to create the scheduled task");
+      exit(1);
+    }
+
+    println!("Created the timr scheduled task, so the daemon starts at login");
+    return;
+  }
+
+  let (path, contents) = if cfg!(target_os = "macos") {
+    (plist_path(), launchd_plist(&exe))
+  } else {
+    (unit_path(), systemd_unit(&exe))
+  };
+
+  if let Err(error) = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&path, contents)) {
+    eprintln!("Failed to write {}: {}", path.display(), error);
+    exit(1);
+  }
+
+  // started now as well as at login, so there's no need to log out first
+  let enabled = if cfg!(target_os = "macos") {
+    run("launchctl", &["load", "-w", &path.display().to_string()])
+  } else {
+    run("systemctl", &["--user", "daemon-reload"]) && run("systemctl", &["--user", "enable", "--now", UNIT])
+  };
+
+  println!("Wrote {}", path.display());
+
+  if enabled {
+    println!("The daemon now starts at login");
+  } else if cfg!(target_os = "macos") {
+    println!("Load it with launchctl load -w {} to start the daemon at login", path.display());
+  } else {
+    println!("Enable it with systemctl --user enable --now {} to start the daemon at login", UNIT);
+  }
+}
+
+/// Stop the daemon starting at login, undoing `install`.
+pub fn uninstall() {
+  if cfg!(windows) {
+    if !run("schtasks", &["/Delete", "/F", "/TN", "timr"]) {
+      eprintln!("Failed to delete the scheduled task");
+      exit(1);
+    }
+
+    println!("Deleted the timr scheduled task");
+    return;
+  }
+
+  let path = if cfg!(target_os = "macos") {
+    let path = plist_path();
+    run("launchctl", &["unload", "-w", &path.display().to_string()]);
+    path
+  } else {
+    run("systemctl", &["--user", "disable", "--now", UNIT]);
+    unit_path()
+  };
+
+  match fs::remove_file(&path) {
+    Ok(_) => println!("Removed {}", path.display()),
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => println!("The daemon wasn't set to start at login"),
+    Err(error) => {
+      eprintln!("Failed to remove {}: {}", path.display(), error);
+      exit(1);
+    }
+  }
+}
+
+/// Run `program` with `args` quietly, returning whether it succeeded.
+fn run(program: &str, args: &[&str]) -> bool {
+  Command::new(program)
+    .args(args)
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .is_ok_and(|status| status.success())
+}
+
+/// Get where the systemd user unit goes.
+fn unit_path() -> PathBuf {
+  let config = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|config| !config.is_empty())
+    .map(PathBuf::from)
+    .unwrap_or_else(|| home_dir().expect("Failed to find user's home directory").join(".config"));
+
+  config.join("systemd").join("user").join(UNIT)
+}
+
+/// Get where the launchd agent goes.
+fn plist_path() -> PathBuf {
+  home_dir()
+    .expect("Failed to find user's home directory")
+    .join("Library")
+    .join("LaunchAgents")
+    .join(format!("{}.plist", LABEL))
+}
+
+/// Get a systemd user unit running the daemon from `exe`.
+fn systemd_unit(exe: &Path) -> String {
+  format!(
+    "[Unit]\nDescription=timr daemon\n\n[Service]\nExecStart=\"{}\" serve\nRestart=on-failure\n\n[Install]\nWantedBy=default.target\n",
+    exe.display().to_string().replace('\\', "\\\\").replace('"', "\\\"")
+  )
+}
+
+/// Get a launchd agent running the daemon from `exe` at login.
+fn launchd_plist(exe: &Path) -> String {
+  let exe = exe.display().to_string().replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+
+  format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>{}</string>
+    <string>serve</string>
+  </array>
+  <key>RunAtLoad</key>
+  <true/>
+</dict>
+</plist>
+"#,
+    LABEL, exe
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn service_files() {
+    let exe = Path::new("/opt/my tools/timr");
+
+    assert!(systemd_unit(exe).contains("ExecStart=\"/opt/my tools/timr\" serve\n"));
+    assert!(launchd_plist(exe).contains("<string>/opt/my tools/timr</string>\n    <string>serve</string>"));
+  }
+}
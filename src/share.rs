@@ -0,0 +1,194 @@
+use std::{
+  io::{self, BufRead, BufReader, Write},
+  net::{TcpListener, TcpStream, ToSocketAddrs},
+  sync::{
+    mpsc::{channel, sync_channel, Receiver, SyncSender},
+    Arc, Mutex,
+  },
+  thread,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  json::{self, Value},
+  timer::Timer,
+};
+
+/// How often the timers are sent to those who've joined, who count down on their own in between.
+const SEND_EVERY: Duration = Duration::from_millis(250);
+/// How long to wait on the host before giving up on joining it.
+const TIMEOUT: Duration = Duration::from_secs(3);
+/// How long a send to someone who's joined may take before they're let go.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Most messages kept waiting for someone who's joined, beyond which they're let go for not keeping up.
+const BACKLOG: usize = 16;
+
+/// Timers shared with `--share`, along with everyone who's joined them.
+pub struct Host {
+  joined: Arc<Mutex<Joined>>,
+  last_sent: Option<Instant>,
+}
+
+/// Those who've joined, and the last message sent to them, for anyone joining next.
+#[derive(Default)]
+struct Joined {
+  /// Messages waiting to be written to each who's joined, on a thread of their own.
+  writers: Vec<SyncSender<String>>,
+  last: String,
+}
+
+/// Share the timers on `address`, like `0.0.0.0:7228`, for `timr join` to show on other machines.
+pub fn host(address: &str) -> io::Result<Host> {
+  Ok(serve(TcpListener::bind(address)?))
+}
+
+/// Share the timers with everyone who connects to `listener`.
+fn serve(listener: TcpListener) -> Host {
+  let joined = Arc::new(Mutex::new(Joined::default()));
+  let joining = joined.clone();
+
+  thread::spawn(move || {
+    for stream in listener.incoming().flatten() {
+      if stream.set_nodelay(true).is_err() || stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+        continue;
+      }
+
+      let writer = write_to(stream);
+      let mut joined = joining.lock().unwrap();
+
+      // caught up straight away, rather than waiting on the next update
+      if joined.last.is_empty() || writer.try_send(joined.last.clone()).is_ok() {
+        joined.writers.push(writer);
+      }
+    }
+  });
+
+  Host { joined, last_sent: None }
+}
+
+impl Host {
+  /// Send `timers`, each with its label, to everyone who's joined, every so often.
+  pub fn update<'a>(&mut self, timers: impl IntoIterator<Item = (&'a str, &'a Timer)>) {
+    if self.last_sent.is_some_and(|sent| sent.elapsed() < SEND_EVERY) {
+      return;
+    }
+
+    self.last_sent = Some(Instant::now());
+
+    let timers = timers.into_iter().map(|(label, timer)| describe(label, timer)).collect();
+    self.send(Value::object([("timers", Value::Array(timers))]));
+  }
+
+  /// Tell everyone who's joined that the timers are over, with `message` saying how.
+  pub fn end(&mut self, message: &str) {
+    self.send(Value::object([("ended", message.into())]));
+  }
+
+  /// Send `message` to everyone who's joined, letting go of any who've left or aren't keeping up.
+  fn send(&mut self, message: Value) {
+    let mut joined = self.joined.lock().unwrap();
+    let message = message.to_string();
+
+    // only queued here, so one slow machine can't hold up the countdown
+    joined.writers.retain(|writer| writer.try_send(message.clone()).is_ok());
+    joined.last = message;
+  }
+}
+
+/// Write each message sent to the channel to `stream` on a thread of its own, closing it once the channel's dropped or a write fails.
+fn write_to(mut stream: TcpStream) -> SyncSender<String> {
+  let (message_tx, messages) = sync_channel::<String>(BACKLOG);
+
+  thread::spawn(move || {
+    for message in messages {
+      if writeln!(stream, "{}", message).is_err() {
+        break;
+      }
+    }
+  });
+
+  message_tx
+}
+
+/// Describe `timer` for those who've joined, in the same shape the daemon uses.
+fn describe(label: &str, timer: &Timer) -> Value {
+  let stages = timer
+    .stages()
+    .iter()
+    .map(|stage| Value::object([("name", stage.name.as_str().into()), ("duration", stage.duration.as_secs_f64().into())]))
+    .collect();
+
+  Value::object([
+    ("label", label.into()),
+    ("duration", timer.duration().as_secs_f64().into()),
+    ("remaining", timer.remaining().as_secs_f64().into()),
+    ("paused", timer.is_paused().into()),
+    ("stages", Value::Array(stages)),
+  ])
+}
+
+/// Join the timers shared at `address`, getting each message from the host as it arrives until it stops sharing.
+pub fn join(address: &str) -> io::Result<Receiver<Value>> {
+  let resolved = address
+    .to_socket_addrs()?
+    .next()
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "address didn't resolve"))?;
+
+  let stream = TcpStream::connect_timeout(&resolved, TIMEOUT)?;
+  let (message_tx, messages) = channel();
+
+  thread::spawn(move || {
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+      let Ok(message) = json::parse(&line) else {
+        continue;
+      };
+
+      if message_tx.send(message).is_err() {
+        break;
+      }
+    }
+  });
+
+  Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::timer::Stage;
+
+  #[test]
+  fn joining() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap_or_else(|error| panic!("{}", error));
+    let address = listener.local_addr().unwrap().to_string();
+    let mut host = serve(listener);
+    let timer = Timer::new(vec![Stage {
+      name: String::new(),
+      duration: Duration::from_secs(60),
+    }]);
+
+    host.update([("tea", &timer)]);
+    assert!(host.joined.lock().unwrap().last.contains(r#""label":"tea""#));
+
+    // sent again only once enough time has passed
+    host.update([("coffee", &timer)]);
+    assert!(!host.joined.lock().unwrap().last.contains("coffee"));
+
+    // caught up on joining, then sent what comes after
+    let messages = join(&address).unwrap_or_else(|error| panic!("{}", error));
+    let first = messages.recv_timeout(TIMEOUT).unwrap();
+    assert!(matches!(first.get("timers"), Some(Value::Array(timers)) if timers.len() == 1));
+
+    host.end("tea finished");
+    assert_eq!(host.joined.lock().unwrap().last, r#"{"ended":"tea finished"}"#);
+    assert_eq!(messages.recv_timeout(TIMEOUT).unwrap().get("ended").and_then(|ended| ended.as_str()), Some("tea finished"));
+
+    // someone who isn't keeping up is let go once their backlog fills, rather than being waited on
+    let (stuck_tx, _stuck) = sync_channel(1);
+    host.joined.lock().unwrap().writers.push(stuck_tx);
+    host.end("first");
+    assert_eq!(host.joined.lock().unwrap().writers.len(), 2);
+    host.end("second");
+    assert_eq!(host.joined.lock().unwrap().writers.len(), 1);
+  }
+}
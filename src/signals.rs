@@ -0,0 +1,70 @@
+use std::{
+  iter::repeat_n,
+  sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::terminal::input::Key;
+
+/// Times SIGUSR1, to pause or resume the timers, arrived since last checked.
+static TOGGLES: AtomicUsize = AtomicUsize::new(0);
+/// Times SIGUSR2, to skip to the next stage, arrived since last checked.
+static SKIPS: AtomicUsize = AtomicUsize::new(0);
+/// Whether SIGTERM or SIGHUP asked timr to stop, as when its terminal closes.
+static ENDED: AtomicBool = AtomicBool::new(false);
+
+/// Note the signal, leaving the timer to act on it, as little else is safe in a handler.
+#[cfg(unix)]
+extern "C" fn handle(signal: libc::c_int) {
+  match signal {
+    libc::SIGUSR1 => {
+      TOGGLES.fetch_add(1, Ordering::SeqCst);
+    }
+    libc::SIGUSR2 => {
+      SKIPS.fetch_add(1, Ordering::SeqCst);
+    }
+    _ => ENDED.store(true, Ordering::SeqCst),
+  }
+}
+
+/// Listen for SIGUSR1 to pause or resume the timers and SIGUSR2 to skip to their next stage, so keybindings and scripts can control them with `kill`.
+#[cfg(unix)]
+pub fn listen() {
+  let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+  unsafe {
+    libc::signal(libc::SIGUSR1, handler);
+    libc::signal(libc::SIGUSR2, handler);
+  }
+}
+
+/// Listen for signals, which only exist on Unix.
+#[cfg(not(unix))]
+pub fn listen() {}
+
+/// Catch SIGTERM and SIGHUP, in place of the Ctrl+C handler, so the timers can be left to pick back up rather than cancelled.
+#[cfg(unix)]
+pub fn catch_end() {
+  let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+  unsafe {
+    libc::signal(libc::SIGTERM, handler);
+    libc::signal(libc::SIGHUP, handler);
+  }
+}
+
+/// Catch the signals ending timr, which only exist on Unix.
+#[cfg(not(unix))]
+pub fn catch_end() {}
+
+/// Check whether timr was asked to stop by SIGTERM or SIGHUP.
+pub fn ended() -> bool {
+  ENDED.load(Ordering::SeqCst)
+}
+
+/// Take the signals that arrived since last checked, each as the key that does the same.
+pub fn take() -> Vec<Key> {
+  let toggles = TOGGLES.swap(0, Ordering::SeqCst);
+  let skips = SKIPS.swap(0, Ordering::SeqCst);
+
+  repeat_n(Key::Char('p'), toggles).chain(repeat_n(Key::Char('s'), skips)).collect()
+}
@@ -0,0 +1,32 @@
+use crate::{format_remaining, webhook, Options, Running};
+
+/// Emoji the message starts with, when neither the profile nor the config gives one.
+const EMOJI: &str = "⏰";
+
+/// Post to Slack, if set up, that `running` has finished.
+pub fn post(running: &Running, options: &Options) {
+  let Some(slack) = &options.slack else {
+    return;
+  };
+
+  let emoji = running.slack_emoji.as_deref().or(slack.emoji.as_deref()).unwrap_or(EMOJI);
+  let text = message(emoji, &running.label, &format_remaining(running.timer.duration().as_secs_f64()));
+
+  webhook::post(&slack.webhook, &webhook::slack_json(&text, running.slack_channel.as_deref().or(slack.channel.as_deref())));
+}
+
+/// Get the message saying the timer called `label` has finished after `duration`.
+fn message(emoji: &str, label: &str, duration: &str) -> String {
+  format!("{} {} timer finished ({})", emoji, label, duration)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn messages() {
+    assert_eq!(message(EMOJI, "tea", "4m0s"), "⏰ tea timer finished (4m0s)");
+    assert_eq!(message("🍅", "write report", "25m0s"), "🍅 write report timer finished (25m0s)");
+  }
+}
@@ -0,0 +1,229 @@
+use std::{path::PathBuf, thread::JoinHandle};
+
+use serde::Deserialize;
+
+/// Loudness of sounds when not set, as a percentage of the file's own volume.
+pub const DEFAULT_VOLUME: u8 = 100;
+
+/// A note of a synthesized tone, as its frequency in hertz (0 for a rest) and length in milliseconds.
+type Note = (f32, u64);
+
+#[cfg(feature = "sound")]
+const TONE_AMPLITUDE: f32 = 0.2; // sine waves at full volume are harsh
+#[cfg(feature = "sound")]
+const TONE_SAMPLE_RATE: u32 = 48000;
+
+const CHIME_START: &[Note] = &[(523.25, 120), (783.99, 180)];
+const CHIME_STAGE_END: &[Note] = &[(659.25, 250)];
+const CHIME_FINISH: &[Note] = &[(783.99, 150), (659.25, 150), (523.25, 350)];
+const CHIME_WARNING: &[Note] = &[(440.0, 200)];
+
+const BEEPS_START: &[Note] = &[(880.0, 80)];
+const BEEPS_STAGE_END: &[Note] = &[(880.0, 80), (0.0, 80), (880.0, 80)];
+const BEEPS_FINISH: &[Note] = &[(1000.0, 100), (0.0, 80), (1000.0, 100), (0.0, 80), (1000.0, 100)];
+const BEEPS_WARNING: &[Note] = &[(660.0, 80)];
+
+/// Something that happens to a timer which can have a sound of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+  Start,
+  StageEnd,
+  Finish,
+  Warning,
+}
+
+/// A sound to play, either from a file or synthesized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sound {
+  File(PathBuf),
+  Tone(&'static [Note]),
+}
+
+/// The sound played for each event.
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+  pub start: Option<Sound>,
+  pub stage_end: Option<Sound>,
+  pub finish: Option<Sound>,
+  pub warning: Option<Sound>,
+}
+
+impl Theme {
+  /// Get the sound played for `event`, if any.
+  pub fn get(&self, event: Event) -> Option<Sound> {
+    match event {
+      Event::Start => self.start.clone(),
+      Event::StageEnd => self.stage_end.clone(),
+      Event::Finish => self.finish.clone(),
+      Event::Warning => self.warning.clone(),
+    }
+  }
+
+  /// Get the audio files the theme plays.
+  pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+    [&self.start, &self.stage_end, &self.finish, &self.warning].into_iter().filter_map(|sound| match sound {
+      Some(Sound::File(path)) => Some(path),
+      _ => None,
+    })
+  }
+}
+
+/// Which set of sounds to play for a timer's events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+  /// The audio files from the `[sounds]` table of the config file.
+  #[default]
+  Custom,
+  /// Soft synthesized notes.
+  Chime,
+  /// Short synthesized beeps.
+  Beeps,
+}
+
+impl std::str::FromStr for ThemeName {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "custom" => Ok(Self::Custom),
+      "chime" => Ok(Self::Chime),
+      "beeps" => Ok(Self::Beeps),
+      _ => Err(format!("Invalid sound theme: {}", value)),
+    }
+  }
+}
+
+impl ThemeName {
+  /// Get the sounds of the theme, with `custom` being the ones from the config file.
+  pub fn theme(self, custom: Theme) -> Theme {
+    let tones = |start, stage_end, finish, warning| Theme {
+      start: Some(Sound::Tone(start)),
+      stage_end: Some(Sound::Tone(stage_end)),
+      finish: Some(Sound::Tone(finish)),
+      warning: Some(Sound::Tone(warning)),
+    };
+
+    match self {
+      Self::Custom => custom,
+      Self::Chime => tones(CHIME_START, CHIME_STAGE_END, CHIME_FINISH, CHIME_WARNING),
+      Self::Beeps => tones(BEEPS_START, BEEPS_STAGE_END, BEEPS_FINISH, BEEPS_WARNING),
+    }
+  }
+}
+
+/// Check whether timr was built with audio playback support.
+pub fn supported() -> bool {
+  cfg!(feature = "sound")
+}
+
+/// Start playing `sound` at `volume` percent on a background thread, so the timer carries on meanwhile.
+///
+/// Files can be WAV, MP3, OGG, or FLAC. Join the returned thread to wait for it to finish, or drop it to let it play out on its own.
+#[cfg(feature = "sound")]
+pub fn play(sound: &Sound, volume: u8) -> Option<JoinHandle<()>> {
+  let sound = sound.clone();
+
+  Some(std::thread::spawn(move || {
+    let result = match &sound {
+      Sound::File(path) => play_to_end(path, volume),
+      Sound::Tone(notes) => play_tone(notes, volume),
+    };
+
+    if let Err(error) = result {
+      match sound {
+        Sound::File(path) => eprintln!("Failed to play {}: {}", path.display(), error),
+        Sound::Tone(_) => eprintln!("Failed to play tone: {}", error),
+      }
+    }
+  }))
+}
+
+/// Play a sound, which does nothing without audio playback support.
+#[cfg(not(feature = "sound"))]
+pub fn play(_sound: &Sound, _volume: u8) -> Option<JoinHandle<()>> {
+  None
+}
+
+/// Parse a volume from 0 to 100.
+pub fn parse_volume(value: &str) -> Result<u8, String> {
+  match value.trim_end_matches('%').parse() {
+    Ok(volume) if volume <= 100 => Ok(volume),
+    _ => Err(format!("Invalid volume: {}", value)),
+  }
+}
+
+#[cfg(feature = "sound")]
+fn play_to_end(path: &std::path::Path, volume: u8) -> Result<(), Box<dyn std::error::Error>> {
+  use std::{fs::File, io::BufReader};
+
+  // the stream has to stay alive for as long as the sound plays
+  let (_stream, handle) = rodio::OutputStream::try_default()?;
+  let sink = rodio::Sink::try_new(&handle)?;
+  sink.set_volume(f32::from(volume) / 100.0);
+
+  sink.append(rodio::Decoder::new(BufReader::new(File::open(path)?))?);
+  sink.sleep_until_end();
+
+  Ok(())
+}
+
+#[cfg(feature = "sound")]
+fn play_tone(notes: &[Note], volume: u8) -> Result<(), Box<dyn std::error::Error>> {
+  use std::time::Duration;
+
+  use rodio::{
+    source::{SineWave, Zero},
+    Source,
+  };
+
+  let (_stream, handle) = rodio::OutputStream::try_default()?;
+  let sink = rodio::Sink::try_new(&handle)?;
+  sink.set_volume(f32::from(volume) / 100.0);
+
+  for &(frequency, length) in notes {
+    let length = Duration::from_millis(length);
+
+    if frequency == 0.0 {
+      sink.append(Zero::<f32>::new(1, TONE_SAMPLE_RATE).take_duration(length));
+    } else {
+      sink.append(SineWave::new(frequency).take_duration(length).amplify(TONE_AMPLITUDE));
+    }
+  }
+
+  sink.sleep_until_end();
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn volumes() {
+    assert_eq!(parse_volume("0"), Ok(0));
+    assert_eq!(parse_volume("35%"), Ok(35));
+    assert_eq!(parse_volume("100"), Ok(100));
+    assert!(parse_volume("101").is_err());
+    assert!(parse_volume("-5").is_err());
+    assert!(parse_volume("loud").is_err());
+  }
+
+  #[test]
+  fn themes() {
+    let custom = Theme {
+      finish: Some(Sound::File(PathBuf::from("done.ogg"))),
+      ..Default::default()
+    };
+
+    let theme = ThemeName::Custom.theme(custom.clone());
+    assert_eq!(theme.get(Event::Finish), custom.finish);
+    assert_eq!(theme.get(Event::Start), None);
+    assert_eq!(theme.files().collect::<Vec<_>>(), vec![&PathBuf::from("done.ogg")]);
+
+    let theme = ThemeName::Chime.theme(custom);
+    assert_eq!(theme.get(Event::Finish), Some(Sound::Tone(CHIME_FINISH)));
+    assert_eq!(theme.files().count(), 0);
+  }
+}
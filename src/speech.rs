@@ -0,0 +1,86 @@
+use std::process::{Child, Command, Stdio};
+
+/// Start saying `text` aloud with the platform's text-to-speech engine, returning the process so it can be waited on.
+///
+/// Returns `None` when no engine could be found.
+pub fn say(text: &str) -> Option<Child> {
+  engines(text)
+    .into_iter()
+    .find_map(|mut command| command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn().ok())
+}
+
+/// Get the commands to try, in order of preference, to say `text`.
+#[cfg(target_os = "macos")]
+fn engines(text: &str) -> Vec<Command> {
+  let mut say = Command::new("say");
+  say.arg(text);
+
+  vec![say]
+}
+
+/// Get the commands to try, in order of preference, to say `text`.
+#[cfg(windows)]
+fn engines(text: &str) -> Vec<Command> {
+  // passed through the environment so the text never has to be quoted for PowerShell
+  let mut sapi = Command::new("powershell");
+  sapi
+    .args([
+      "-NoProfile",
+      "-NonInteractive",
+      "-Command",
+      "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:TIMR_SPEECH)",
+    ])
+    .env("TIMR_SPEECH", text);
+
+  vec![sapi]
+}
+
+/// Get the commands to try, in order of preference, to say `text`.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn engines(text: &str) -> Vec<Command> {
+  let mut espeak_ng = Command::new("espeak-ng");
+  espeak_ng.arg(text);
+
+  let mut espeak = Command::new("espeak");
+  espeak.arg(text);
+
+  // speech dispatcher returns straight away unless told to wait
+  let mut spd_say = Command::new("spd-say");
+  spd_say.args(["--wait", text]);
+
+  vec![espeak_ng, espeak, spd_say]
+}
+
+/// Write a duration out the way it would be said, like `1 hour 30 minutes`.
+pub fn spoken_duration(seconds: f64) -> String {
+  let seconds = seconds.round() as u64;
+  let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+  let mut parts = Vec::new();
+
+  for (value, unit) in [(hours, "hour"), (minutes, "minute"), (seconds, "second")] {
+    match value {
+      0 => {}
+      1 => parts.push(format!("1 {}", unit)),
+      _ => parts.push(format!("{} {}s", value, unit)),
+    }
+  }
+
+  if parts.is_empty() {
+    return "0 seconds".to_string();
+  }
+
+  parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn spoken_durations() {
+    assert_eq!(spoken_duration(0.0), "0 seconds");
+    assert_eq!(spoken_duration(61.0), "1 minute 1 second");
+    assert_eq!(spoken_duration(5400.0), "1 hour 30 minutes");
+  }
+}
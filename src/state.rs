@@ -0,0 +1,403 @@
+use std::{fs, io, path::PathBuf, process};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_dir;
+
+/// How long after a timer should have ended its snapshot is still trusted, in case the process was killed without cleaning up.
+const STALE_AFTER: f64 = 60.0;
+/// How long the timers of a process that was lost, say along with its terminal, can still be resumed, in seconds.
+const LOST_FOR: f64 = 24.0 * 60.0 * 60.0;
+
+/// A snapshot of a running timer, published so other commands can show it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimerState {
+  /// Id the daemon knows the timer by, for timers running on it.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<u64>,
+  pub label: String,
+  /// Name of the current stage, empty for timers without stages.
+  pub stage: String,
+  /// Total duration in seconds.
+  pub duration: f64,
+  /// Seconds remaining when the snapshot was taken.
+  pub remaining: f64,
+  pub paused: bool,
+  /// When the timer was started, in seconds since the Unix epoch.
+  #[serde(default)]
+  pub started_at: f64,
+  /// When the snapshot was taken, in seconds since the Unix epoch.
+  pub saved_at: f64,
+}
+
+impl TimerState {
+  /// Get the seconds remaining right now, counting down from the snapshot unless the timer is paused.
+  pub fn remaining_now(&self) -> f64 {
+    if self.paused {
+      return self.remaining;
+    }
+
+    (self.remaining - (now() - self.saved_at)).max(0.0)
+  }
+
+  /// Get the seconds counted down right now, the point to pick the timer back up from.
+  pub fn elapsed_now(&self) -> f64 {
+    (self.duration - self.remaining_now()).max(0.0)
+  }
+
+  /// Check whether the timer has run out, or the process looks to have died without cleaning up.
+  fn is_stale(&self) -> bool {
+    !self.paused && self.remaining - (now() - self.saved_at) < -STALE_AFTER
+  }
+}
+
+/// Something asked of every running timer by another timr, like `timr pause`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+  Pause,
+  Resume,
+  /// Stop the timers, like pressing q.
+  Cancel,
+}
+
+impl Control {
+  /// Get the name the control goes by as a command and in control files.
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Pause => "pause",
+      Self::Resume => "resume",
+      Self::Cancel => "cancel",
+    }
+  }
+}
+
+impl std::str::FromStr for Control {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "pause" => Ok(Self::Pause),
+      "resume" => Ok(Self::Resume),
+      "cancel" => Ok(Self::Cancel),
+      _ => Err(format!("Invalid control: {}", value)),
+    }
+  }
+}
+
+/// How a timer came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+  Finished,
+  Cancelled,
+}
+
+/// A timer on the daemon that has ended, kept for a while so `timr list` can say how it went.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndedState {
+  pub id: u64,
+  pub label: String,
+  pub outcome: Outcome,
+  /// When the timer ended, in seconds since the Unix epoch.
+  pub ended_at: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+  /// Arguments the timers were started with, to start them again if the process is lost, or none when they can't be.
+  #[serde(default)]
+  args: Vec<String>,
+  timers: Vec<TimerState>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  ended: Vec<EndedState>,
+}
+
+/// Timers left behind by a process that went away without finishing them, along with the arguments they were started with.
+pub struct Lost {
+  pub args: Vec<String>,
+  pub timers: Vec<TimerState>,
+}
+
+/// Get the current time in seconds since the Unix epoch.
+pub fn now() -> f64 {
+  chrono::Utc::now().timestamp_millis() as f64 / 1000.0
+}
+
+/// Get the directory where running timers publish their snapshots.
+fn dir() -> PathBuf {
+  data_dir().join("timers")
+}
+
+/// Get the file naming the timer pinned for status bars and prompts.
+fn pin_path() -> PathBuf {
+  data_dir().join("pinned")
+}
+
+/// Pin the timer called `label` for status bars and prompts to show, or stop pinning one.
+pub fn pin(label: Option<&str>) -> std::io::Result<()> {
+  match label {
+    Some(label) => {
+      fs::create_dir_all(data_dir())?;
+      fs::write(pin_path(), label)
+    }
+    None => match fs::remove_file(pin_path()) {
+      Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+      _ => Ok(()),
+    },
+  }
+}
+
+/// Get the label of the timer pinned for status bars and prompts, if one is.
+pub fn pinned() -> Option<String> {
+  fs::read_to_string(pin_path()).ok().filter(|label| !label.is_empty())
+}
+
+/// Get the snapshot file for this process.
+fn path() -> PathBuf {
+  dir().join(format!("{}.toml", process::id()))
+}
+
+/// Publish the state of this process's timers, and those that have `ended` lately, replacing the last snapshot, with the `args` to start them again if it's lost.
+pub fn save(timers: Vec<TimerState>, ended: Vec<EndedState>, args: &[String]) -> io::Result<()> {
+  fs::create_dir_all(dir())?;
+
+  let snapshot = Snapshot {
+    args: args.to_vec(),
+    timers,
+    ended,
+  };
+
+  // write to the side and move into place so readers never see half a file
+  let temporary = path().with_extension("tmp");
+  fs::write(&temporary, toml::to_string(&snapshot).map_err(io::Error::other)?)?;
+  fs::rename(temporary, path())
+}
+
+/// Remove this process's snapshot, once its timers are done.
+pub fn remove() {
+  let _ = fs::remove_file(path());
+  let _ = fs::remove_file(path().with_extension("control"));
+}
+
+/// Load the snapshots of every running timer, cleaning up after processes that are gone.
+pub fn load() -> Vec<TimerState> {
+  snapshots().into_iter().flat_map(|(_, snapshot)| snapshot.timers).collect()
+}
+
+/// Load the timers that have ended lately, as kept by the daemon.
+pub fn load_ended() -> Vec<EndedState> {
+  snapshots().into_iter().flat_map(|(_, snapshot)| snapshot.ended).collect()
+}
+
+/// Ask every running timr but `except` to `control` its timers, or only those running a timer `picked` picks, returning how many were asked.
+///
+/// A `reason` for cancelling goes along on the line after the control.
+pub fn send(control: Control, reason: Option<&str>, picked: Option<&dyn Fn(&TimerState) -> bool>, except: Option<i32>) -> usize {
+  let pids = snapshots()
+    .into_iter()
+    .filter(|(pid, _)| Some(*pid) != except)
+    .filter(|(_, snapshot)| picked.is_none_or(|picked| snapshot.timers.iter().any(picked)))
+    .map(|(pid, _)| pid)
+    .collect::<Vec<_>>();
+
+  for pid in &pids {
+    // picked up on the timer's next tick, the same way it checks for keys
+    let contents = match reason {
+      Some(reason) => format!("{}\n{}", control.name(), reason.replace('\n', " ")),
+      None => control.name().to_string(),
+    };
+
+    if let Err(error) = fs::write(dir().join(format!("{}.control", pid)), contents) {
+      eprintln!("Failed to control timer: {}", error);
+    }
+  }
+
+  pids.len()
+}
+
+/// Take the timers lost most recently, or those of the one running a timer called `name`, so they can be picked back up.
+pub fn take_lost(name: Option<&str>) -> Option<Lost> {
+  // finds any process that has gone since last checked
+  snapshots();
+
+  let entries = fs::read_dir(dir()).ok()?;
+  let mut found: Option<(PathBuf, Snapshot)> = None;
+
+  for path in entries.flatten().map(|entry| entry.path()) {
+    if path.extension().is_none_or(|extension| extension != "lost") {
+      continue;
+    }
+
+    let Some(snapshot) = fs::read_to_string(&path).ok().and_then(|contents| toml::from_str::<Snapshot>(&contents).ok()) else {
+      let _ = fs::remove_file(&path);
+      continue;
+    };
+
+    let saved_at = saved_at(&snapshot);
+
+    if now() - saved_at > LOST_FOR {
+      let _ = fs::remove_file(&path);
+      continue;
+    }
+
+    if name.is_some_and(|name| !snapshot.timers.iter().any(|timer| timer.label == name)) {
+      continue;
+    }
+
+    if found.as_ref().is_none_or(|(_, newest)| saved_at > self::saved_at(newest)) {
+      found = Some((path, snapshot));
+    }
+  }
+
+  let (path, snapshot) = found?;
+  let _ = fs::remove_file(path);
+
+  Some(Lost {
+    args: snapshot.args,
+    timers: snapshot.timers,
+  })
+}
+
+/// Get when `snapshot` was last saved.
+fn saved_at(snapshot: &Snapshot) -> f64 {
+  snapshot.timers.iter().map(|timer| timer.saved_at).fold(0.0, f64::max)
+}
+
+/// Take the control sent to this process since last checked, if any, along with the reason for it if one was given.
+pub fn take_control() -> Option<(Control, Option<String>)> {
+  let path = path().with_extension("control");
+  let contents = fs::read_to_string(&path).ok()?;
+  let _ = fs::remove_file(&path);
+
+  let (control, reason) = contents.split_once('\n').unwrap_or((&contents, ""));
+  let reason = reason.trim();
+
+  Some((control.trim().parse().ok()?, (!reason.is_empty()).then(|| reason.to_string())))
+}
+
+/// Load the snapshot of every running timr, along with its pid, cleaning up after processes that are gone.
+fn snapshots() -> Vec<(i32, Snapshot)> {
+  let Ok(entries) = fs::read_dir(dir()) else {
+    return Vec::new();
+  };
+
+  let mut snapshots = Vec::new();
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+
+    if path.extension().is_none_or(|extension| extension != "toml") {
+      continue;
+    }
+
+    let pid = path.file_stem().and_then(|stem| stem.to_str()?.parse().ok()).filter(|&pid| is_running(pid));
+    let snapshot = fs::read_to_string(&path).ok().and_then(|contents| toml::from_str::<Snapshot>(&contents).ok());
+
+    match (pid, snapshot) {
+      (Some(pid), Some(snapshot)) if !snapshot.timers.iter().all(TimerState::is_stale) => snapshots.push((pid, snapshot)),
+      // the daemon with nothing running, but timers that ended lately to list
+      (Some(pid), Some(snapshot)) if snapshot.timers.is_empty() && !snapshot.ended.is_empty() => snapshots.push((pid, snapshot)),
+      // kept aside for `timr resume`, as the process went without finishing its timers
+      (None, Some(snapshot)) if !snapshot.args.is_empty() && !snapshot.timers.is_empty() => {
+        let _ = fs::rename(&path, path.with_extension("lost"));
+        let _ = fs::remove_file(path.with_extension("control"));
+      }
+      _ => {
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("control"));
+      }
+    }
+  }
+
+  snapshots
+}
+
+/// Check whether the process `pid` is still running.
+#[cfg(unix)]
+fn is_running(pid: i32) -> bool {
+  // signal 0 only checks the process exists, permission errors mean it belongs to someone else
+  unsafe { libc::kill(pid, 0) == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM) }
+}
+
+/// Check whether the process `pid` is still running.
+#[cfg(windows)]
+fn is_running(pid: i32) -> bool {
+  use windows_sys::Win32::{
+    Foundation::{CloseHandle, GetLastError, ERROR_ACCESS_DENIED, STILL_ACTIVE},
+    System::Threading::{GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+  };
+
+  unsafe {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid as u32);
+
+    // processes belonging to someone else can't be opened, but are still there
+    if process.is_null() {
+      return GetLastError() == ERROR_ACCESS_DENIED;
+    }
+
+    let mut code = 0;
+    let running = GetExitCodeProcess(process, &mut code) != 0 && code == STILL_ACTIVE as u32;
+    CloseHandle(process);
+
+    running
+  }
+}
+
+/// Check whether the process `pid` is still running, which is left to the stale check elsewhere.
+#[cfg(not(any(unix, windows)))]
+fn is_running(_pid: i32) -> bool {
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remaining_counts_down() {
+    let mut state = TimerState {
+      id: None,
+      label: "5m".to_string(),
+      stage: String::new(),
+      duration: 300.0,
+      remaining: 100.0,
+      paused: false,
+      started_at: 0.0,
+      saved_at: now() - 30.0,
+    };
+
+    assert_eq!(state.remaining_now().round(), 70.0);
+    assert!(!state.is_stale());
+
+    state.paused = true;
+    assert_eq!(state.remaining_now(), 100.0);
+
+    state.paused = false;
+    state.saved_at = now() - 200.0;
+    assert_eq!(state.remaining_now(), 0.0);
+    assert!(state.is_stale());
+  }
+
+  #[test]
+  fn lost_timers_pick_up_where_they_should_be() {
+    let mut state = TimerState {
+      id: None,
+      label: "focus".to_string(),
+      stage: String::new(),
+      duration: 300.0,
+      remaining: 200.0,
+      paused: false,
+      started_at: 0.0,
+      saved_at: now() - 60.0,
+    };
+
+    assert_eq!(state.elapsed_now().round(), 160.0);
+
+    state.paused = true;
+    assert_eq!(state.elapsed_now(), 100.0);
+
+    state.paused = false;
+    state.saved_at = now() - 1000.0;
+    assert_eq!(state.elapsed_now(), 300.0);
+  }
+}
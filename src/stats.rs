@@ -0,0 +1,512 @@
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::{config, format_short, history::Session, render::Rgb, terminal};
+
+/// Blocks a sparkline is drawn with, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Widest a bar in the tables is drawn.
+const BAR_WIDTH: usize = 30;
+/// Profile counting as a pomodoro when the config doesn't pick one.
+const POMODORO_PROFILE: &str = "pomodoro";
+/// Colors for the groups in a report, from the Okabe-Ito palette so they stay distinct for all common kinds of color blindness.
+const COLORS: [Rgb; 7] = [(230, 159, 0), (86, 180, 233), (0, 158, 115), (240, 228, 66), (0, 114, 178), (213, 94, 0), (204, 121, 167)];
+/// Color for the time in groups beyond those with a color of their own.
+const OTHER_COLOR: Rgb = (153, 153, 153);
+/// Blocks the groups in a report are drawn with when it isn't in color, so they can still be told apart.
+const FILLS: [char; 7] = ['█', '▓', '▒', '░', '▞', '▚', '▪'];
+/// Block for the time in groups beyond those with a block of their own.
+const OTHER_FILL: char = '·';
+
+/// What the time in `timr report` is broken down by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+  Tag,
+  Profile,
+}
+
+impl std::str::FromStr for Group {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "tag" => Ok(Self::Tag),
+      "profile" => Ok(Self::Profile),
+      _ => Err(format!("Invalid grouping: {}", value)),
+    }
+  }
+}
+
+/// How the pomodoros are going.
+#[derive(Debug, PartialEq)]
+pub struct Pomodoros {
+  /// Pomodoros finished today.
+  pub today: usize,
+  /// Pomodoros to aim for each day, if set.
+  pub goal: Option<u32>,
+  /// Pomodoros finished one after the other since the last one was cancelled.
+  pub streak: usize,
+}
+
+impl Pomodoros {
+  /// Get a short badge for today, like `🍅 4/8 today`.
+  pub fn badge(&self) -> String {
+    match self.goal {
+      Some(goal) => format!("🍅 {}/{} today", self.today, goal),
+      None => format!("🍅 {} today", self.today),
+    }
+  }
+
+  /// Get a line for `timr stats`, like `🍅 4/8 today, 6 in a row`.
+  fn line(&self) -> String {
+    let line = format!("Pomodoros  {}, {} in a row", self.badge(), self.streak);
+
+    if self.goal.is_some_and(|goal| self.today >= goal as usize) {
+      format!("{}, goal reached\n", line)
+    } else {
+      format!("{}\n", line)
+    }
+  }
+}
+
+/// How much a profile's been used, as told by the history.
+#[derive(Debug, PartialEq)]
+pub struct Usage {
+  /// Timers started from the profile, finished or not.
+  pub runs: usize,
+  /// Those that ran to the end.
+  pub finished: usize,
+  /// Average time they ran for in seconds, counting extensions and stopping short where cancelled.
+  pub average: f64,
+  /// When it was last started, in seconds since the Unix epoch.
+  pub last: Option<f64>,
+}
+
+/// Get how much `profile` has been used across `sessions`.
+pub fn usage(sessions: &[Session], profile: &str) -> Usage {
+  let runs = sessions.iter().filter(|session| session.profile.as_deref() == Some(profile)).collect::<Vec<_>>();
+
+  Usage {
+    runs: runs.len(),
+    finished: runs.iter().filter(|session| !session.cancelled).count(),
+    average: if runs.is_empty() {
+      0.0
+    } else {
+      runs.iter().map(|session| session.duration).sum::<f64>() / runs.len() as f64
+    },
+    last: runs.iter().map(|session| session.started_at).reduce(f64::max),
+  }
+}
+
+/// Get the profile counting as a pomodoro under `config`.
+pub fn pomodoro_profile(config: &config::Config) -> String {
+  config
+    .pomodoro
+    .as_ref()
+    .and_then(|pomodoro| pomodoro.profile.clone())
+    .unwrap_or_else(|| POMODORO_PROFILE.to_string())
+}
+
+/// Count the pomodoros in `sessions`, those from `profile`, finished on `today` and since the last one cancelled, against the `goal`.
+pub fn pomodoros(sessions: &[Session], profile: &str, goal: Option<u32>, today: NaiveDate) -> Pomodoros {
+  let pomodoros = sessions.iter().filter(|session| session.profile.as_deref() == Some(profile)).collect::<Vec<_>>();
+
+  Pomodoros {
+    today: pomodoros.iter().filter(|session| !session.cancelled && day(session) == today).count(),
+    goal,
+    streak: pomodoros.iter().rev().take_while(|session| !session.cancelled).count(),
+  }
+}
+
+/// Get the day `session` started on.
+fn day(session: &Session) -> NaiveDate {
+  DateTime::from_timestamp_millis((session.started_at * 1000.0) as i64)
+    .unwrap_or_default()
+    .with_timezone(&Local)
+    .date_naive()
+}
+
+/// Sum up `sessions` from `from` through `to`, as totals followed by tables by profile, by tag if any were tagged, and by day.
+///
+/// How the `pomodoros` are going is added to the totals when there are any, or a goal for them.
+pub fn text(sessions: &[Session], from: NaiveDate, to: NaiveDate, pomodoros: &Pomodoros) -> String {
+  let sessions = sessions.iter().filter(|session| (from..=to).contains(&day(session))).collect::<Vec<_>>();
+
+  if sessions.is_empty() {
+    return "No sessions in the history for this period\n".to_string();
+  }
+
+  // every day in the period, down to the first with anything in it so a long one doesn't run on for pages
+  let first = sessions.iter().map(|session| day(session)).min().unwrap().max(from);
+  let days = first.iter_days().take_while(|date| *date <= to).collect::<Vec<_>>();
+  let daily = days
+    .iter()
+    .map(|date| sessions.iter().filter(|session| day(session) == *date).map(|session| session.duration).sum::<f64>())
+    .collect::<Vec<_>>();
+
+  let total = daily.iter().sum::<f64>();
+  let finished = sessions.iter().filter(|session| !session.cancelled).count();
+
+  let mut text = format!(
+    "Timed      {} across {}  {}\nCompleted  {} of {} ({:.0}%)\n",
+    format_short(total),
+    count(sessions.len()),
+    sparkline(&daily),
+    finished,
+    sessions.len(),
+    finished as f64 / sessions.len() as f64 * 100.0
+  );
+
+  if pomodoros.streak > 0 || pomodoros.today > 0 || pomodoros.goal.is_some() {
+    text.push_str(&pomodoros.line());
+  }
+
+  let mut profiles = Vec::<(String, usize)>::new();
+
+  for session in &sessions {
+    let name = session.profile.clone().unwrap_or_else(|| "(none)".to_string());
+
+    match profiles.iter_mut().find(|(profile, _)| *profile == name) {
+      Some((_, count)) => *count += 1,
+      None => profiles.push((name, 1)),
+    }
+  }
+
+  profiles.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+  let most = profiles[0].1 as f64;
+  let rows = profiles
+    .iter()
+    .map(|(name, count)| (name.clone(), count.to_string(), bar(*count as f64, most)))
+    .collect::<Vec<_>>();
+  text.push_str(&format!("\nBy profile\n{}", table(&rows)));
+
+  let mut tags = Vec::<(String, f64)>::new();
+
+  for session in &sessions {
+    for tag in &session.tags {
+      match tags.iter_mut().find(|(name, _)| name == tag) {
+        Some((_, timed)) => *timed += session.duration,
+        None => tags.push((tag.clone(), session.duration)),
+      }
+    }
+  }
+
+  if !tags.is_empty() {
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let most = tags[0].1;
+    let rows = tags.iter().map(|(name, timed)| (name.clone(), format_short(*timed), bar(*timed, most))).collect::<Vec<_>>();
+    text.push_str(&format!("\nBy tag\n{}", table(&rows)));
+  }
+
+  let most = daily.iter().cloned().fold(0.0, f64::max);
+  let rows = days
+    .iter()
+    .zip(&daily)
+    .map(|(date, timed)| (date.format("%a %Y-%m-%d").to_string(), format_short(*timed), bar(*timed, most)))
+    .collect::<Vec<_>>();
+  text.push_str(&format!("\nBy day\n{}", table(&rows)));
+
+  text
+}
+
+/// Chart the time on `sessions` from `from` through `to` as a bar for each day up to `width` long, split up by `group` and in color if `colored`, with a key below.
+///
+/// Sessions with more than one tag count towards their first.
+pub fn report(sessions: &[Session], from: NaiveDate, to: NaiveDate, group: Group, width: usize, colored: bool) -> String {
+  let sessions = sessions.iter().filter(|session| (from..=to).contains(&day(session))).collect::<Vec<_>>();
+
+  if sessions.is_empty() {
+    return "No sessions in the history for this period\n".to_string();
+  }
+
+  let name = |session: &Session| {
+    match group {
+      Group::Tag => session.tags.first().cloned(),
+      Group::Profile => session.profile.clone(),
+    }
+    .unwrap_or_else(|| "(none)".to_string())
+  };
+
+  let mut groups = Vec::<(String, f64)>::new();
+
+  for session in &sessions {
+    let name = name(session);
+
+    match groups.iter_mut().find(|(group, _)| *group == name) {
+      Some((_, timed)) => *timed += session.duration,
+      None => groups.push((name, session.duration)),
+    }
+  }
+
+  groups.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+  // those beyond the colors there are share the last, as other
+  if groups.len() > COLORS.len() {
+    let other = groups.split_off(COLORS.len()).iter().map(|(_, timed)| timed).sum();
+    groups.push(("other".to_string(), other));
+  }
+
+  let index = |session: &Session| {
+    let name = name(session);
+    groups.iter().position(|(group, _)| *group == name).unwrap_or(COLORS.len())
+  };
+
+  let swatch = |index: usize, cells: usize| {
+    let fill = FILLS.get(index).copied().unwrap_or(OTHER_FILL);
+
+    if colored {
+      let (red, green, blue) = COLORS.get(index).copied().unwrap_or(OTHER_COLOR);
+      format!("{}{}{}", terminal::ansi_rgb(red, green, blue), "█".repeat(cells), terminal::reset_color())
+    } else {
+      fill.to_string().repeat(cells)
+    }
+  };
+
+  // every day in the period, down to the first with anything in it like the stats
+  let first = sessions.iter().map(|session| day(session)).min().unwrap().max(from);
+  let days = first.iter_days().take_while(|date| *date <= to).collect::<Vec<_>>();
+  let daily = days
+    .iter()
+    .map(|date| {
+      let mut timed = vec![0.0; COLORS.len() + 1];
+
+      for session in sessions.iter().filter(|session| day(session) == *date) {
+        timed[index(session)] += session.duration;
+      }
+
+      timed
+    })
+    .collect::<Vec<_>>();
+
+  let most = daily.iter().map(|timed| timed.iter().sum::<f64>()).fold(0.0, f64::max);
+  let total = groups.iter().map(|(_, timed)| timed).sum::<f64>();
+
+  let mut text = format!("Timed {} from {} to {}\n\n", format_short(total), first.format("%a %Y-%m-%d"), to.format("%a %Y-%m-%d"));
+
+  for (date, timed) in days.iter().zip(&daily) {
+    let mut bar = String::new();
+    let mut drawn = 0;
+    let mut sum = 0.0;
+
+    // each ends where the running total would, so rounding doesn't add up to a bar too long or short
+    for (index, timed) in timed.iter().enumerate() {
+      sum += timed;
+
+      let end = if most > 0.0 { ((sum / most) * width as f64).round() as usize } else { 0 };
+
+      if end > drawn {
+        bar.push_str(&swatch(index, end - drawn));
+        drawn = end;
+      }
+    }
+
+    let line = format!("  {}  {}{}  {}", date.format("%a %Y-%m-%d"), bar, " ".repeat(width - drawn), format_short(sum));
+    text.push_str(&format!("{}\n", line));
+  }
+
+  let rows = groups
+    .iter()
+    .enumerate()
+    .map(|(index, (name, timed))| (format!("{} {}", swatch(index, 1), name), format_short(*timed), String::new()))
+    .collect::<Vec<_>>();
+  text.push_str(&format!("\n{}", table(&rows)));
+
+  text
+}
+
+/// Get how many sessions there were, like `3 sessions`.
+fn count(sessions: usize) -> String {
+  if sessions == 1 {
+    "1 session".to_string()
+  } else {
+    format!("{} sessions", sessions)
+  }
+}
+
+/// Draw `values` as a sparkline, one block each, scaled to the largest.
+fn sparkline(values: &[f64]) -> String {
+  let most = values.iter().cloned().fold(0.0, f64::max);
+
+  values
+    .iter()
+    .map(|value| {
+      if most > 0.0 {
+        SPARKS[((value / most) * (SPARKS.len() - 1) as f64).round() as usize]
+      } else {
+        SPARKS[0]
+      }
+    })
+    .collect()
+}
+
+/// Draw a bar as long as `value` is next to `most`, which fills the whole width.
+fn bar(value: f64, most: f64) -> String {
+  if most <= 0.0 {
+    return String::new();
+  }
+
+  "█".repeat(((value / most) * BAR_WIDTH as f64).round() as usize)
+}
+
+/// Lay out `rows` of a name, a figure, and a bar in lined up columns, indented under a heading.
+fn table(rows: &[(String, String, String)]) -> String {
+  let name_width = rows.iter().map(|(name, _, _)| terminal::display_width(name)).max().unwrap_or(0);
+  let figure_width = rows.iter().map(|(_, figure, _)| figure.len()).max().unwrap_or(0);
+
+  rows
+    .iter()
+    .map(|(name, figure, bar)| {
+      let line = format!(
+        "  {}{}  {:>width$}  {}",
+        name,
+        " ".repeat(name_width - terminal::display_width(name)),
+        figure,
+        bar,
+        width = figure_width
+      );
+
+      format!("{}\n", line.trim_end())
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+
+  use super::*;
+
+  #[test]
+  fn summary() {
+    let session = |day: u32, minutes: f64, profile: Option<&str>, cancelled: bool, tag: &str| Session {
+      label: "focus".to_string(),
+      duration: minutes * 60.0,
+      started_at: Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap().timestamp() as f64,
+      finished_at: 0.0,
+      cancelled,
+      profile: profile.map(str::to_string),
+      tags: if tag.is_empty() { Vec::new() } else { vec![tag.to_string()] },
+      note: None,
+      reason: None,
+    };
+    let sessions = [
+      session(1, 25.0, Some("pomodoro"), false, "work"),
+      session(1, 25.0, Some("pomodoro"), false, ""),
+      session(3, 10.0, None, true, "chores"),
+      session(9, 60.0, None, false, "work"),
+    ];
+    let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+
+    let pomodoros = Pomodoros { today: 0, goal: None, streak: 0 };
+
+    assert_eq!(
+      text(&sessions, date(1), date(4), &pomodoros),
+      format!(
+        "Timed      1h across 3 sessions  █▁▂▁\nCompleted  2 of 3 (67%)\n\nBy profile\n  pomodoro  2  {}\n  (none)    1  {}\n\nBy tag\n  work    25m  {}\n  chores  10m  {}\n\nBy day\n  Wed 2024-05-01  50m  {}\n  Thu 2024-05-02   0s\n  Fri 2024-05-03  10m  {}\n  Sat 2024-05-04   0s\n",
+        "█".repeat(30),
+        "█".repeat(15),
+        "█".repeat(30),
+        "█".repeat(12),
+        "█".repeat(30),
+        "█".repeat(6)
+      )
+    );
+    assert_eq!(text(&sessions, date(20), date(21), &pomodoros), "No sessions in the history for this period\n");
+  }
+
+  #[test]
+  fn weekly_report() {
+    let session = |day: u32, minutes: f64, profile: Option<&str>, tags: &[&str]| Session {
+      label: "focus".to_string(),
+      duration: minutes * 60.0,
+      started_at: Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap().timestamp() as f64,
+      finished_at: 0.0,
+      cancelled: false,
+      profile: profile.map(str::to_string),
+      tags: tags.iter().map(|tag| tag.to_string()).collect(),
+      note: None,
+      reason: None,
+    };
+    let sessions = [
+      session(1, 60.0, Some("pomodoro"), &["work", "deep"]),
+      session(1, 20.0, None, &[]),
+      session(3, 20.0, Some("pomodoro"), &["chores"]),
+    ];
+    let date = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+
+    assert_eq!(
+      report(&sessions, date(1), date(3), Group::Tag, 8, false),
+      "Timed 1h40m from Wed 2024-05-01 to Fri 2024-05-03\n\n  Wed 2024-05-01  ██████▓▓  1h20m\n  Thu 2024-05-02            0s\n  Fri 2024-05-03  ▒▒        20m\n\n  █ work     1h\n  ▓ (none)  20m\n  ▒ chores  20m\n"
+    );
+    assert!(report(&sessions, date(1), date(3), Group::Profile, 8, true).contains(&format!("{}██████", terminal::ansi_rgb(230, 159, 0))));
+    assert_eq!(report(&sessions, date(20), date(21), Group::Tag, 8, false), "No sessions in the history for this period\n");
+  }
+
+  #[test]
+  fn profile_usage() {
+    let session = |profile: &str, duration: f64, started_at: f64, cancelled: bool| Session {
+      label: profile.to_string(),
+      duration,
+      started_at,
+      finished_at: started_at + duration,
+      cancelled,
+      profile: Some(profile.to_string()),
+      tags: Vec::new(),
+      note: None,
+      reason: None,
+    };
+    let sessions = [
+      session("tea", 180.0, 100.0, false),
+      session("tea", 60.0, 300.0, true),
+      session("pomodoro", 1500.0, 200.0, false),
+    ];
+
+    assert_eq!(
+      usage(&sessions, "tea"),
+      Usage {
+        runs: 2,
+        finished: 1,
+        average: 120.0,
+        last: Some(300.0),
+      }
+    );
+    assert_eq!(usage(&sessions, "pasta").runs, 0);
+  }
+
+  #[test]
+  fn pomodoro_counts() {
+    let session = |day: u32, profile: &str, cancelled: bool| Session {
+      label: profile.to_string(),
+      duration: 1500.0,
+      started_at: Local.with_ymd_and_hms(2024, 5, day, 9, 0, 0).unwrap().timestamp() as f64,
+      finished_at: 0.0,
+      cancelled,
+      profile: Some(profile.to_string()),
+      tags: Vec::new(),
+      note: None,
+      reason: None,
+    };
+    let sessions = [
+      session(1, "pomodoro", false),
+      session(1, "pomodoro", true),
+      session(1, "pomodoro", false),
+      session(2, "pomodoro", false),
+      session(2, "tea", true),
+      session(2, "pomodoro", false),
+    ];
+
+    let pomodoros = pomodoros(&sessions, "pomodoro", Some(2), NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+
+    assert_eq!(
+      pomodoros,
+      Pomodoros {
+        today: 2,
+        goal: Some(2),
+        streak: 3,
+      }
+    );
+    assert_eq!(pomodoros.badge(), "🍅 2/2 today");
+    assert_eq!(pomodoros.line(), "Pomodoros  🍅 2/2 today, 3 in a row, goal reached\n");
+  }
+}
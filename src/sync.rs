@@ -0,0 +1,450 @@
+use std::{
+  fs,
+  io::Write,
+  path::Path,
+  process::{Command, Stdio},
+};
+
+use crate::{
+  config::{self, SyncKind},
+  data_dir,
+  history::{self, Session},
+  json::{self, Value},
+  webhook,
+};
+
+/// Longest a request to a remote may take, in seconds.
+const TIMEOUT: &str = "30";
+/// File the history is kept in on a git remote.
+const GIT_FILE: &str = "history.toml";
+/// How many times to try putting the history on a WebDAV server back, when another machine changes it first.
+const ATTEMPTS: usize = 3;
+
+/// How a sync went.
+#[derive(Debug, PartialEq)]
+pub struct Synced {
+  /// Sessions brought in from the remote.
+  pub pulled: usize,
+  /// Sessions sent to the remote.
+  pub pushed: usize,
+}
+
+/// Sync the history kept in `backend` with the remote in `sync`, bringing in the sessions it has from other machines and sending it those only here.
+pub fn run(sync: &config::Sync, backend: config::Backend) -> Result<Synced, String> {
+  let local = history::load(backend);
+
+  let (synced, merged) = match kind(sync)? {
+    SyncKind::Git => with_git(&sync.remote, &local)?,
+    SyncKind::Webdav => with_webdav(sync, &local)?,
+    SyncKind::Http => with_endpoint(sync, &local)?,
+  };
+
+  // only what came in is added, rather than the history being written over, so sessions recorded meanwhile stay
+  let pulled = merged.iter().filter(|session| !local.iter().any(|kept| same(kept, session))).cloned().collect::<Vec<_>>();
+  history::add(backend, &pulled)?;
+
+  // along with notes made on the other machines to sessions here too
+  for (index, kept) in local.iter().enumerate().filter(|(_, kept)| kept.note.is_none()) {
+    if let Some(note) = merged.iter().find(|session| same(kept, session)).and_then(|session| session.note.as_deref()) {
+      history::note(backend, Some(index + 1), note)?;
+    }
+  }
+
+  Ok(synced)
+}
+
+/// Get what kind of remote `sync` is, working it out from the address for git repositories.
+fn kind(sync: &config::Sync) -> Result<SyncKind, String> {
+  match sync.kind {
+    Some(kind) => Ok(kind),
+    None if sync.remote.ends_with(".git") || sync.remote.starts_with("git@") || sync.remote.starts_with("ssh://") => Ok(SyncKind::Git),
+    None => Err(format!("Set kind under [sync] in the config to git, webdav, or http for {}", sync.remote)),
+  }
+}
+
+/// Bring `local` and `remote` together, oldest first, with a session in both only once.
+///
+/// Sessions are told apart by when they started and their label. Where both have one, the local copy wins, picking up a note or reason only the remote has.
+pub fn merge(local: &[Session], remote: &[Session]) -> Vec<Session> {
+  let mut merged = local.to_vec();
+
+  for session in remote {
+    match merged.iter_mut().find(|kept| same(kept, session)) {
+      Some(kept) => {
+        kept.note = kept.note.take().or_else(|| session.note.clone());
+        kept.reason = kept.reason.take().or_else(|| session.reason.clone());
+      }
+      None => merged.push(session.clone()),
+    }
+  }
+
+  merged.sort_by(|a, b| a.started_at.total_cmp(&b.started_at));
+  merged
+}
+
+/// Check whether `a` and `b` are the same session, kept on two machines.
+fn same(a: &Session, b: &Session) -> bool {
+  (a.started_at * 1000.0).round() == (b.started_at * 1000.0).round() && a.label == b.label
+}
+
+/// Count the sessions in `sessions` that `others` doesn't have.
+fn missing(sessions: &[Session], others: &[Session]) -> usize {
+  sessions.iter().filter(|session| !others.iter().any(|other| same(session, other))).count()
+}
+
+/// Sync `local` with the git repository at `remote`, through a clone kept in the data directory.
+fn with_git(remote: &str, local: &[Session]) -> Result<(Synced, Vec<Session>), String> {
+  let dir = data_dir().join("sync");
+
+  if !dir.join(".git").exists() {
+    fs::create_dir_all(data_dir()).map_err(|error| error.to_string())?;
+    // after --, so a remote starting with - can't pass for an option
+    git(&data_dir(), &["clone", "--quiet", "--", remote, "sync"])?;
+  } else {
+    git(&dir, &["fetch", "--quiet", "origin"])?;
+
+    // whatever didn't make it last time is in the history here still, so the remote's copy can be taken as it is
+    if git(&dir, &["rev-parse", "--verify", "--quiet", "@{upstream}"]).is_ok() {
+      git(&dir, &["reset", "--quiet", "--hard", "@{upstream}"])?;
+    }
+  }
+
+  let file = dir.join(GIT_FILE);
+  let remote_sessions = match fs::read_to_string(&file) {
+    Ok(contents) => history::from_toml(&contents).map_err(|error| format!("Failed to read {} from {}: {}", GIT_FILE, remote, error))?,
+    Err(_) => Vec::new(),
+  };
+
+  let merged = merge(local, &remote_sessions);
+  let synced = Synced {
+    pulled: missing(&remote_sessions, local),
+    pushed: missing(local, &remote_sessions),
+  };
+
+  if synced.pushed > 0 {
+    let contents = history::to_toml(merged.clone()).map_err(|error| error.to_string())?;
+    fs::write(&file, contents).map_err(|error| format!("Failed to write {}: {}", file.display(), error))?;
+
+    git(&dir, &["add", GIT_FILE])?;
+    git(
+      &dir,
+      &[
+        "-c",
+        "user.name=timr",
+        "-c",
+        "user.email=timr@localhost",
+        "commit",
+        "--quiet",
+        "--message",
+        &commit_message(synced.pushed),
+      ],
+    )?;
+    git(&dir, &["push", "--quiet", "--set-upstream", "origin", "HEAD"])?;
+  }
+
+  Ok((synced, merged))
+}
+
+/// Get the message for a commit adding `count` sessions to the remote.
+fn commit_message(count: usize) -> String {
+  match count {
+    1 => "Add 1 session".to_string(),
+    count => format!("Add {} sessions", count),
+  }
+}
+
+/// Run git in `dir` with `args`, getting what it printed or what went wrong.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+  let output = Command::new("git")
+    .arg("-C")
+    .arg(dir)
+    .args(args)
+    .stdin(Stdio::null())
+    .output()
+    .map_err(|error| format!("Failed to run git: {}", error))?;
+
+  if output.status.success() {
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  } else {
+    Err(format!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()))
+  }
+}
+
+/// Sync `local` with the history file on a WebDAV server, fetching it and putting back the two brought together.
+///
+/// The file is only put back if it hasn't changed since it was fetched, starting over when another machine got there first.
+fn with_webdav(sync: &config::Sync, local: &[Session]) -> Result<(Synced, Vec<Session>), String> {
+  for _ in 0..ATTEMPTS {
+    let fetched = curl(sync, &[], None)?;
+
+    let remote_sessions = match fetched.status {
+      200 => history::from_toml(&fetched.body).map_err(|error| format!("Failed to read the history from {}: {}", sync.remote, error))?,
+      // nothing there yet, for the first machine to sync
+      404 => Vec::new(),
+      status => return Err(format!("{} answered with {}", sync.remote, status)),
+    };
+
+    let merged = merge(local, &remote_sessions);
+    let synced = Synced {
+      pulled: missing(&remote_sessions, local),
+      pushed: missing(local, &remote_sessions),
+    };
+
+    if synced.pushed == 0 {
+      return Ok((synced, merged));
+    }
+
+    let contents = history::to_toml(merged.clone()).map_err(|error| error.to_string())?;
+    let condition = match &fetched.etag {
+      Some(etag) => format!("If-Match: {}", etag),
+      None => "If-None-Match: *".to_string(),
+    };
+
+    match curl(
+      sync,
+      &[("request", "PUT"), ("header", "Content-Type: application/toml"), ("header", &condition)],
+      Some(&contents),
+    )?
+    .status
+    {
+      200..=299 => return Ok((synced, merged)),
+      // changed by another machine since it was fetched
+      412 => continue,
+      status => return Err(format!("{} answered with {} to putting the history back", sync.remote, status)),
+    }
+  }
+
+  Err(format!("{} kept changing while putting the history back, try syncing again", sync.remote))
+}
+
+/// Sync `local` with an endpoint that lists every session it has as a JSON array, sending it each one it doesn't have.
+fn with_endpoint(sync: &config::Sync, local: &[Session]) -> Result<(Synced, Vec<Session>), String> {
+  let fetched = curl(sync, &[], None)?;
+
+  let remote_sessions = match fetched.status {
+    200 => match json::parse(&fetched.body) {
+      Ok(Value::Array(sessions)) => sessions.iter().filter_map(from_json).collect::<Vec<_>>(),
+      _ => return Err(format!("Expected a JSON array of sessions from {}", sync.remote)),
+    },
+    status => return Err(format!("{} answered with {}", sync.remote, status)),
+  };
+
+  let mut pushed = 0;
+
+  for session in local.iter().filter(|session| !remote_sessions.iter().any(|other| same(session, other))) {
+    match curl(
+      sync,
+      &[("request", "POST"), ("header", "Content-Type: application/json")],
+      Some(&to_json(session).to_string()),
+    )?
+    .status
+    {
+      200..=299 => pushed += 1,
+      status => return Err(format!("{} answered with {} to {}, after sending {}", sync.remote, status, session.label, pushed)),
+    }
+  }
+
+  let synced = Synced {
+    pulled: missing(&remote_sessions, local),
+    pushed,
+  };
+
+  Ok((synced, merge(local, &remote_sessions)))
+}
+
+/// What the remote answered a request with.
+#[derive(Debug, PartialEq)]
+struct Response {
+  status: u16,
+  /// Version of what was fetched, for putting it back only if it hasn't changed.
+  etag: Option<String>,
+  body: String,
+}
+
+/// Send a request to the remote in `sync` with `options` and `body`, signed in as the config says, getting what it answered.
+///
+/// The password, token, and body go to curl as a config on its stdin rather than as arguments anyone can see in the process list.
+fn curl(sync: &config::Sync, options: &[(&str, &str)], body: Option<&str>) -> Result<Response, String> {
+  let mut options = options.to_vec();
+
+  let user = sync
+    .username
+    .as_ref()
+    .map(|username| format!("{}:{}", username, sync.password.as_deref().unwrap_or_default()));
+  let authorization = sync.token.as_ref().map(|token| format!("Authorization: Bearer {}", token));
+
+  if let Some(user) = &user {
+    options.push(("user", user));
+  }
+
+  if let Some(authorization) = &authorization {
+    options.push(("header", authorization));
+  }
+
+  if let Some(body) = body {
+    options.push(("data-raw", body));
+  }
+
+  let output = Command::new("curl")
+    .args([
+      "--silent",
+      "--show-error",
+      "--location",
+      "--max-time",
+      TIMEOUT,
+      "--write-out",
+      "\n%header{etag}\n%{http_code}",
+      "--config",
+      "-",
+    ])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .and_then(|mut child| {
+      child.stdin.take().unwrap().write_all(webhook::curl_config(&sync.remote, &options).as_bytes())?;
+      child.wait_with_output()
+    })
+    .map_err(|error| format!("Failed to run curl: {}", error))?;
+
+  if !output.status.success() {
+    return Err(format!("Failed to reach {}: {}", sync.remote, String::from_utf8_lossy(&output.stderr).trim()));
+  }
+
+  Ok(response(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Get the response curl wrote to `output`, with the ETag and then the status after the body on lines of their own.
+fn response(output: &str) -> Response {
+  let (rest, status) = output.rsplit_once('\n').unwrap_or(("", output));
+  let (body, etag) = rest.rsplit_once('\n').unwrap_or(("", rest));
+
+  Response {
+    status: status.trim().parse().unwrap_or_default(),
+    etag: Some(etag.trim()).filter(|etag| !etag.is_empty()).map(str::to_string),
+    body: body.to_string(),
+  }
+}
+
+/// Describe `session` for the endpoint, as an object with the same fields the history keeps.
+fn to_json(session: &Session) -> Value {
+  let optional = |value: &Option<String>| value.as_deref().map_or(Value::Null, Value::from);
+
+  Value::object([
+    ("label", session.label.as_str().into()),
+    ("duration", session.duration.into()),
+    ("started_at", session.started_at.into()),
+    ("finished_at", session.finished_at.into()),
+    ("cancelled", session.cancelled.into()),
+    ("profile", optional(&session.profile)),
+    ("tags", Value::Array(session.tags.iter().map(|tag| tag.as_str().into()).collect())),
+    ("note", optional(&session.note)),
+    ("reason", optional(&session.reason)),
+  ])
+}
+
+/// Get the session the endpoint `described`, or nothing if it isn't one.
+fn from_json(described: &Value) -> Option<Session> {
+  let text = |key| described.get(key).and_then(Value::as_str).map(str::to_string);
+
+  Some(Session {
+    label: text("label")?,
+    duration: described.get("duration").and_then(Value::as_f64)?,
+    started_at: described.get("started_at").and_then(Value::as_f64)?,
+    finished_at: described.get("finished_at").and_then(Value::as_f64)?,
+    cancelled: described.get("cancelled") == Some(&Value::Bool(true)),
+    profile: text("profile"),
+    tags: match described.get("tags") {
+      Some(Value::Array(tags)) => tags.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+      _ => Vec::new(),
+    },
+    note: text("note"),
+    reason: text("reason"),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn session(label: &str, started_at: f64) -> Session {
+    Session {
+      label: label.to_string(),
+      duration: 1500.0,
+      started_at,
+      finished_at: started_at + 1500.0,
+      cancelled: false,
+      profile: None,
+      tags: vec!["work".to_string()],
+      note: None,
+      reason: None,
+    }
+  }
+
+  #[test]
+  fn merging() {
+    let mut noted = session("focus", 200.0);
+    noted.note = Some("outline".to_string());
+
+    let local = [session("focus", 200.0), session("tea", 400.0)];
+    let remote = [session("laundry", 100.0), noted, session("tea", 300.0)];
+
+    let merged = merge(&local, &remote);
+
+    assert_eq!(
+      merged.iter().map(|session| (session.label.as_str(), session.started_at)).collect::<Vec<_>>(),
+      [("laundry", 100.0), ("focus", 200.0), ("tea", 300.0), ("tea", 400.0)]
+    );
+    assert_eq!(merged[1].note.as_deref(), Some("outline"));
+    assert_eq!(missing(&local, &remote), 1);
+    assert_eq!(missing(&remote, &local), 2);
+  }
+
+  #[test]
+  fn endpoint_sessions() {
+    let mut cancelled = session("focus", 1714564800.5);
+    cancelled.cancelled = true;
+    cancelled.reason = Some("a call".to_string());
+
+    let described = to_json(&cancelled);
+    let read = from_json(&json::parse(&described.to_string()).unwrap()).unwrap();
+
+    assert!(same(&read, &cancelled));
+    assert_eq!(read.tags, ["work"]);
+    assert_eq!(read.reason.as_deref(), Some("a call"));
+    assert_eq!(from_json(&Value::object([("label", "tea".into())])).map(|session| session.label), None);
+  }
+
+  #[test]
+  fn kinds() {
+    let sync = |remote: &str| config::Sync {
+      remote: remote.to_string(),
+      kind: None,
+      username: None,
+      password: None,
+      token: None,
+    };
+
+    assert_eq!(kind(&sync("git@github.com:me/history.git")), Ok(SyncKind::Git));
+    assert_eq!(kind(&sync("https://example.com/history.git")), Ok(SyncKind::Git));
+    assert!(kind(&sync("https://dav.example.com/timr/history.toml")).is_err());
+  }
+
+  #[test]
+  fn responses() {
+    let fetched = response("[[sessions]]\nlabel = \"tea\"\n\n\"abc\"\n200");
+    assert_eq!(fetched.status, 200);
+    assert_eq!(fetched.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(fetched.body, "[[sessions]]\nlabel = \"tea\"\n");
+
+    let missing = response("\n\n404");
+    assert_eq!(
+      missing,
+      Response {
+        status: 404,
+        etag: None,
+        body: String::new()
+      }
+    );
+  }
+}
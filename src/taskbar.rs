@@ -0,0 +1,88 @@
+/// How the taskbar button shows progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+  Normal,
+  Paused,
+  Error,
+}
+
+/// Progress on the console window's taskbar button, cleared when dropped.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Taskbar {
+  #[cfg(windows)]
+  list: windows::Win32::UI::Shell::ITaskbarList3,
+  #[cfg(windows)]
+  window: windows::Win32::Foundation::HWND,
+}
+
+/// Get the taskbar button of the console window timr runs in, for hosts like conhost that ignore the progress escape.
+#[cfg(windows)]
+pub fn connect() -> Option<Taskbar> {
+  use windows::Win32::{
+    System::{
+      Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED},
+      Console::GetConsoleWindow,
+    },
+    UI::Shell::{ITaskbarList3, TaskbarList},
+  };
+
+  let window = unsafe { GetConsoleWindow() };
+
+  // no console window when output is redirected or timr runs detached
+  if window.is_invalid() {
+    return None;
+  }
+
+  let result = (|| -> windows::core::Result<Taskbar> {
+    unsafe {
+      // already being initialized on this thread is fine too
+      let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+      let list: ITaskbarList3 = CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)?;
+      list.HrInit()?;
+
+      Ok(Taskbar { list, window })
+    }
+  })();
+
+  result.ok()
+}
+
+/// Get the taskbar button of the console window, which only exists on Windows.
+#[cfg(not(windows))]
+pub fn connect() -> Option<Taskbar> {
+  None
+}
+
+impl Taskbar {
+  /// Show `percent` progress in `state`.
+  #[cfg(windows)]
+  pub fn set(&self, percent: u32, state: State) {
+    use windows::Win32::UI::Shell::{TBPF_ERROR, TBPF_NORMAL, TBPF_PAUSED};
+
+    let flag = match state {
+      State::Normal => TBPF_NORMAL,
+      State::Paused => TBPF_PAUSED,
+      State::Error => TBPF_ERROR,
+    };
+
+    // a failure only means the button doesn't update, which isn't worth interrupting the timer over
+    unsafe {
+      let _ = self.list.SetProgressState(self.window, flag);
+      let _ = self.list.SetProgressValue(self.window, percent.min(100) as u64, 100);
+    }
+  }
+
+  /// Show `percent` progress in `state`.
+  #[cfg(not(windows))]
+  pub fn set(&self, _percent: u32, _state: State) {}
+}
+
+impl Drop for Taskbar {
+  fn drop(&mut self) {
+    #[cfg(windows)]
+    unsafe {
+      let _ = self.list.SetProgressState(self.window, windows::Win32::UI::Shell::TBPF_NOPROGRESS);
+    }
+  }
+}
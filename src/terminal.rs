@@ -1,47 +1,406 @@
-use std::io::{stdout, Write};
+use std::{
+  env,
+  io::{stdout, Write},
+  sync::OnceLock,
+  thread::{self, JoinHandle},
+  time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use unicode_width::UnicodeWidthChar;
+
+pub mod graphics;
+pub mod input;
 
 // ANSI codes
 const ESCAPE: char = 27 as char;
 const ALERT: char = 7 as char;
 
-/// Move cursor to beginning of the previous line.
-pub fn previous_line() {
-  print!("{ESCAPE}[F");
+const BELL_STEP: Duration = Duration::from_millis(150); // length of each ring or pause in a bell pattern
+
+/// Write `output` to stdout in a single call and flush it, so a frame is never shown half drawn.
+pub fn write(output: &str) {
+  let mut stdout = stdout().lock();
+
+  stdout.write_all(output.as_bytes()).unwrap();
+  stdout.flush().unwrap();
+}
+
+/// Get the ANSI code to move the cursor to the beginning of the previous line.
+pub fn previous_line() -> String {
+  format!("{ESCAPE}[F")
+}
+
+/// Get the ANSI code to move to the beginning of the line and erase it.
+pub fn clear_line() -> String {
+  format!("\r{ESCAPE}[2K")
+}
+
+/// Get the ANSI code to move the cursor up `rows`, staying in the same column.
+pub fn cursor_up(rows: u16) -> String {
+  format!("{ESCAPE}[{rows}A")
 }
 
-/// Clear the current line of all characters.
-pub fn clear_line() {
-  // move the cursor to the beginning of the line
-  print!("\r");
+/// Get the ANSI code to erase from the cursor to the end of the line.
+pub fn clear_to_end_of_line() -> String {
+  format!("{ESCAPE}[0K")
+}
+
+/// Get the ANSI code to erase from the cursor to the end of the screen.
+pub fn clear_below() -> String {
+  format!("{ESCAPE}[0J")
+}
+
+/// Get the ANSI code to save the cursor position.
+pub fn save_cursor() -> String {
+  format!("{ESCAPE}7")
+}
 
-  // print whitespace characters to clear the line
-  for _ in 0..get_width() {
-    print!(" ");
+/// Get the ANSI code to restore the cursor to the last saved position.
+pub fn restore_cursor() -> String {
+  format!("{ESCAPE}8")
+}
+
+/// Get the ANSI code to move the cursor to `row` and `column` (1-based).
+pub fn move_to(row: u16, column: u16) -> String {
+  format!("{ESCAPE}[{row};{column}H")
+}
+
+/// Get the ANSI code to restrict scrolling to the rows from `top` to `bottom` (1-based), or the whole screen with `None`.
+///
+/// Note this moves the cursor to the top left of the screen.
+pub fn scroll_region(region: Option<(u16, u16)>) -> String {
+  match region {
+    Some((top, bottom)) => format!("{ESCAPE}[{top};{bottom}r"),
+    None => format!("{ESCAPE}[r"),
   }
+}
 
-  // reset back to beginning of line
-  print!("\r");
+/// Redraws a multi-line frame in place, anchored at the line the cursor was on when first drawn.
+#[derive(Default)]
+pub struct Canvas {
+  /// Lines reserved below the anchor, including the anchor line itself.
+  height: usize,
+  /// Whether the frame is pinned to the bottom rows of the screen, outside the scrolling region.
+  pinned: bool,
+  /// Terminal rows the scrolling region was last set up for, when pinned.
+  rows: u16,
+  /// Whether the frame takes over the whole screen, on the alternate screen so the scrollback is left alone.
+  fullscreen: bool,
+}
+
+impl Canvas {
+  /// Create a canvas that keeps the frame on the bottom rows of the screen while anything else printed scrolls above it.
+  pub fn pinned() -> Self {
+    Self { pinned: true, ..Self::default() }
+  }
+
+  /// Create a canvas that takes over the whole screen, with each line drawn at its own row from the top.
+  pub fn fullscreen() -> Self {
+    Self {
+      fullscreen: true,
+      ..Self::default()
+    }
+  }
+
+  /// Get the output to replace the previously drawn frame with `lines`.
+  pub fn draw(&mut self, lines: &[String]) -> String {
+    if self.pinned {
+      return self.draw_pinned(lines);
+    }
+
+    if self.fullscreen {
+      return self.draw_fullscreen(lines);
+    }
+
+    let mut output = String::new();
+
+    // make room for a taller frame before saving the anchor, so scrolling the terminal can't shift it
+    if lines.len() > self.height {
+      if self.height > 0 {
+        output.push_str(&restore_cursor());
+      }
+
+      output.push('\r');
+      output.push_str(&"\n".repeat(lines.len() - 1));
+      output.push_str(&previous_line().repeat(lines.len() - 1));
+      output.push_str(&save_cursor());
+
+      self.height = lines.len();
+    }
+
+    // cut lines to the terminal width, as wrapping would throw off where the next frame is drawn
+    let width = get_width() as usize;
+    let lines = lines.iter().map(|line| truncate(line, width)).collect::<Vec<_>>();
+
+    output.push_str(&restore_cursor());
+    output.push_str(&lines.join(&format!("{}\n", clear_to_end_of_line())));
+    output.push_str(&clear_to_end_of_line());
+
+    // anything left over from a taller frame or lines wrapped by a shrinking terminal
+    output.push_str(&clear_below());
+
+    output
+  }
+
+  fn draw_pinned(&mut self, lines: &[String]) -> String {
+    let mut output = String::new();
+    let rows = get_height();
+    let height = lines.len().min(rows.saturating_sub(1) as usize) as u16;
+
+    // (re)claim the bottom rows when first drawing, or when the frame or terminal changes size
+    if height as usize != self.height || rows != self.rows {
+      // push whatever is on the bottom rows up into the scrolling region
+      output.push_str(&"\n".repeat(height as usize));
+      output.push_str(&previous_line().repeat(height as usize));
+
+      output.push_str(&save_cursor());
+      output.push_str(&scroll_region(Some((1, rows - height))));
+      output.push_str(&restore_cursor());
+
+      self.height = height as usize;
+      self.rows = rows;
+    }
+
+    output.push_str(&save_cursor());
+
+    for (i, line) in lines.iter().take(height as usize).enumerate() {
+      output.push_str(&move_to(rows - height + 1 + i as u16, 1));
+      output.push_str(&truncate(line, get_width() as usize));
+      output.push_str(&clear_to_end_of_line());
+    }
+
+    output.push_str(&restore_cursor());
+
+    output
+  }
+
+  fn draw_fullscreen(&mut self, lines: &[String]) -> String {
+    let mut output = String::new();
+
+    if self.height == 0 {
+      output.push_str(&format!("{ESCAPE}[?1049h"));
+    }
+
+    self.height = lines.len().max(1);
+
+    for (i, line) in lines.iter().take(get_height() as usize).enumerate() {
+      output.push_str(&move_to(i as u16 + 1, 1));
+      output.push_str(&truncate(line, get_width() as usize));
+      output.push_str(&clear_to_end_of_line());
+    }
+
+    output.push_str(&clear_below());
+
+    output
+  }
+
+  /// Get the output to erase the frame, leaving the cursor at its anchor.
+  pub fn clear(&mut self) -> String {
+    if self.height == 0 {
+      return String::new();
+    }
+
+    // back to the normal screen, just as it was
+    if self.fullscreen {
+      self.height = 0;
+      return format!("{ESCAPE}[?1049l");
+    }
+
+    let height = self.height as u16;
+    self.height = 0;
+
+    if !self.pinned {
+      return format!("{}{}", restore_cursor(), clear_below());
+    }
+
+    // give the rows back to the scrolling region, then wipe them
+    let mut output = format!("{}{}", save_cursor(), scroll_region(None));
+
+    for row in self.rows - height + 1..=self.rows {
+      output.push_str(&move_to(row, 1));
+      output.push_str(&clear_line());
+    }
+
+    output.push_str(&restore_cursor());
+
+    output
+  }
 }
 
 /// Enables/disables cursor visibility in the terminal.
 pub fn set_cursor_visible(visible: bool) {
+  print!("{}", cursor_visibility(visible));
+  stdout().flush().unwrap();
+}
+
+/// Get the ANSI code to show or hide the cursor.
+pub fn cursor_visibility(visible: bool) -> String {
   if visible {
-    print!("{ESCAPE}[?25h");
+    format!("{ESCAPE}[?25h")
+  } else {
+    format!("{ESCAPE}[?25l")
+  }
+}
+
+/// Enables/disables reverse video for the whole screen, swapping the foreground and background colors.
+pub fn set_reverse_video(enabled: bool) {
+  if enabled {
+    print!("{ESCAPE}[?5h");
   } else {
-    print!("{ESCAPE}[?25l");
+    print!("{ESCAPE}[?5l");
   }
 
   stdout().flush().unwrap();
 }
 
-/// Sets virtual terminal progress
-pub fn progress(progress: u32) {
-  print!("{ESCAPE}]9;4;1;{progress}{ALERT}");
+/// Rings of the terminal bell and the pauses between them, written with a `.` for each ring and a space for each pause, like `... ...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BellPattern(Vec<bool>);
+
+impl Default for BellPattern {
+  fn default() -> Self {
+    Self(vec![true])
+  }
 }
 
-/// Hide virtual terminal progress
-pub fn hide_progress() {
-  print!("{ESCAPE}]9;4;0;100{ALERT}");
+impl std::str::FromStr for BellPattern {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    if !value.contains('.') || value.chars().any(|c| c != '.' && c != ' ') {
+      return Err(format!("Invalid bell pattern: {}", value));
+    }
+
+    // trailing pauses would only hold up whatever comes after
+    Ok(Self(value.trim_end().chars().map(|c| c == '.').collect()))
+  }
+}
+
+/// Ring the terminal bell in `pattern` on a background thread, so the timer carries on meanwhile.
+pub fn ring(pattern: &BellPattern) -> JoinHandle<()> {
+  let steps = pattern.0.clone();
+
+  thread::spawn(move || {
+    for (index, ring) in steps.iter().enumerate() {
+      if *ring {
+        let mut stdout = stdout().lock();
+
+        write!(stdout, "{ALERT}").unwrap();
+        stdout.flush().unwrap();
+      }
+
+      if index + 1 < steps.len() {
+        thread::sleep(BELL_STEP);
+      }
+    }
+  })
+}
+
+/// Terminal families that report progress to the taskbar, tab, or dock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProgressProtocol {
+  /// Windows Terminal and anything else understanding `OSC 9;4` ended by a bell.
+  WindowsTerminal,
+  /// ConEmu (and Cmder) documents `OSC 9;4` ended by a string terminator.
+  ConEmu,
+  /// iTerm2 3.6+ shows `OSC 9;4` progress in the tab, ended by a string terminator.
+  ITerm2,
+}
+
+/// Detect which progress protocol the current terminal speaks from its environment.
+fn progress_protocol() -> ProgressProtocol {
+  static PROTOCOL: OnceLock<ProgressProtocol> = OnceLock::new();
+
+  *PROTOCOL.get_or_init(|| {
+    if env::var_os("ConEmuPID").is_some() || env::var("ConEmuANSI").is_ok_and(|value| value == "ON") {
+      ProgressProtocol::ConEmu
+    } else if env::var("TERM_PROGRAM").is_ok_and(|value| value == "iTerm.app") || env::var_os("ITERM_SESSION_ID").is_some() {
+      ProgressProtocol::ITerm2
+    } else {
+      ProgressProtocol::WindowsTerminal
+    }
+  })
+}
+
+/// Get the code to set the progress `state` (0 hidden, 1 normal, 2 error, 3 indeterminate, 4 paused) and `progress` (0-100).
+fn progress_sequence(state: u8, progress: u32) -> String {
+  match progress_protocol() {
+    ProgressProtocol::WindowsTerminal => format!("{ESCAPE}]9;4;{state};{progress}{ALERT}"),
+    ProgressProtocol::ConEmu | ProgressProtocol::ITerm2 => format!("{ESCAPE}]9;4;{state};{progress}{ESCAPE}\\"),
+  }
+}
+
+/// Get the code to set virtual terminal progress
+pub fn progress(progress: u32) -> String {
+  progress_sequence(1, progress)
+}
+
+/// Get the code to show virtual terminal progress as paused (yellow)
+pub fn paused_progress(progress: u32) -> String {
+  progress_sequence(4, progress)
+}
+
+/// Get the code to show virtual terminal progress as errored (red)
+pub fn error_progress(progress: u32) -> String {
+  progress_sequence(2, progress)
+}
+
+/// Get the code to hide virtual terminal progress
+pub fn hide_progress() -> String {
+  progress_sequence(0, 100)
+}
+
+/// Get the code to show `text` as a badge over the terminal, or to clear it when empty.
+///
+/// Only iTerm2 has badges, so this is empty anywhere else, including tmux or SSH inside it, which set their own `TERM_PROGRAM` or none.
+/// It stands in for a Dock badge, which only the terminal app itself can set.
+pub fn badge(text: &str) -> String {
+  if !env::var("TERM_PROGRAM").is_ok_and(|value| value == "iTerm.app") {
+    return String::new();
+  }
+
+  format!("{ESCAPE}]1337;SetBadgeFormat={}{ALERT}", STANDARD.encode(text))
+}
+
+/// Get the code asking the terminal to put `text` on the clipboard, which works over SSH where supported.
+pub fn clipboard(text: &str) -> String {
+  format!("{ESCAPE}]52;c;{}{ALERT}", STANDARD.encode(text))
+}
+
+/// Escapes terminals turn into desktop notifications of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotificationProtocol {
+  /// `OSC 9`, from iTerm2 and understood by kitty, WezTerm, Ghostty, and Windows Terminal, with only a body.
+  Osc9,
+  /// `OSC 777;notify`, from rxvt-unicode and understood by foot and VTE terminals like GNOME Terminal, with a title and body.
+  Osc777,
+}
+
+/// Detect which notification escape the current terminal understands from its environment.
+///
+/// `TERM` is passed along over SSH, so this still works when the timer runs on another machine.
+fn notification_protocol() -> NotificationProtocol {
+  let term = env::var("TERM").unwrap_or_default();
+
+  if term.starts_with("foot") || term.starts_with("rxvt") || env::var_os("VTE_VERSION").is_some() {
+    NotificationProtocol::Osc777
+  } else {
+    NotificationProtocol::Osc9
+  }
+}
+
+/// Get the code asking the terminal to show a desktop notification with `title` and `body`.
+pub fn notification(title: &str, body: &str) -> String {
+  // control characters would end the escape early, or start another
+  let clean = |text: &str| text.chars().filter(|c| !c.is_control()).collect::<String>();
+
+  match notification_protocol() {
+    NotificationProtocol::Osc9 => format!("{ESCAPE}]9;{}: {}{ALERT}", clean(title), clean(body)),
+    // semicolons separate the title from the body
+    NotificationProtocol::Osc777 => format!("{ESCAPE}]777;notify;{};{}{ALERT}", clean(title).replace(';', ","), clean(body)),
+  }
 }
 
 /// Get the ANSI code to color the foreground in `red`, `green`, `blue`.
@@ -49,7 +408,178 @@ pub fn ansi_rgb(red: u8, green: u8, blue: u8) -> String {
   format!("{ESCAPE}[38;2;{red};{green};{blue}m")
 }
 
+/// Get the ANSI code to make text bold.
+pub fn bold() -> String {
+  format!("{ESCAPE}[1m")
+}
+
+/// Get the ANSI code to make text blink.
+pub fn blink() -> String {
+  format!("{ESCAPE}[5m")
+}
+
+/// Get the ANSI code to turn off bold and blinking, leaving colors alone.
+pub fn reset_style() -> String {
+  format!("{ESCAPE}[22;25m")
+}
+
+/// Get the ANSI code to reset the foreground color to the default.
+pub fn reset_color() -> String {
+  format!("{ESCAPE}[39m")
+}
+
+/// Split `text` into pieces that are either a whole escape sequence or a single printable character.
+fn segments(text: &str) -> impl Iterator<Item = (&str, bool)> {
+  let mut rest = text;
+
+  std::iter::from_fn(move || {
+    let mut chars = rest.char_indices();
+    let (_, first) = chars.next()?;
+
+    let end = if first == ESCAPE {
+      match chars.next() {
+        // CSI, ends at the first byte in the range `@` to `~`
+        Some((_, '[')) => chars.find(|(_, c)| ('@'..='~').contains(c)).map(|(i, c)| i + c.len_utf8()),
+        // OSC, APC (graphics), and DCS (sixel) strings, ending with a bell or `ESC \`
+        Some((_, ']' | '_' | 'P')) => {
+          let mut previous = ' ';
+          chars
+            .find(|&(_, c)| {
+              let found = c == ALERT || (previous == ESCAPE && c == '\\');
+              previous = c;
+              found
+            })
+            .map(|(i, c)| i + c.len_utf8())
+        }
+        // two character sequences, like saving the cursor
+        Some((i, c)) => Some(i + c.len_utf8()),
+        None => None,
+      }
+      .unwrap_or(rest.len())
+    } else {
+      first.len_utf8()
+    };
+
+    let (segment, remaining) = rest.split_at(end);
+    rest = remaining;
+
+    Some((segment, first == ESCAPE))
+  })
+}
+
+/// Get the number of columns `text` takes up in the terminal, skipping over escape sequences and accounting for wide characters.
+pub fn display_width(text: &str) -> usize {
+  segments(text)
+    .filter(|(_, escape)| !escape)
+    .map(|(segment, _)| segment.chars().next().and_then(|c| c.width()).unwrap_or(0))
+    .sum()
+}
+
+/// Cut `text` down to at most `width` columns, keeping any escape sequences so colors are still reset.
+pub fn truncate(text: &str, width: usize) -> String {
+  let mut output = String::new();
+  let mut used = 0;
+
+  for (segment, escape) in segments(text) {
+    if !escape {
+      used += segment.chars().next().and_then(|c| c.width()).unwrap_or(0);
+
+      if used > width {
+        continue;
+      }
+    }
+
+    output.push_str(segment);
+  }
+
+  output
+}
+
+/// Where a block of lines sits horizontally in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+  #[default]
+  Left,
+  Center,
+  Right,
+}
+
+impl std::str::FromStr for Align {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value {
+      "left" => Ok(Self::Left),
+      "center" => Ok(Self::Center),
+      "right" => Ok(Self::Right),
+      _ => Err(format!("Invalid alignment: {}", value)),
+    }
+  }
+}
+
+/// Pad `lines` so the block as a whole is aligned within `width` columns, keeping the lines lined up with each other.
+pub fn align(lines: &[String], align: Align, width: usize) -> Vec<String> {
+  let block_width = lines.iter().map(|line| display_width(line)).max().unwrap_or(0);
+
+  let offset = match align {
+    Align::Left => 0,
+    Align::Center => width.saturating_sub(block_width) / 2,
+    // keep the last column free so the line doesn't wrap
+    Align::Right => width.saturating_sub(block_width + 1),
+  };
+
+  lines.iter().map(|line| format!("{}{}", " ".repeat(offset), line)).collect()
+}
+
 /// Get the terminal's column count.
 pub fn get_width() -> u16 {
   termsize::get().unwrap_or(termsize::Size { rows: 10, cols: 80 }).cols
 }
+
+/// Get the terminal's row count.
+pub fn get_height() -> u16 {
+  termsize::get().unwrap_or(termsize::Size { rows: 10, cols: 80 }).rows
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn width_skips_escapes() {
+    assert_eq!(display_width("hello"), 5);
+    assert_eq!(display_width(&format!("{}██{}", ansi_rgb(1, 2, 3), reset_color())), 2);
+    assert_eq!(display_width(&progress(50)), 0);
+    assert_eq!(display_width("タイマー"), 8);
+    assert_eq!(display_width("⏰ 5m"), 5);
+  }
+
+  #[test]
+  fn bell_patterns() {
+    assert_eq!("...  ... ".parse(), Ok(BellPattern(vec![true, true, true, false, false, true, true, true])));
+    assert_eq!(".".parse(), Ok(BellPattern::default()));
+    assert!("".parse::<BellPattern>().is_err());
+    assert!("  ".parse::<BellPattern>().is_err());
+    assert!("..-..".parse::<BellPattern>().is_err());
+  }
+
+  #[test]
+  fn align_as_block() {
+    let lines = vec!["ab".to_string(), "abcd".to_string()];
+
+    assert_eq!(align(&lines, Align::Left, 10), lines);
+    assert_eq!(align(&lines, Align::Center, 10), vec!["   ab", "   abcd"]);
+    assert_eq!(align(&lines, Align::Right, 10), vec!["     ab", "     abcd"]);
+  }
+
+  #[test]
+  fn truncate_keeps_escapes() {
+    assert_eq!(truncate("hello", 3), "hel");
+    assert_eq!(truncate("タイマー", 5), "タイ");
+    assert_eq!(
+      truncate(&format!("{}abc{}", ansi_rgb(1, 2, 3), reset_color()), 2),
+      format!("{}ab{}", ansi_rgb(1, 2, 3), reset_color())
+    );
+  }
+}
@@ -0,0 +1,201 @@
+use std::{env, sync::OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::ESCAPE;
+
+/// Largest piece of base64 data the kitty graphics protocol accepts in a single escape code.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// An RGBA image, four bytes per pixel, row by row from the top left.
+pub struct Image {
+  pub width: u32,
+  pub height: u32,
+  pub pixels: Vec<u8>,
+}
+
+impl Image {
+  /// Create a fully transparent image.
+  pub fn new(width: u32, height: u32) -> Self {
+    Self {
+      width,
+      height,
+      pixels: vec![0; (width * height * 4) as usize],
+    }
+  }
+
+  /// Set the pixel at `x`, `y` to `color` (RGBA), ignoring anything outside the image.
+  pub fn set(&mut self, x: u32, y: u32, color: [u8; 4]) {
+    if x < self.width && y < self.height {
+      let i = ((y * self.width + x) * 4) as usize;
+      self.pixels[i..i + 4].copy_from_slice(&color);
+    }
+  }
+}
+
+/// Get the size of a character cell in pixels, guessing when the terminal doesn't say.
+pub fn cell_size() -> (u32, u32) {
+  #[cfg(unix)]
+  unsafe {
+    let mut size: libc::winsize = std::mem::zeroed();
+
+    if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_xpixel > 0 && size.ws_ypixel > 0 && size.ws_col > 0 && size.ws_row > 0 {
+      return ((size.ws_xpixel / size.ws_col) as u32, (size.ws_ypixel / size.ws_row) as u32);
+    }
+  }
+
+  (10, 20)
+}
+
+/// Check whether the terminal understands the kitty graphics protocol.
+pub fn kitty_supported() -> bool {
+  static SUPPORTED: OnceLock<bool> = OnceLock::new();
+
+  *SUPPORTED.get_or_init(|| {
+    // tmux swallows graphics unless passthrough is set up, so don't count on it
+    if env::var_os("TMUX").is_some() {
+      return false;
+    }
+
+    env::var("TERM").is_ok_and(|value| value == "xterm-kitty" || value == "xterm-ghostty")
+      || env::var_os("KITTY_WINDOW_ID").is_some()
+      || env::var("TERM_PROGRAM").is_ok_and(|value| value == "WezTerm" || value == "ghostty")
+  })
+}
+
+/// Get the code to show `image` at the cursor, scaled to fill `columns` by `rows` cells, replacing any earlier image with the same `id`.
+///
+/// The cursor is left where it was, so the cells still need to be stepped over.
+pub fn kitty_image(id: u32, image: &Image, columns: u16, rows: u16) -> String {
+  let data = STANDARD.encode(&image.pixels);
+  let chunks = data.as_bytes().chunks(KITTY_CHUNK_SIZE).collect::<Vec<_>>();
+  let mut output = String::new();
+
+  for (i, chunk) in chunks.iter().enumerate() {
+    let more = (i + 1 < chunks.len()) as u8;
+    let chunk = std::str::from_utf8(chunk).unwrap();
+
+    if i == 0 {
+      output.push_str(&format!(
+        "{ESCAPE}_Ga=T,f=32,q=2,C=1,i={id},s={},v={},c={columns},r={rows},m={more};{chunk}{ESCAPE}\\",
+        image.width, image.height
+      ));
+    } else {
+      output.push_str(&format!("{ESCAPE}_Gm={more};{chunk}{ESCAPE}\\"));
+    }
+  }
+
+  output
+}
+
+/// Get the sixel code to draw `image` at the cursor, leaving transparent pixels alone.
+///
+/// Colors are rounded to a 6x6x6 cube, as sixel terminals only promise a limited number of color registers.
+pub fn sixel(image: &Image) -> String {
+  // color register of each pixel, or none where it's transparent
+  let registers = image
+    .pixels
+    .chunks(4)
+    .map(|pixel| (pixel[3] >= 128).then(|| pixel[..3].iter().fold(0, |register, &channel| register * 6 + (channel as usize * 5 + 127) / 255)))
+    .collect::<Vec<_>>();
+
+  // raster attributes: 1:1 pixel aspect ratio and the image size
+  let mut output = format!("{ESCAPE}P0;1;0q\"1;1;{};{}", image.width, image.height);
+
+  let mut used = registers.iter().flatten().copied().collect::<Vec<_>>();
+  used.sort_unstable();
+  used.dedup();
+
+  for &register in &used {
+    let percent = |channel: usize| channel * 100 / 5;
+    output.push_str(&format!(
+      "#{};2;{};{};{}",
+      register,
+      percent(register / 36),
+      percent(register / 6 % 6),
+      percent(register % 6)
+    ));
+  }
+
+  // each band of six rows is drawn one color at a time, going back to the start of the band in between
+  for top in (0..image.height).step_by(6) {
+    let band = |x: u32, register: usize| {
+      (0..6)
+        .filter(|row| top + row < image.height && registers[((top + row) * image.width + x) as usize] == Some(register))
+        .fold(0, |bits, row| bits | 1 << row)
+    };
+
+    for &register in &used {
+      let columns = (0..image.width).map(|x| band(x, register)).collect::<Vec<_>>();
+
+      if columns.iter().all(|&bits| bits == 0) {
+        continue;
+      }
+
+      output.push_str(&format!("#{}", register));
+
+      // runs of the same column are repeated with `!count`
+      for run in columns.chunk_by(|a, b| a == b) {
+        let c = (63 + run[0]) as u8 as char;
+
+        if run.len() > 3 {
+          output.push_str(&format!("!{}{}", run.len(), c));
+        } else {
+          output.push_str(&c.to_string().repeat(run.len()));
+        }
+      }
+
+      output.push('$');
+    }
+
+    output.push('-');
+  }
+
+  output.push_str(&format!("{ESCAPE}\\"));
+
+  output
+}
+
+/// Get the code to remove the image with `id` from the screen and free its data.
+pub fn kitty_delete(id: u32) -> String {
+  format!("{ESCAPE}_Ga=d,d=I,q=2,i={id}{ESCAPE}\\")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn kitty_image_chunks() {
+    let image = Image {
+      width: 64,
+      height: 32,
+      pixels: vec![255; 64 * 32 * 4],
+    };
+    let output = kitty_image(1, &image, 8, 1);
+
+    // 8 KiB of pixels is a little over 10 KiB of base64, so it takes three pieces
+    assert_eq!(output.matches(&format!("{ESCAPE}_G")).count(), 3);
+    assert!(output.contains("s=64,v=32,c=8,r=1,m=1;"));
+    assert!(output.contains(&format!("{ESCAPE}_Gm=0;")));
+    assert_eq!(super::super::display_width(&output), 0);
+  }
+
+  #[test]
+  fn sixel_run_lengths() {
+    let mut image = Image::new(8, 6);
+
+    for x in 0..8 {
+      for y in 0..6 {
+        image.set(x, y, [255, 0, 0, 255]);
+      }
+    }
+
+    let output = sixel(&image);
+
+    // pure red is register 5 * 36, all six rows of all eight columns set
+    assert!(output.contains("#180;2;100;0;0"));
+    assert!(output.contains("#180!8~$-"));
+    assert_eq!(super::super::display_width(&output), 0);
+  }
+}
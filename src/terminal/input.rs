@@ -0,0 +1,250 @@
+use std::{
+  io::{stdin, stdout, IsTerminal, Read},
+  sync::mpsc::{channel, Receiver},
+  thread,
+};
+
+/// A single keypress read from the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+  Char(char),
+  Enter,
+  Escape,
+  Backspace,
+  Up,
+  Down,
+}
+
+/// Put the terminal into raw mode and start reading keypresses on a background thread.
+///
+/// Returns `None` when stdin is not a terminal. Call [`restore`] before exiting to give the terminal back its original mode.
+pub fn listen() -> Option<Receiver<Key>> {
+  if !stdin().is_terminal() || !enable_raw_mode() {
+    return None;
+  }
+
+  let (key_tx, key_rx) = channel();
+
+  thread::spawn(move || {
+    let mut buffer = [0; 16];
+
+    loop {
+      let count = match stdin().read(&mut buffer) {
+        Ok(0) | Err(_) => return,
+        Ok(count) => count,
+      };
+
+      for key in parse_keys(&buffer[..count]) {
+        if key_tx.send(key).is_err() {
+          return; // main loop has gone away
+        }
+      }
+    }
+  });
+
+  Some(key_rx)
+}
+
+/// Ask the terminal for its background color (OSC 11), giving up if it doesn't answer quickly.
+///
+/// Must be called before [`listen`], as the answer arrives on stdin.
+#[cfg(unix)]
+pub fn query_background() -> Option<(u8, u8, u8)> {
+  if !stdin().is_terminal() || !stdout().is_terminal() {
+    return None;
+  }
+
+  let mut reply = Vec::new();
+
+  unsafe {
+    let mut original: libc::termios = std::mem::zeroed();
+
+    if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+      return None;
+    }
+
+    // reads give up after a tenth of a second without anything arriving
+    let mut mode = original;
+    mode.c_lflag &= !(libc::ICANON | libc::ECHO);
+    mode.c_cc[libc::VMIN] = 0;
+    mode.c_cc[libc::VTIME] = 1;
+
+    if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode) != 0 {
+      return None;
+    }
+
+    super::write("\x1b]11;?\x07");
+
+    // the reply ends with a bell or `ESC \`
+    let mut byte = 0u8;
+    while libc::read(libc::STDIN_FILENO, &mut byte as *mut u8 as *mut libc::c_void, 1) == 1 {
+      reply.push(byte);
+
+      if byte == 7 || reply.ends_with(b"\x1b\\") || reply.len() > 64 {
+        break;
+      }
+    }
+
+    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original);
+  }
+
+  parse_color_report(&String::from_utf8_lossy(&reply))
+}
+
+/// Ask the terminal for its background color, which isn't supported on this platform.
+#[cfg(not(unix))]
+pub fn query_background() -> Option<(u8, u8, u8)> {
+  None
+}
+
+/// Get the color out of a reply like `ESC ]11;rgb:ffff/ffff/ffff BEL`, where each channel has one to four hex digits.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_color_report(reply: &str) -> Option<(u8, u8, u8)> {
+  let start = reply.find("rgb:")? + 4;
+  let end = reply[start..].find(|c: char| !c.is_ascii_hexdigit() && c != '/').map_or(reply.len(), |end| start + end);
+
+  let channels = reply[start..end]
+    .split('/')
+    .map(|channel| {
+      let value = u32::from_str_radix(channel, 16).ok()?;
+      let max = 16u32.checked_pow(channel.len() as u32)? - 1;
+
+      Some((value * 255 / max) as u8)
+    })
+    .collect::<Option<Vec<_>>>()?;
+
+  match channels[..] {
+    [red, green, blue] => Some((red, green, blue)),
+    _ => None,
+  }
+}
+
+/// Turn raw bytes from the terminal into keys, ignoring anything we don't understand.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+  // escape sequences arrive together in a single read, the up and down arrows are kept and the rest dropped entirely
+  if bytes.len() > 1 && bytes[0] == 27 {
+    return match bytes {
+      b"\x1b[A" | b"\x1bOA" => vec![Key::Up],
+      b"\x1b[B" | b"\x1bOB" => vec![Key::Down],
+      _ => Vec::new(),
+    };
+  }
+
+  String::from_utf8_lossy(bytes)
+    .chars()
+    .filter_map(|character| match character {
+      '\r' | '\n' => Some(Key::Enter),
+      '\u{1b}' => Some(Key::Escape),
+      '\u{7f}' | '\u{8}' => Some(Key::Backspace),
+      character if !character.is_control() => Some(Key::Char(character)),
+      _ => None,
+    })
+    .collect()
+}
+
+#[cfg(unix)]
+static ORIGINAL_MODE: std::sync::Mutex<Option<libc::termios>> = std::sync::Mutex::new(None);
+
+/// Disable line buffering and echo so keys are delivered as soon as they are pressed.
+///
+/// Signal generation is left on so Ctrl+C still reaches the handler.
+#[cfg(unix)]
+fn enable_raw_mode() -> bool {
+  unsafe {
+    let mut mode: libc::termios = std::mem::zeroed();
+
+    if libc::tcgetattr(libc::STDIN_FILENO, &mut mode) != 0 {
+      return false;
+    }
+
+    *ORIGINAL_MODE.lock().unwrap() = Some(mode);
+
+    mode.c_lflag &= !(libc::ICANON | libc::ECHO);
+    mode.c_cc[libc::VMIN] = 1;
+    mode.c_cc[libc::VTIME] = 0;
+
+    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode) == 0
+  }
+}
+
+/// Restore the terminal mode from before [`listen`] was called.
+#[cfg(unix)]
+pub fn restore() {
+  if let Some(mode) = ORIGINAL_MODE.lock().unwrap().take() {
+    unsafe {
+      libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode);
+    }
+  }
+}
+
+#[cfg(windows)]
+static ORIGINAL_MODE: std::sync::Mutex<Option<u32>> = std::sync::Mutex::new(None);
+
+/// Disable line buffering and echo so keys are delivered as soon as they are pressed.
+///
+/// Processed input is left on so Ctrl+C still reaches the handler.
+#[cfg(windows)]
+fn enable_raw_mode() -> bool {
+  use windows::Win32::System::Console::{GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, STD_INPUT_HANDLE};
+
+  unsafe {
+    let Ok(handle) = GetStdHandle(STD_INPUT_HANDLE) else {
+      return false;
+    };
+
+    let mut mode = CONSOLE_MODE(0);
+
+    if GetConsoleMode(handle, &mut mode).is_err() {
+      return false;
+    }
+
+    *ORIGINAL_MODE.lock().unwrap() = Some(mode.0);
+
+    SetConsoleMode(handle, mode & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT)).is_ok()
+  }
+}
+
+/// Restore the terminal mode from before [`listen`] was called.
+#[cfg(windows)]
+pub fn restore() {
+  use windows::Win32::System::Console::{GetStdHandle, SetConsoleMode, CONSOLE_MODE, STD_INPUT_HANDLE};
+
+  if let Some(mode) = ORIGINAL_MODE.lock().unwrap().take() {
+    unsafe {
+      if let Ok(handle) = GetStdHandle(STD_INPUT_HANDLE) {
+        let _ = SetConsoleMode(handle, CONSOLE_MODE(mode));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_single_keys() {
+    assert_eq!(parse_keys(b"q"), vec![Key::Char('q')]);
+    assert_eq!(parse_keys(b" "), vec![Key::Char(' ')]);
+    assert_eq!(parse_keys(b"\r"), vec![Key::Enter]);
+    assert_eq!(parse_keys(&[27]), vec![Key::Escape]);
+    assert_eq!(parse_keys(&[127]), vec![Key::Backspace]);
+  }
+
+  #[test]
+  fn parse_background_reply() {
+    assert_eq!(parse_color_report("\x1b]11;rgb:ffff/ffff/ffff\x07"), Some((255, 255, 255)));
+    assert_eq!(parse_color_report("\x1b]11;rgb:1e1e/2020/3030\x1b\\"), Some((30, 32, 48)));
+    assert_eq!(parse_color_report("\x1b]11;rgb:f/8/0\x07"), Some((255, 136, 0)));
+    assert_eq!(parse_color_report(""), None);
+  }
+
+  #[test]
+  fn parse_ignores_escape_sequences() {
+    assert_eq!(parse_keys(b"\x1b[C"), vec![]);
+    assert_eq!(parse_keys(b"\x1b[15~"), vec![]);
+    assert_eq!(parse_keys(&[1]), vec![]);
+    assert_eq!(parse_keys(b"\x1b[A"), vec![Key::Up]);
+    assert_eq!(parse_keys(b"\x1b[B"), vec![Key::Down]);
+  }
+}
@@ -0,0 +1,203 @@
+use std::time::{Duration, Instant};
+
+/// One named stretch of a timer, like the work or break of a pomodoro.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+  pub name: String,
+  pub duration: Duration,
+}
+
+/// A countdown made up of one or more stages run back to back, which can be paused, extended, and skipped through.
+pub struct Timer {
+  stages: Vec<Stage>,
+  /// Time counted before the timer was last resumed (or started).
+  elapsed_before: Duration,
+  /// When the timer was last resumed (or started), `None` while paused.
+  running_since: Option<Instant>,
+}
+
+impl Timer {
+  /// Create and start a timer running through `stages` in order.
+  pub fn new(stages: Vec<Stage>) -> Self {
+    Self {
+      stages,
+      elapsed_before: Duration::ZERO,
+      running_since: Some(Instant::now()),
+    }
+  }
+
+  /// Create a timer running through `stages` that has already counted down `elapsed`, running unless `paused`.
+  pub fn resumed(stages: Vec<Stage>, elapsed: Duration, paused: bool) -> Self {
+    Self {
+      stages,
+      elapsed_before: elapsed,
+      running_since: (!paused).then(Instant::now),
+    }
+  }
+
+  /// Get the total duration of every stage.
+  pub fn duration(&self) -> Duration {
+    self.stages.iter().map(|stage| stage.duration).sum()
+  }
+
+  /// Get how much of the timer has been counted down, not including time spent paused.
+  pub fn elapsed(&self) -> Duration {
+    let elapsed = self.elapsed_before + self.running_since.map(|since| since.elapsed()).unwrap_or_default();
+
+    elapsed.min(self.duration())
+  }
+
+  pub fn remaining(&self) -> Duration {
+    self.duration() - self.elapsed()
+  }
+
+  /// Get the overall progress, from 0 to 1.
+  pub fn progress(&self) -> f64 {
+    fraction(self.elapsed(), self.duration())
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.elapsed() >= self.duration()
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.running_since.is_none()
+  }
+
+  /// Pause a running timer or resume a paused one, returning whether it is now paused.
+  pub fn toggle_pause(&mut self) -> bool {
+    match self.running_since.take() {
+      Some(since) => self.elapsed_before += since.elapsed(),
+      None => self.running_since = Some(Instant::now()),
+    }
+
+    self.is_paused()
+  }
+
+  /// Add time to the current stage.
+  pub fn extend(&mut self, by: Duration) {
+    let index = self.stage_index();
+
+    self.stages[index].duration += by;
+  }
+
+  /// Jump to the start of the next stage, or the end of the timer when on the last one.
+  pub fn skip_stage(&mut self) {
+    self.elapsed_before += self.stage_remaining();
+  }
+
+  pub fn stages(&self) -> &[Stage] {
+    &self.stages
+  }
+
+  /// Get the position of the stage currently counting down.
+  pub fn stage_index(&self) -> usize {
+    let elapsed = self.elapsed();
+    let mut end = Duration::ZERO;
+
+    for (index, stage) in self.stages.iter().enumerate() {
+      end += stage.duration;
+
+      if elapsed < end {
+        return index;
+      }
+    }
+
+    self.stages.len() - 1
+  }
+
+  pub fn stage(&self) -> &Stage {
+    &self.stages[self.stage_index()]
+  }
+
+  /// Get how far into the current stage the timer is.
+  fn stage_elapsed(&self) -> Duration {
+    let before = self.stages[..self.stage_index()].iter().map(|stage| stage.duration).sum::<Duration>();
+
+    self.elapsed() - before
+  }
+
+  pub fn stage_remaining(&self) -> Duration {
+    self.stage().duration - self.stage_elapsed()
+  }
+
+  /// Get the progress through the current stage, from 0 to 1.
+  pub fn stage_progress(&self) -> f64 {
+    fraction(self.stage_elapsed(), self.stage().duration)
+  }
+}
+
+fn fraction(part: Duration, whole: Duration) -> f64 {
+  if whole.is_zero() {
+    return 1.0;
+  }
+
+  part.as_secs_f64() / whole.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn stages() -> Vec<Stage> {
+    vec![
+      Stage {
+        name: "work".to_string(),
+        duration: Duration::from_secs(60),
+      },
+      Stage {
+        name: "break".to_string(),
+        duration: Duration::from_secs(30),
+      },
+    ]
+  }
+
+  #[test]
+  fn skip_through_stages() {
+    let mut timer = Timer::new(stages());
+    timer.toggle_pause();
+
+    assert_eq!(timer.duration(), Duration::from_secs(90));
+    assert_eq!(timer.stage().name, "work");
+
+    timer.skip_stage();
+    assert_eq!(timer.stage().name, "break");
+    assert_eq!(timer.remaining(), Duration::from_secs(30));
+    assert_eq!(timer.stage_progress(), 0.0);
+
+    timer.skip_stage();
+    assert!(timer.is_finished());
+    assert_eq!(timer.progress(), 1.0);
+  }
+
+  #[test]
+  fn extend_current_stage() {
+    let mut timer = Timer::new(stages());
+    timer.toggle_pause();
+
+    timer.extend(Duration::from_secs(60));
+    assert_eq!(timer.stages()[0].duration, Duration::from_secs(120));
+    assert_eq!(timer.remaining().as_secs_f64().round(), 150.0);
+  }
+
+  #[test]
+  fn resume_partway() {
+    let timer = Timer::resumed(stages(), Duration::from_secs(70), true);
+
+    assert_eq!(timer.stage().name, "break");
+    assert_eq!(timer.remaining(), Duration::from_secs(20));
+    assert!(timer.is_paused());
+  }
+
+  #[test]
+  fn paused_timer_stands_still() {
+    let mut timer = Timer::new(stages());
+
+    assert!(timer.toggle_pause());
+    let elapsed = timer.elapsed();
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(timer.elapsed(), elapsed);
+
+    assert!(!timer.toggle_pause());
+  }
+}
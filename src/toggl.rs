@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+
+use crate::{config, history::Session, json::Value, webhook};
+
+const API_URL: &str = "https://api.track.toggl.com/api/v9";
+
+/// Add `session` to Toggl Track as a time entry, when `toggl` has the token and workspace the API needs.
+pub fn push(toggl: &config::Toggl, session: &Session) {
+  let (Some(token), Some(workspace)) = (&toggl.api_token, toggl.workspace_id) else {
+    return;
+  };
+
+  let url = format!("{}/workspaces/{}/time_entries", API_URL, workspace);
+  let user = format!("{}:api_token", token);
+  let entry = entry(session, workspace).to_string();
+
+  webhook::send(&url, &[("user", &user), ("header", "Content-Type: application/json"), ("data-raw", &entry)]);
+}
+
+/// Get `session` as a time entry in `workspace`, labelled and tagged as it was.
+fn entry(session: &Session, workspace: u64) -> Value {
+  let start = DateTime::<Utc>::from_timestamp(session.started_at as i64, 0).unwrap_or_default();
+
+  Value::object([
+    ("created_with", env!("CARGO_PKG_NAME").into()),
+    ("description", session.label.as_str().into()),
+    ("tags", Value::Array(session.tags.iter().map(|tag| tag.as_str().into()).collect())),
+    ("start", start.format("%Y-%m-%dT%H:%M:%SZ").to_string().as_str().into()),
+    ("duration", session.duration.round().into()),
+    ("workspace_id", (workspace as f64).into()),
+  ])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn entries() {
+    let session = Session {
+      label: "focus".to_string(),
+      duration: 1499.6,
+      started_at: 1714564800.0,
+      finished_at: 1714566300.0,
+      cancelled: false,
+      profile: None,
+      tags: vec!["work".to_string()],
+      note: None,
+      reason: None,
+    };
+
+    assert_eq!(
+      entry(&session, 42).to_string(),
+      r#"{"created_with":"timr","description":"focus","tags":["work"],"start":"2024-05-01T12:00:00Z","duration":1500,"workspace_id":42}"#
+    );
+  }
+}
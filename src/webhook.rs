@@ -0,0 +1,323 @@
+use std::{
+  io::Write,
+  process::{Command, Stdio},
+  thread,
+};
+
+use chrono::{DateTime, Local};
+
+/// Something happening to a timer that webhooks and the event pipe are told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+  Start,
+  Pause,
+  Resume,
+  /// Time was added to the timer.
+  Extend,
+  /// The timer moved on to its next stage.
+  Stage,
+  Finish,
+  Cancel,
+}
+
+impl Event {
+  /// Get the name the event goes by in payloads.
+  pub fn name(self) -> &'static str {
+    match self {
+      Self::Start => "start",
+      Self::Pause => "pause",
+      Self::Resume => "resume",
+      Self::Extend => "extend",
+      Self::Stage => "stage",
+      Self::Finish => "finish",
+      Self::Cancel => "cancel",
+    }
+  }
+
+  /// Check whether webhooks are told about the event, which they only are for timers starting and ending.
+  pub fn is_posted(self) -> bool {
+    matches!(self, Self::Start | Self::Finish | Self::Cancel)
+  }
+}
+
+/// What a webhook is sent about a timer.
+pub struct Payload<'a> {
+  pub event: Event,
+  pub label: &'a str,
+  /// Name of the stage the timer is on, empty for a single duration.
+  pub stage: &'a str,
+  /// Total duration in seconds.
+  pub duration: f64,
+  /// Time remaining in seconds.
+  pub remaining: f64,
+  pub started_at: DateTime<Local>,
+  pub timestamp: DateTime<Local>,
+}
+
+impl Payload<'_> {
+  /// Write the payload as a JSON object.
+  pub fn to_json(&self) -> String {
+    format!(
+      r#"{{"event":"{}","label":{},"stage":{},"duration":{},"remaining":{},"started_at":"{}","timestamp":"{}"}}"#,
+      self.event.name(),
+      json_string(self.label),
+      json_string(self.stage),
+      self.duration,
+      self.remaining,
+      self.started_at.to_rfc3339(),
+      self.timestamp.to_rfc3339()
+    )
+  }
+
+  /// Fill in the placeholders in `template`, like `{label}` and `{status}`, leaving any other braces alone.
+  ///
+  /// The template quotes values itself, and when it's `json` they're escaped to fit inside those quotes.
+  /// Placeholders are only looked for in the template, so a label with `{status}` in it stays as it is.
+  pub fn fill(&self, template: &str, json: bool) -> String {
+    let values = [
+      ("label", self.label.to_string()),
+      ("stage", self.stage.to_string()),
+      ("status", self.event.name().to_string()),
+      ("duration", self.duration.to_string()),
+      ("remaining", self.remaining.to_string()),
+      ("started_at", self.started_at.to_rfc3339()),
+      ("ended_at", self.timestamp.to_rfc3339()),
+    ];
+
+    let mut body = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+      body.push_str(&rest[..start]);
+      rest = &rest[start + 1..];
+
+      match values.iter().find(|(name, _)| rest.strip_prefix(name).is_some_and(|after| after.starts_with('}'))) {
+        Some((name, value)) if json => {
+          let quoted = json_string(value);
+          body.push_str(&quoted[1..quoted.len() - 1]);
+          rest = &rest[name.len() + 1..];
+        }
+        Some((name, value)) => {
+          body.push_str(value);
+          rest = &rest[name.len() + 1..];
+        }
+        None => body.push('{'),
+      }
+    }
+
+    body.push_str(rest);
+    body
+  }
+}
+
+/// Get the JSON for a Slack incoming webhook posting `text`, to `channel` in place of the webhook's own if given.
+pub fn slack_json(text: &str, channel: Option<&str>) -> String {
+  match channel {
+    Some(channel) => format!(r#"{{"text":{},"channel":{}}}"#, json_string(text), json_string(channel)),
+    None => format!(r#"{{"text":{}}}"#, json_string(text)),
+  }
+}
+
+/// Get the JSON for a Discord webhook posting an embed titled `title`, with a colored bar down its side and `fields` side by side.
+pub fn discord_json(title: &str, color: u32, fields: &[(&str, String)], timestamp: DateTime<Local>) -> String {
+  let fields = fields
+    .iter()
+    .map(|(name, value)| format!(r#"{{"name":{},"value":{},"inline":true}}"#, json_string(name), json_string(value)))
+    .collect::<Vec<_>>()
+    .join(",");
+
+  format!(
+    r#"{{"embeds":[{{"title":{},"color":{},"fields":[{}],"timestamp":"{}"}}]}}"#,
+    json_string(title),
+    color,
+    fields,
+    timestamp.to_rfc3339()
+  )
+}
+
+/// POST `json` to `url` in the background with curl, which outlives timr if need be.
+///
+/// Failures are reported by curl itself, as the timer carries on either way.
+pub fn post(url: &str, json: &str) {
+  send(url, &[("header", "Content-Type: application/json"), ("data-raw", json)]);
+}
+
+/// POST `body` to `url` in the background with curl, along with `headers` like `Authorization: Bearer token`.
+///
+/// The body is sent as JSON unless the headers say otherwise.
+pub fn post_with_headers(url: &str, body: &str, headers: &[String]) {
+  let mut options = headers.iter().map(|header| ("header", header.as_str())).collect::<Vec<_>>();
+
+  if content_type(headers).is_none() {
+    options.push(("header", "Content-Type: application/json"));
+  }
+
+  options.push(("data-raw", body));
+
+  send(url, &options);
+}
+
+/// Check whether a body sent along with `headers` goes as JSON, as it does unless they give another content type.
+pub fn sends_json(headers: &[String]) -> bool {
+  content_type(headers).is_none_or(|kind| kind.to_lowercase().contains("json"))
+}
+
+/// Get the content type `headers` give, if any.
+fn content_type(headers: &[String]) -> Option<&str> {
+  headers.iter().find_map(|header| {
+    header
+      .split_once(':')
+      .filter(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+      .map(|(_, kind)| kind.trim())
+  })
+}
+
+/// Send a request to `url` in the background with curl, passing it `options` like `("header", "Tags: alarm_clock")` to say what to send.
+///
+/// The options go to curl as a config on its stdin, keeping bodies and secrets out of the process list, and `data-raw` takes a body starting with `@` as it is rather than as a file to upload.
+pub fn send(url: &str, options: &[(&str, &str)]) {
+  let result = Command::new("curl")
+    .args(["--silent", "--show-error", "--max-time", "10", "--config", "-"])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .spawn()
+    .and_then(|mut child| {
+      // the stdin is dropped once written, so curl knows it has the whole config
+      let written = child.stdin.take().unwrap().write_all(curl_config(url, options).as_bytes());
+      // waited on out of the way, so it doesn't linger as a zombie once done
+      thread::spawn(move || child.wait());
+      written
+    });
+
+  if let Err(error) = result {
+    eprintln!("Failed to run curl: {}", error);
+  }
+}
+
+/// Write `options` for a request to `url` as a curl config, one to a line with the value quoted.
+pub fn curl_config(url: &str, options: &[(&str, &str)]) -> String {
+  let quote = |value: &str| {
+    let escaped = value
+      .replace('\\', "\\\\")
+      .replace('"', "\\\"")
+      .replace('\n', "\\n")
+      .replace('\r', "\\r")
+      .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+  };
+
+  let mut config = format!("url = {}\n", quote(url));
+
+  for (name, value) in options {
+    config.push_str(&format!("{} = {}\n", name, quote(value)));
+  }
+
+  config
+}
+
+/// Quote `value` as a JSON string.
+pub fn json_string(value: &str) -> String {
+  let mut output = String::from('"');
+
+  for c in value.chars() {
+    match c {
+      '"' => output.push_str("\\\""),
+      '\\' => output.push_str("\\\\"),
+      '\n' => output.push_str("\\n"),
+      '\r' => output.push_str("\\r"),
+      '\t' => output.push_str("\\t"),
+      c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+      c => output.push(c),
+    }
+  }
+
+  output.push('"');
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn curl_configs() {
+    assert_eq!(
+      curl_config(
+        "https://example.com/hook",
+        &[("header", "Authorization: Bearer secret"), ("data-raw", "@~/.ssh/id_rsa \"quoted\"\nnext\\")]
+      ),
+      "url = \"https://example.com/hook\"\nheader = \"Authorization: Bearer secret\"\ndata-raw = \"@~/.ssh/id_rsa \\\"quoted\\\"\\nnext\\\\\"\n"
+    );
+  }
+
+  #[test]
+  fn json_strings() {
+    assert_eq!(json_string("tea"), r#""tea""#);
+    assert_eq!(json_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+    assert_eq!(json_string("\u{1b}"), r#""\u001b""#);
+  }
+
+  #[test]
+  fn slack_messages() {
+    assert_eq!(slack_json("⏰ tea timer finished", None), r#"{"text":"⏰ tea timer finished"}"#);
+    assert_eq!(slack_json("done", Some("#kitchen")), r##"{"text":"done","channel":"#kitchen"}"##);
+  }
+
+  #[test]
+  fn discord_embeds() {
+    let at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap().with_timezone(&Local);
+    let json = discord_json("tea timer finished", 0x57f287, &[("Duration", "4m0s".to_string())], at);
+
+    assert!(json.starts_with(r#"{"embeds":[{"title":"tea timer finished","color":5763719,"fields":[{"name":"Duration","value":"4m0s","inline":true}],"timestamp":""#));
+  }
+
+  #[test]
+  fn payload_json() {
+    let at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap().with_timezone(&Local);
+    let payload = Payload {
+      event: Event::Finish,
+      label: "tea",
+      stage: "",
+      duration: 240.0,
+      remaining: 0.0,
+      started_at: at,
+      timestamp: at,
+    };
+
+    let json = payload.to_json();
+
+    assert!(json.starts_with(r#"{"event":"finish","label":"tea","stage":"","duration":240,"remaining":0,"started_at":""#));
+  }
+
+  #[test]
+  fn templates() {
+    let at = DateTime::parse_from_rfc3339("2024-05-01T12:00:00+00:00").unwrap().with_timezone(&Local);
+    let payload = Payload {
+      event: Event::Cancel,
+      label: "tea",
+      stage: "",
+      duration: 240.0,
+      remaining: 30.5,
+      started_at: at,
+      timestamp: at,
+    };
+
+    assert_eq!(
+      payload.fill(r#"{"state": "{status}", "name": "{label}", "left": {remaining}, "other": "{unknown}"}"#, true),
+      r#"{"state": "cancel", "name": "tea", "left": 30.5, "other": "{unknown}"}"#
+    );
+    assert_eq!(payload.fill("{ended_at}", false), at.to_rfc3339());
+
+    // quotes in a label can't break out of the JSON, and placeholders in it are left alone
+    let quoted = Payload {
+      label: r#"say "hi" {status}"#,
+      ..payload
+    };
+    assert_eq!(quoted.fill(r#"{"name": "{label}"}"#, true), r#"{"name": "say \"hi\" {status}"}"#);
+    assert_eq!(quoted.fill("name={label}", false), r#"name=say "hi" {status}"#);
+
+    assert!(sends_json(&[]));
+    assert!(sends_json(&["content-type: application/json; charset=utf-8".to_string()]));
+    assert!(!sends_json(&["Authorization: Bearer x".to_string(), "Content-Type: text/plain".to_string()]));
+  }
+}